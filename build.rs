@@ -4,4 +4,3 @@ fn main() -> Result<()> {
     prost_build::compile_protos(&["proto/cursor.proto"], &["proto/"])?;
     Ok(())
 }
-
@@ -1,8 +1,9 @@
 /**
  * @file deragabu_agent.h
- * @brief C FFI interface for the Deragabu Agent (Rust static library).
+ * @brief C FFI interface for the Deragabu Agent (Rust static or shared library).
  *
- * Link against `libderagabu_agent.a` (macOS/Linux) or `deragabu_agent.lib` (Windows).
+ * Link against `libderagabu_agent.a` (macOS/Linux) or `deragabu_agent.lib` (Windows),
+ * or the shared `libderagabu_agent.so` / `.dylib` / `deragabu_agent.dll`.
  *
  * The agent provides:
  *   - System cursor capture at ~60 FPS (WebP encoded)
@@ -21,11 +22,25 @@
  *
  *   // On shutdown:
  *   deragabu_agent_shutdown();
+ *
+ * A host with its own transport can run cursor capture alone instead:
+ *
+ *   static void on_cursor(const deragabu_cursor_info *cursor, void *userdata) {
+ *       if (cursor == NULL) { hide_cursor(); return; }
+ *       upload_cursor(cursor->cursor_id, cursor->webp_data, cursor->webp_len,
+ *                     cursor->hotspot_x, cursor->hotspot_y);
+ *   }
+ *
+ *   deragabu_agent_start_capture(on_cursor, my_state);
+ *   ...
+ *   deragabu_agent_stop_capture();
  */
 
 #pragma once
 
 #include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
 
 #ifdef __cplusplus
 extern "C" {
@@ -74,6 +89,61 @@ void deragabu_agent_set_display_cursor(bool display);
  */
 bool deragabu_agent_is_running(void);
 
+/**
+ * A captured cursor.  All pointers are only valid during the callback;
+ * copy what you need to keep.
+ */
+typedef struct deragabu_cursor_info {
+    const char *cursor_id;       /**< NUL-terminated id, see deragabu_agent_get_cursor */
+    const uint8_t *webp_data;    /**< WebP image (static or animated) */
+    size_t webp_len;
+    uint32_t width;
+    uint32_t height;
+    int32_t hotspot_x;
+    int32_t hotspot_y;
+    bool is_animated;
+    uint32_t frame_count;
+    uint32_t frame_delay_ms;
+} deragabu_cursor_info;
+
+/**
+ * Cursor change callback.  `cursor` is NULL when the cursor was hidden.
+ * Runs on an agent thread; return quickly, capture waits for it.
+ * Do not call deragabu_agent_stop_capture from inside the callback.
+ */
+typedef void (*deragabu_cursor_callback)(const deragabu_cursor_info *cursor, void *userdata);
+
+/**
+ * Start cursor capture without the WebRTC server.  `callback` is invoked
+ * with `userdata` on every cursor change; pointer moves are not reported.
+ *
+ * Capture shares its cursor cache with the full agent, so this cannot run
+ * alongside deragabu_agent_init.
+ *
+ * @return 0 on success, -1 if capture or the agent is already running.
+ */
+int deragabu_agent_start_capture(deragabu_cursor_callback callback, void *userdata);
+
+/**
+ * Stop the capture started by deragabu_agent_start_capture.  Waits up to
+ * five seconds for a callback in progress.  No-op if not running.
+ *
+ * Must be called from a host thread, never from the cursor callback: a call
+ * made on an agent thread is ignored (the runtime cannot wait for itself).
+ */
+void deragabu_agent_stop_capture(void);
+
+/**
+ * Copy the WebP bytes of a cached cursor into `out_buf`.
+ *
+ * Nothing is copied when `out_buf` is NULL or `out_len` is smaller than the
+ * image, so pass NULL first to learn the size.  Ids stay valid while the
+ * cursor remains in the cache of recently used cursors.
+ *
+ * @return the image size in bytes, or -1 if `cursor_id` is unknown.
+ */
+int64_t deragabu_agent_get_cursor(const char *cursor_id, uint8_t *out_buf, size_t out_len);
+
 #ifdef __cplusplus
 }
 #endif
@@ -0,0 +1,43 @@
+//! Typed configuration messages sent by clients as data-channel text frames.
+
+use serde::Deserialize;
+use tracing::debug;
+
+/// Client configuration, e.g. `{"device_pixel_ratio": 2.0}` or
+/// `{"preferred_cursor_size": 32}`.  Absent fields leave the current value
+/// unchanged.
+///
+/// Unknown fields are ignored so newer clients can send extra settings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientConfig {
+    #[serde(default)]
+    pub device_pixel_ratio: Option<f32>,
+    /// Fit every cursor into a box this many pixels square instead of
+    /// sending it at capture size; 0 goes back to capture size.
+    #[serde(default)]
+    pub preferred_cursor_size: Option<u32>,
+    /// Schema version of `cursor.proto` the client was built against
+    #[serde(default)]
+    pub schema_version: Option<u32>,
+    /// The client acknowledges each `CursorData` with a `CursorAck`
+    #[serde(default)]
+    pub cursor_ack: Option<bool>,
+    /// Cursor image codecs the client decodes (`"webp"`, `"avif"`); WebP
+    /// is assumed either way
+    #[serde(default)]
+    pub cursor_codecs: Option<Vec<String>>,
+    /// `"full"` (default) or `"preview"`, which caps cursors at 24 pixels
+    /// for low-bandwidth clients (see
+    /// [`CursorQuality`](crate::cursor_capture::CursorQuality))
+    #[serde(default)]
+    pub cursor_quality: Option<String>,
+}
+
+impl ClientConfig {
+    /// Parse a text frame, returning `None` if it is not a valid config.
+    pub fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str(text)
+            .map_err(|e| debug!("Ignoring client text message: {}", e))
+            .ok()
+    }
+}
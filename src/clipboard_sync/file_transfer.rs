@@ -0,0 +1,360 @@
+//! Chunked file transfer for `ClipboardContent::Files`.
+//!
+//! A file set is announced with a `ClipboardData` (type FILES) listing the
+//! names and sizes, then each file follows as `FileTransferBegin`, a run of
+//! `FileTransferChunk`s and `FileTransferEnd` carrying the blake3 of the whole
+//! file.  This module holds the receiving side: it reassembles files into a
+//! per-set temp directory and reports when the whole set has arrived.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::ClipboardFile;
+
+/// Size of each `FileTransferChunk` payload.
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Largest single file accepted or sent.
+pub const MAX_FILE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Most files accepted in one incoming set.
+pub const MAX_SET_FILES: usize = 256;
+
+/// Largest total size accepted for one incoming set.
+pub const MAX_SET_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Root directory for received files.
+fn transfer_root() -> PathBuf {
+    std::env::temp_dir().join("deragabu_transfers")
+}
+
+/// Reduce a peer-supplied file name to a bare file name, so it cannot escape
+/// the transfer directory.
+fn sanitize_filename(name: &str) -> Option<String> {
+    let base = Path::new(&name.replace('\\', "/"))
+        .file_name()?
+        .to_string_lossy()
+        .into_owned();
+    (!base.is_empty() && base != "." && base != "..").then_some(base)
+}
+
+/// A file set announced by a `ClipboardData` message, waiting for its files.
+struct PendingSet {
+    dir: PathBuf,
+    /// Sanitized file name → declared size, for files not yet completed
+    expected: HashMap<String, u64>,
+    completed: Vec<ClipboardFile>,
+}
+
+/// A file currently being received.
+struct IncomingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    received: u64,
+    next_seq: u32,
+    hasher: blake3::Hasher,
+}
+
+/// Per-peer reassembly state for incoming file transfers.
+#[derive(Default)]
+pub struct IncomingTransfers {
+    pending: Option<PendingSet>,
+    active: HashMap<String, IncomingFile>,
+}
+
+impl IncomingTransfers {
+    /// Start expecting a new file set, abandoning any incomplete one.
+    pub fn expect(
+        &mut self,
+        content_hash: &str,
+        filenames: &[String],
+        sizes: &[u64],
+    ) -> Result<()> {
+        if let Some(old) = &self.pending {
+            warn!(
+                "Abandoning incomplete file set ({} file(s) outstanding)",
+                old.expected.len()
+            );
+        }
+        self.abandon();
+
+        if filenames.len() > MAX_SET_FILES {
+            bail!(
+                "Too many files in set ({}, limit {})",
+                filenames.len(),
+                MAX_SET_FILES
+            );
+        }
+        let mut expected = HashMap::new();
+        let mut total = 0u64;
+        for (i, name) in filenames.iter().enumerate() {
+            let name =
+                sanitize_filename(name).ok_or_else(|| anyhow!("Invalid file name {:?}", name))?;
+            let size = sizes.get(i).copied().unwrap_or(0);
+            if size > MAX_FILE_SIZE {
+                bail!("File {} too large ({} bytes)", name, size);
+            }
+            total += size;
+            if total > MAX_SET_SIZE {
+                bail!("File set too large (over {} bytes)", MAX_SET_SIZE);
+            }
+            expected.insert(name, size);
+        }
+        if expected.is_empty() {
+            bail!("Empty file list");
+        }
+
+        let dir_name: String = content_hash
+            .chars()
+            .filter(|c| c.is_ascii_hexdigit())
+            .take(16)
+            .collect();
+        let dir = transfer_root().join(if dir_name.is_empty() {
+            "unnamed".into()
+        } else {
+            dir_name
+        });
+        std::fs::create_dir_all(&dir).with_context(|| format!("create {:?}", dir))?;
+
+        info!(
+            "Expecting {} file(s) from client into {:?}",
+            expected.len(),
+            dir
+        );
+        self.pending = Some(PendingSet {
+            dir,
+            expected,
+            completed: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Handle `FileTransferBegin`.  Each announced file may be received
+    /// once; a second begin for a file in progress or done is rejected.
+    pub fn begin(&mut self, transfer_id: &str, filename: &str, size: u64) -> Result<()> {
+        let set = self
+            .pending
+            .as_ref()
+            .ok_or_else(|| anyhow!("No file set announced"))?;
+        let name = sanitize_filename(filename)
+            .ok_or_else(|| anyhow!("Invalid file name {:?}", filename))?;
+        if self.active.contains_key(transfer_id) {
+            bail!("Transfer {} already started", transfer_id);
+        }
+        let path = set.dir.join(&name);
+        if self.active.values().any(|f| f.path == path) {
+            bail!("File {:?} is already being received", name);
+        }
+        if set.completed.iter().any(|f| f.path == path) {
+            bail!("File {:?} was already received", name);
+        }
+        let declared = *set
+            .expected
+            .get(&name)
+            .ok_or_else(|| anyhow!("Unexpected file {:?}", name))?;
+        if size != declared {
+            bail!(
+                "File {} size {} differs from announced {}",
+                name,
+                size,
+                declared
+            );
+        }
+
+        let file = File::create(&path).with_context(|| format!("create {:?}", path))?;
+        debug!(
+            "Receiving {:?} ({} bytes, transfer {})",
+            path, size, transfer_id
+        );
+        self.active.insert(
+            transfer_id.to_string(),
+            IncomingFile {
+                path,
+                file,
+                size,
+                received: 0,
+                next_seq: 0,
+                hasher: blake3::Hasher::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Handle `FileTransferChunk`.  Chunks must arrive in order (the data
+    /// channel is reliable and ordered).
+    pub fn chunk(&mut self, transfer_id: &str, seq: u32, data: &[u8]) -> Result<()> {
+        let incoming = self
+            .active
+            .get_mut(transfer_id)
+            .ok_or_else(|| anyhow!("Unknown transfer {}", transfer_id))?;
+        if seq != incoming.next_seq {
+            self.abandon();
+            bail!("Transfer {} chunk {} out of order", transfer_id, seq);
+        }
+        if incoming.received + data.len() as u64 > incoming.size {
+            self.abandon();
+            bail!("Transfer {} exceeds announced size", transfer_id);
+        }
+
+        if let Err(e) = incoming.file.write_all(data) {
+            self.abandon();
+            return Err(e).with_context(|| format!("write transfer {}", transfer_id));
+        }
+        incoming.hasher.update(data);
+        incoming.received += data.len() as u64;
+        incoming.next_seq += 1;
+        Ok(())
+    }
+
+    /// Handle `FileTransferEnd`: verify size and hash.  Returns the complete
+    /// file list once every file of the set has arrived.
+    pub fn end(
+        &mut self,
+        transfer_id: &str,
+        blake3_hex: &str,
+    ) -> Result<Option<Vec<ClipboardFile>>> {
+        let mut incoming = self
+            .active
+            .remove(transfer_id)
+            .ok_or_else(|| anyhow!("Unknown transfer {}", transfer_id))?;
+        let flushed = incoming.file.flush();
+        drop(incoming.file);
+
+        if let Err(e) = flushed {
+            self.abandon();
+            return Err(e).with_context(|| format!("write transfer {}", transfer_id));
+        }
+        if incoming.received != incoming.size {
+            self.abandon();
+            bail!(
+                "Transfer {} incomplete ({} of {} bytes)",
+                transfer_id,
+                incoming.received,
+                incoming.size
+            );
+        }
+        let actual = incoming.hasher.finalize().to_hex().to_string();
+        if !actual.eq_ignore_ascii_case(blake3_hex) {
+            self.abandon();
+            bail!("Transfer {} blake3 mismatch", transfer_id);
+        }
+
+        let Some(set) = self.pending.as_mut() else {
+            return Ok(None);
+        };
+        let name = incoming
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        set.expected.remove(&name);
+        set.completed.push(ClipboardFile {
+            path: incoming.path,
+            size: incoming.size,
+        });
+        debug!(
+            "Transfer {} complete, {} file(s) outstanding",
+            transfer_id,
+            set.expected.len()
+        );
+
+        if set.expected.is_empty() {
+            let set = self.pending.take().unwrap();
+            info!(
+                "Received {} file(s) from client into {:?}",
+                set.completed.len(),
+                set.dir
+            );
+            return Ok(Some(set.completed));
+        }
+        Ok(None)
+    }
+
+    /// Drop the pending set, if any: close its open files and delete its
+    /// directory.  A set that failed is not retried piecemeal; the peer has
+    /// to announce it again.
+    fn abandon(&mut self) {
+        self.active.clear();
+        if let Some(set) = self.pending.take() {
+            if let Err(e) = std::fs::remove_dir_all(&set.dir) {
+                debug!("Failed to remove {:?}: {}", set.dir, e);
+            }
+        }
+    }
+}
+
+impl Drop for IncomingTransfers {
+    /// A peer that disconnects mid-set leaves nothing behind.
+    fn drop(&mut self) {
+        self.abandon();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn set_caps_are_enforced() {
+        let mut transfers = IncomingTransfers::default();
+        let many: Vec<String> = (0..=MAX_SET_FILES).map(|i| format!("f{}", i)).collect();
+        assert!(transfers.expect("a1", &many, &vec![1; many.len()]).is_err());
+
+        let sizes = [MAX_FILE_SIZE; 5];
+        assert!(transfers
+            .expect("a2", &names(&["1", "2", "3", "4", "5"]), &sizes)
+            .is_err());
+    }
+
+    #[test]
+    fn second_begin_for_a_file_is_rejected() {
+        let mut transfers = IncomingTransfers::default();
+        transfers
+            .expect("b1", &names(&["x.txt", "y.txt"]), &[3, 1])
+            .unwrap();
+
+        transfers.begin("t0", "x.txt", 3).unwrap();
+        assert!(
+            transfers.begin("t1", "x.txt", 3).is_err(),
+            "file in progress"
+        );
+        assert!(
+            transfers.begin("t0", "y.txt", 1).is_err(),
+            "transfer id in use"
+        );
+
+        transfers.chunk("t0", 0, b"abc").unwrap();
+        let hash = blake3::hash(b"abc").to_hex().to_string();
+        assert!(transfers.end("t0", &hash).unwrap().is_none());
+        assert!(
+            transfers.begin("t2", "x.txt", 3).is_err(),
+            "file already received"
+        );
+    }
+
+    #[test]
+    fn failed_set_removes_its_directory() {
+        let mut transfers = IncomingTransfers::default();
+        transfers.expect("c1", &names(&["z.bin"]), &[2]).unwrap();
+        let dir = transfer_root().join("c1");
+        assert!(dir.is_dir());
+
+        transfers.begin("t0", "z.bin", 2).unwrap();
+        assert!(transfers.chunk("t0", 1, b"z").is_err(), "out of order");
+        assert!(!dir.exists());
+
+        transfers.expect("c2", &names(&["z.bin"]), &[2]).unwrap();
+        let dir = transfer_root().join("c2");
+        drop(transfers);
+        assert!(!dir.exists(), "abandoned on drop");
+    }
+}
@@ -1,24 +1,94 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
 use tokio::sync::mpsc;
 use tokio::time::{interval, Duration};
 use tracing::{debug, info, warn};
 
+pub mod file_transfer;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows::{clipboard_updated, read_rtf, spawn_clipboard_listener, write_rtf};
+
 // ── Public types ─────────────────────────────────────────────────────────────
 
-/// Clipboard content variants.  Images are transmitted as raw PNG bytes
-/// (no additional compression) so the receiver always sees the original format.
+/// Clipboard content variants.  Images are transmitted as PNG by default, or
+/// as WebP with `CLIPBOARD_IMAGE_FORMAT=webp`.
 #[derive(Debug, Clone)]
 pub enum ClipboardContent {
     Text(String),
+    /// Rich text as HTML, with the plain-text representation alongside so
+    /// clients can pick either.
+    Html {
+        html: String,
+        text: String,
+    },
+    /// Rich text as RTF, with its plain-text representation.
+    Rtf {
+        rtf: String,
+        text: String,
+    },
     Image {
-        /// Raw PNG-encoded bytes (lossless, original format)
-        png_data: Vec<u8>,
+        /// Encoded image bytes, in `format`
+        data: Vec<u8>,
+        format: ImageFormat,
         width: u32,
         height: u32,
     },
-    /// File list stub — only metadata is sent; actual file bytes are not
-    /// transferred until chunked-file-transfer is implemented.
-    Files(Vec<String>),
+    /// File list.  Names and sizes travel in `ClipboardData`; the bytes follow
+    /// as a chunked transfer (see [`file_transfer`]).
+    Files(Vec<ClipboardFile>),
+}
+
+/// Encoding of a clipboard image payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Webp,
+}
+
+impl ClipboardContent {
+    /// Bytes this content adds to a `ClipboardData` message.  File lists
+    /// count as empty: their bytes travel as separate chunked transfers.
+    pub fn payload_len(&self) -> usize {
+        match self {
+            ClipboardContent::Text(text) => text.len(),
+            ClipboardContent::Html { html, text } => html.len() + text.len(),
+            ClipboardContent::Rtf { rtf, text } => rtf.len() + text.len(),
+            ClipboardContent::Image { data, .. } => data.len(),
+            ClipboardContent::Files(_) => 0,
+        }
+    }
+}
+
+/// A file on the local filesystem referenced by the clipboard.
+#[derive(Debug, Clone)]
+pub struct ClipboardFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl ClipboardFile {
+    /// File name without directory, as sent to peers.
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Dedup hash for a file list: paths and sizes, not contents.
+pub fn files_hash(files: &[ClipboardFile]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for f in files {
+        hasher.update(f.path.to_string_lossy().as_bytes());
+        hasher.update(&f.size.to_le_bytes());
+    }
+    hasher.finalize().to_hex().to_string()
 }
 
 /// An event carrying new clipboard content and its blake3 content hash.
@@ -47,21 +117,474 @@ fn get_last_set_hash() -> Option<String> {
     LAST_SET_HASH.lock().ok()?.clone()
 }
 
+// ── Client writes in progress (keeps the capture loop off half-set content) ──
+
+/// How long after a client write the capture loop still holds off.  Some
+/// platforms finish a set asynchronously (X11 hands the selection to a
+/// background thread), and Windows briefly shows an empty clipboard.
+const WRITE_SETTLE: Duration = Duration::from_millis(50);
+
+/// Client writes currently in progress.
+static WRITES_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Bumped when a client write starts and again when it ends.  A read that
+/// saw it change is stale.
+static WRITE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// When the last client write finished.
+static LAST_WRITE_END: StdMutex<Option<std::time::Instant>> = StdMutex::new(None);
+
+/// Marks a client write in progress for as long as it lives.
+struct WriteGuard;
+
+impl WriteGuard {
+    fn begin() -> Self {
+        WRITES_ACTIVE.fetch_add(1, Ordering::SeqCst);
+        WRITE_GENERATION.fetch_add(1, Ordering::SeqCst);
+        WriteGuard
+    }
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        *LAST_WRITE_END.lock().unwrap() = Some(std::time::Instant::now());
+        WRITE_GENERATION.fetch_add(1, Ordering::SeqCst);
+        WRITES_ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// How long the capture loop should wait before reading: while a write is
+/// in progress, or until [`WRITE_SETTLE`] has passed since the last one.
+fn write_settle_remaining() -> Option<Duration> {
+    if WRITES_ACTIVE.load(Ordering::SeqCst) > 0 {
+        return Some(WRITE_SETTLE);
+    }
+    let end = (*LAST_WRITE_END.lock().unwrap())?;
+    WRITE_SETTLE
+        .checked_sub(end.elapsed())
+        .filter(|d| !d.is_zero())
+}
+
+/// Run `read` on a blocking thread once no client write is in flight, and
+/// again if one started or finished during the read: what it saw may be a
+/// half-written (or momentarily empty) clipboard.
+async fn read_between_writes<T, F>(read: F) -> Result<T, tokio::task::JoinError>
+where
+    F: Fn() -> T + Clone + Send + 'static,
+    T: Send + 'static,
+{
+    loop {
+        if let Some(wait) = write_settle_remaining() {
+            tokio::time::sleep(wait).await;
+            continue;
+        }
+        let generation = WRITE_GENERATION.load(Ordering::SeqCst);
+        let result = tokio::task::spawn_blocking(read.clone()).await;
+        if WRITE_GENERATION.load(Ordering::SeqCst) == generation {
+            return result;
+        }
+        debug!("Client clipboard write during read, reading again");
+    }
+}
+
+// ── Last encoded image (skips re-encoding an unchanged image) ────────────────
+
+/// blake3 of the raw RGBA (plus dimensions) of the last clipboard image, and
+/// the event built from it.  PNG encoding a large image on every poll is
+/// expensive; the raw-pixel hash is cheap by comparison.
+static LAST_IMAGE: StdMutex<Option<(blake3::Hash, ClipboardEvent)>> = StdMutex::new(None);
+
+fn image_fingerprint(width: usize, height: usize, rgba: &[u8]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&(width as u64).to_le_bytes());
+    hasher.update(&(height as u64).to_le_bytes());
+    hasher.update(rgba);
+    hasher.finalize()
+}
+
+// ── Shared clipboard handle ──────────────────────────────────────────────────
+
+/// First retry delay after `arboard::Clipboard::new()` fails; doubled on each
+/// further failure up to [`MAX_INIT_BACKOFF`].
+const INIT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_INIT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// One `arboard::Clipboard` reused by the capture loop and client writes.
+/// Creating one per call failed (and logged) on every poll when no display
+/// was reachable, and on Linux dropping it can take our own clipboard
+/// contents with it.  `arboard::Clipboard` is `Send + Sync` on every
+/// platform, so it only needs to stay off the async threads.
+struct SharedClipboard {
+    clipboard: Option<arboard::Clipboard>,
+    /// Consecutive failed creation attempts
+    failures: u32,
+    /// Earliest next creation attempt while failing
+    retry_at: Option<std::time::Instant>,
+}
+
+static CLIPBOARD: StdMutex<SharedClipboard> = StdMutex::new(SharedClipboard {
+    clipboard: None,
+    failures: 0,
+    retry_at: None,
+});
+
+/// Run `f` on the shared clipboard, creating it first if needed.  Returns
+/// `None` when the clipboard cannot be created; until the backoff expires no
+/// new attempt is made unless `retry_now` is set.  The first failure is
+/// logged as a warning and the recovery as info.
+fn with_clipboard<T>(
+    retry_now: bool,
+    f: impl FnOnce(&mut arboard::Clipboard) -> Result<T>,
+) -> Option<Result<T>> {
+    let mut shared = CLIPBOARD.lock().unwrap();
+    if shared.clipboard.is_none() {
+        let waiting = shared
+            .retry_at
+            .is_some_and(|at| std::time::Instant::now() < at);
+        if waiting && !retry_now {
+            return None;
+        }
+        match arboard::Clipboard::new() {
+            Ok(clipboard) => {
+                if shared.failures > 0 {
+                    info!(
+                        "Clipboard available again (after {} failed attempts)",
+                        shared.failures
+                    );
+                }
+                shared.failures = 0;
+                shared.retry_at = None;
+                shared.clipboard = Some(clipboard);
+            }
+            Err(e) => {
+                if shared.failures == 0 {
+                    warn!("Clipboard unavailable: {}", e);
+                } else {
+                    debug!(
+                        "Clipboard still unavailable (attempt {}): {}",
+                        shared.failures + 1,
+                        e
+                    );
+                }
+                let backoff = INIT_BACKOFF
+                    .saturating_mul(1 << shared.failures.min(6))
+                    .min(MAX_INIT_BACKOFF);
+                shared.failures += 1;
+                shared.retry_at = Some(std::time::Instant::now() + backoff);
+                return None;
+            }
+        }
+    }
+    shared.clipboard.as_mut().map(f)
+}
+
+// ── History ──────────────────────────────────────────────────────────────────
+
+/// Default number of entries kept in the clipboard history.
+const DEFAULT_HISTORY_SIZE: usize = 20;
+/// Upper bound for `CLIPBOARD_HISTORY_SIZE`.
+const MAX_HISTORY_SIZE: usize = 200;
+/// Images larger than this (encoded) are left out of the history.
+const HISTORY_MAX_IMAGE_BYTES: usize = 1024 * 1024;
+
+/// Recent host clipboard entries, newest first, unique by content hash.
+static HISTORY: StdMutex<VecDeque<ClipboardEvent>> = StdMutex::new(VecDeque::new());
+
+/// History capacity from `CLIPBOARD_HISTORY_SIZE` (default 20, at most 200;
+/// 0 disables the history).  Read once on first use.
+fn history_size() -> usize {
+    static SIZE: OnceLock<usize> = OnceLock::new();
+    *SIZE.get_or_init(|| {
+        let Ok(raw) = std::env::var("CLIPBOARD_HISTORY_SIZE") else {
+            return DEFAULT_HISTORY_SIZE;
+        };
+        match raw.trim().parse::<usize>() {
+            Ok(size) => {
+                if size > MAX_HISTORY_SIZE {
+                    warn!(
+                        "CLIPBOARD_HISTORY_SIZE={} too large, using {}",
+                        size, MAX_HISTORY_SIZE
+                    );
+                }
+                size.min(MAX_HISTORY_SIZE)
+            }
+            Err(_) => {
+                warn!(
+                    "Invalid CLIPBOARD_HISTORY_SIZE={:?}, using {}",
+                    raw, DEFAULT_HISTORY_SIZE
+                );
+                DEFAULT_HISTORY_SIZE
+            }
+        }
+    })
+}
+
+/// Add a broadcast clipboard event to the history.  Only text-like content
+/// and small images are kept; an entry copied again moves to the front
+/// instead of being duplicated.
+fn record_history(event: &ClipboardEvent) {
+    let capacity = history_size();
+    let keep = match &event.content {
+        ClipboardContent::Text(_)
+        | ClipboardContent::Html { .. }
+        | ClipboardContent::Rtf { .. } => true,
+        ClipboardContent::Image { data, .. } => data.len() <= HISTORY_MAX_IMAGE_BYTES,
+        ClipboardContent::Files(_) => false,
+    };
+    if capacity == 0 || !keep {
+        return;
+    }
+
+    let mut history = HISTORY.lock().unwrap();
+    history.retain(|e| e.content_hash != event.content_hash);
+    history.push_front(event.clone());
+    history.truncate(capacity);
+}
+
+/// The `count` most recent clipboard entries, newest first.
+pub fn history(count: usize) -> Vec<ClipboardEvent> {
+    HISTORY
+        .lock()
+        .unwrap()
+        .iter()
+        .take(count)
+        .cloned()
+        .collect()
+}
+
+// ── Configuration ────────────────────────────────────────────────────────────
+
+/// Default clipboard poll interval.
+const DEFAULT_POLL_MS: u64 = 500;
+/// Accepted range for `CLIPBOARD_POLL_MS`.
+const MIN_POLL_MS: u64 = 100;
+const MAX_POLL_MS: u64 = 60_000;
+
+/// Default largest clipboard payload broadcast to clients.
+const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// Clipboard poll interval in milliseconds, from `CLIPBOARD_POLL_MS`
+/// (default 500, clamped to 100–60000).  Read once on first use.
+fn poll_interval_ms() -> u64 {
+    static POLL_MS: OnceLock<u64> = OnceLock::new();
+    *POLL_MS.get_or_init(|| {
+        let Ok(raw) = std::env::var("CLIPBOARD_POLL_MS") else {
+            return DEFAULT_POLL_MS;
+        };
+        match raw.trim().parse::<u64>() {
+            Ok(ms) => {
+                let clamped = ms.clamp(MIN_POLL_MS, MAX_POLL_MS);
+                if clamped != ms {
+                    warn!(
+                        "CLIPBOARD_POLL_MS={} out of range, using {} ms",
+                        ms, clamped
+                    );
+                }
+                clamped
+            }
+            Err(_) => {
+                warn!(
+                    "Invalid CLIPBOARD_POLL_MS={:?}, using {} ms",
+                    raw, DEFAULT_POLL_MS
+                );
+                DEFAULT_POLL_MS
+            }
+        }
+    })
+}
+
+/// Which way clipboard content flows, from `CLIPBOARD_DIRECTION`.
+///
+/// Echo suppression (`LAST_SET_HASH`) only matters when both directions are
+/// on: content a client pushes into the host clipboard would otherwise be
+/// read back and broadcast to every client.  With `ClientToHost` the host
+/// clipboard is never read, and with `HostToClient` nothing is ever written
+/// to it, so the recorded hash is never consulted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardDirection {
+    /// Host changes go to clients and client pushes apply to the host (default)
+    Bidirectional,
+    /// Host changes go to clients; client pushes are ignored
+    HostToClient,
+    /// Client pushes apply to the host; host changes are not broadcast
+    ClientToHost,
+    /// No clipboard sync at all
+    Off,
+}
+
+impl ClipboardDirection {
+    /// Whether host clipboard changes are broadcast to clients.
+    pub fn host_to_client(self) -> bool {
+        matches!(self, Self::Bidirectional | Self::HostToClient)
+    }
+
+    /// Whether client pushes (content and files) are applied to the host.
+    pub fn client_to_host(self) -> bool {
+        matches!(self, Self::Bidirectional | Self::ClientToHost)
+    }
+}
+
+/// Clipboard direction from `CLIPBOARD_DIRECTION`: `bidirectional`
+/// (default), `host-to-client`, `client-to-host` or `off`.  Always `off`
+/// with `ENABLE_CLIPBOARD=0`.  Read once on first use.
+pub fn clipboard_direction() -> ClipboardDirection {
+    static DIRECTION: OnceLock<ClipboardDirection> = OnceLock::new();
+    *DIRECTION.get_or_init(|| {
+        if !crate::subsystem_enabled("ENABLE_CLIPBOARD") {
+            return ClipboardDirection::Off;
+        }
+        let Ok(raw) = std::env::var("CLIPBOARD_DIRECTION") else {
+            return ClipboardDirection::Bidirectional;
+        };
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "bidirectional" => ClipboardDirection::Bidirectional,
+            "host-to-client" => ClipboardDirection::HostToClient,
+            "client-to-host" => ClipboardDirection::ClientToHost,
+            "off" => ClipboardDirection::Off,
+            _ => {
+                warn!("Invalid CLIPBOARD_DIRECTION={:?}, using bidirectional", raw);
+                ClipboardDirection::Bidirectional
+            }
+        }
+    })
+}
+
+/// How host clipboard images are encoded for clients.
+#[derive(Debug, Clone, Copy)]
+enum ImageEncoding {
+    Png,
+    /// WebP, lossy at the given quality or lossless when `None`
+    Webp(Option<f32>),
+}
+
+/// Image encoding from `CLIPBOARD_IMAGE_FORMAT`: `png` (default), `webp`
+/// (lossless) or `webp:<quality>` (lossy, quality 0–100).  Read once on
+/// first use.
+fn image_encoding() -> ImageEncoding {
+    static ENCODING: OnceLock<ImageEncoding> = OnceLock::new();
+    *ENCODING.get_or_init(|| {
+        let Ok(raw) = std::env::var("CLIPBOARD_IMAGE_FORMAT") else {
+            return ImageEncoding::Png;
+        };
+        let value = raw.trim().to_ascii_lowercase();
+        let encoding = match value.split_once(':') {
+            None if value == "png" || value.is_empty() => Some(ImageEncoding::Png),
+            None if value == "webp" => Some(ImageEncoding::Webp(None)),
+            Some(("webp", q)) => q
+                .parse::<f32>()
+                .ok()
+                .filter(|q| (0.0..=100.0).contains(q))
+                .map(|q| ImageEncoding::Webp(Some(q))),
+            _ => None,
+        };
+        encoding.unwrap_or_else(|| {
+            warn!("Invalid CLIPBOARD_IMAGE_FORMAT={:?}, using png", raw);
+            ImageEncoding::Png
+        })
+    })
+}
+
+/// Largest clipboard payload (text or encoded image) that is broadcast, from
+/// `CLIPBOARD_MAX_BYTES` (default 16 MiB).  Read once on first use.
+fn max_payload_bytes() -> usize {
+    static MAX_BYTES: OnceLock<usize> = OnceLock::new();
+    *MAX_BYTES.get_or_init(|| {
+        let Ok(raw) = std::env::var("CLIPBOARD_MAX_BYTES") else {
+            return DEFAULT_MAX_BYTES;
+        };
+        match raw.trim().parse::<usize>() {
+            Ok(bytes) if bytes > 0 => bytes,
+            _ => {
+                warn!(
+                    "Invalid CLIPBOARD_MAX_BYTES={:?}, using {} bytes",
+                    raw, DEFAULT_MAX_BYTES
+                );
+                DEFAULT_MAX_BYTES
+            }
+        }
+    })
+}
+
 // ── Capture task ─────────────────────────────────────────────────────────────
 
-/// Poll the host clipboard every 500 ms and send a [`ClipboardEvent`] whenever
-/// the content changes.  Runs until the receiver end of `tx` is dropped.
+/// Watch the host clipboard and send a [`ClipboardEvent`] whenever the
+/// content changes.  On Windows the clipboard is read on each
+/// `WM_CLIPBOARDUPDATE`; elsewhere (or if the listener cannot be registered)
+/// it is polled every `CLIPBOARD_POLL_MS`.  Runs until the receiver end of
+/// `tx` is dropped or shutdown is requested.
 pub async fn run_clipboard_capture(tx: mpsc::Sender<ClipboardEvent>) -> Result<()> {
-    info!("Clipboard capture started (polling every 500 ms)");
+    let direction = clipboard_direction();
+    info!("Clipboard direction: {:?}", direction);
+    if !direction.host_to_client() {
+        // Stay alive like the other subsystems, but never read the host clipboard.
+        info!("Host clipboard is not shared with clients, clipboard capture idle");
+        crate::shutdown::requested().await;
+        return Ok(());
+    }
+
+    let poll_ms = poll_interval_ms();
+    let max_bytes = max_payload_bytes();
 
-    let mut poll = interval(Duration::from_millis(500));
+    #[cfg(target_os = "windows")]
+    let event_driven = match spawn_clipboard_listener() {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(
+                "Clipboard change listener unavailable, falling back to polling: {}",
+                e
+            );
+            false
+        }
+    };
+    #[cfg(not(target_os = "windows"))]
+    let event_driven = false;
+
+    if event_driven {
+        info!(
+            "Clipboard capture started (change notifications, max payload {} bytes)",
+            max_bytes
+        );
+    } else {
+        info!(
+            "Clipboard capture started (polling every {} ms, max payload {} bytes)",
+            poll_ms, max_bytes
+        );
+    }
+
+    let mut poll = interval(Duration::from_millis(poll_ms));
     let mut last_broadcast_hash: Option<String> = None;
+    // A busy clipboard is re-read shortly; change notifications would not
+    // fire again for the change it hid.
+    let mut busy_retries = 0u32;
 
     loop {
-        poll.tick().await;
+        tokio::select! {
+            _ = poll.tick(), if !event_driven => {}
+            _ = clipboard_updated(), if event_driven => {}
+            _ = tokio::time::sleep(BUSY_RETRY), if busy_retries > 0 => {}
+            _ = crate::shutdown::requested() => {
+                info!("Shutdown requested, stopping clipboard capture");
+                break;
+            }
+        }
 
         // arboard must be called on a non-async thread (especially on macOS).
-        let result = tokio::task::spawn_blocking(read_clipboard).await;
+        let result = read_between_writes(read_clipboard).await;
+
+        if matches!(&result, Ok(Err(e)) if e.is::<ClipboardBusy>()) {
+            busy_retries += 1;
+            if busy_retries > MAX_BUSY_RETRIES {
+                debug!(
+                    "Clipboard still busy after {} attempts, waiting for the next change",
+                    MAX_BUSY_RETRIES
+                );
+                busy_retries = 0;
+            } else {
+                debug!("Clipboard busy, skipping read (attempt {})", busy_retries);
+            }
+            continue;
+        }
+        busy_retries = 0;
 
         let event = match result {
             Ok(Ok(Some(ev))) => ev,
@@ -90,8 +613,22 @@ pub async fn run_clipboard_capture(tx: mpsc::Sender<ClipboardEvent>) -> Result<(
             continue;
         }
 
-        debug!("Clipboard changed — broadcasting (hash prefix: {}…)", &hash[..8]);
+        let size = event.content.payload_len();
+        if size > max_bytes {
+            warn!(
+                "Clipboard content too large to sync ({} bytes, limit {}), skipping",
+                size, max_bytes
+            );
+            last_broadcast_hash = Some(hash);
+            continue;
+        }
+
+        debug!(
+            "Clipboard changed — broadcasting (hash prefix: {}…)",
+            &hash[..8]
+        );
         last_broadcast_hash = Some(hash);
+        record_history(&event);
 
         if tx.send(event).await.is_err() {
             info!("Clipboard capture: receiver dropped, stopping");
@@ -104,12 +641,76 @@ pub async fn run_clipboard_capture(tx: mpsc::Sender<ClipboardEvent>) -> Result<(
 
 // ── Low-level clipboard read (sync, meant for spawn_blocking) ────────────────
 
+/// Delay before re-reading a clipboard another process held open.
+const BUSY_RETRY: Duration = Duration::from_millis(100);
+/// Re-reads of a busy clipboard before waiting for the next change.
+const MAX_BUSY_RETRIES: u32 = 10;
+
+/// Another process held the clipboard open throughout a read.
+#[derive(Debug)]
+struct ClipboardBusy;
+
+impl std::fmt::Display for ClipboardBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("clipboard held open by another process")
+    }
+}
+
+impl std::error::Error for ClipboardBusy {}
+
+/// Read the host clipboard; `Ok(None)` while it is unavailable, and a
+/// [`ClipboardBusy`] error while another process holds it open.
 fn read_clipboard() -> Result<Option<ClipboardEvent>> {
-    let mut clipboard = arboard::Clipboard::new()?;
+    with_clipboard(false, read_from).unwrap_or(Ok(None))
+}
+
+fn read_from(clipboard: &mut arboard::Clipboard) -> Result<Option<ClipboardEvent>> {
+    // Files first: file managers usually also offer the paths as plain text.
+    // On Windows arboard reads them from CF_HDROP.  A clipboard held open
+    // elsewhere fails every read, so skip rather than fall through to text.
+    let file_list = match clipboard.get().file_list() {
+        Err(arboard::Error::ClipboardOccupied) => return Err(ClipboardBusy.into()),
+        result => result,
+    };
+    if let Ok(paths) = file_list {
+        let files: Vec<ClipboardFile> = paths
+            .into_iter()
+            .filter_map(|path| {
+                let meta = std::fs::metadata(&path).ok()?;
+                meta.is_file().then_some(ClipboardFile {
+                    path,
+                    size: meta.len(),
+                })
+            })
+            .collect();
+        if !files.is_empty() {
+            let hash = files_hash(&files);
+            return Ok(Some(ClipboardEvent {
+                content: ClipboardContent::Files(files),
+                content_hash: hash,
+            }));
+        }
+    }
 
-    // Try text first (cheapest).
+    // Then text.  Rich-text copies (browsers, Word) carry plain text plus
+    // HTML and/or RTF; send the richest form along with the text and hash the
+    // rich form so formatting-only changes are still picked up.
     if let Ok(text) = clipboard.get_text() {
         if !text.is_empty() {
+            if let Some(html) = clipboard.get().html().ok().filter(|h| !h.is_empty()) {
+                let hash = blake3::hash(html.as_bytes()).to_hex().to_string();
+                return Ok(Some(ClipboardEvent {
+                    content: ClipboardContent::Html { html, text },
+                    content_hash: hash,
+                }));
+            }
+            if let Some(rtf) = read_rtf() {
+                let hash = blake3::hash(rtf.as_bytes()).to_hex().to_string();
+                return Ok(Some(ClipboardEvent {
+                    content: ClipboardContent::Rtf { rtf, text },
+                    content_hash: hash,
+                }));
+            }
             let hash = blake3::hash(text.as_bytes()).to_hex().to_string();
             return Ok(Some(ClipboardEvent {
                 content: ClipboardContent::Text(text),
@@ -120,27 +721,58 @@ fn read_clipboard() -> Result<Option<ClipboardEvent>> {
 
     // Try image.
     if let Ok(img) = clipboard.get_image() {
-        let png = encode_rgba_to_png(img.bytes.as_ref(), img.width as u32, img.height as u32)?;
-        let hash = blake3::hash(&png).to_hex().to_string();
-        return Ok(Some(ClipboardEvent {
+        let fingerprint = image_fingerprint(img.width, img.height, img.bytes.as_ref());
+
+        let mut last_image = LAST_IMAGE.lock().unwrap();
+        if let Some((last_fingerprint, event)) = last_image.as_ref() {
+            if *last_fingerprint == fingerprint {
+                return Ok(Some(event.clone()));
+            }
+        }
+
+        let (width, height) = (img.width as u32, img.height as u32);
+        let (data, format) = match image_encoding() {
+            ImageEncoding::Png => (
+                encode_rgba_to_png(img.bytes.as_ref(), width, height)?,
+                ImageFormat::Png,
+            ),
+            ImageEncoding::Webp(quality) => (
+                encode_rgba_to_webp(img.bytes.as_ref(), width, height, quality)?,
+                ImageFormat::Webp,
+            ),
+        };
+        let hash = blake3::hash(&data).to_hex().to_string();
+        let event = ClipboardEvent {
             content: ClipboardContent::Image {
-                png_data: png,
-                width: img.width as u32,
-                height: img.height as u32,
+                data,
+                format,
+                width,
+                height,
             },
             content_hash: hash,
-        }));
+        };
+        *last_image = Some((fingerprint, event.clone()));
+        return Ok(Some(event));
     }
 
-    // File-list detection: arboard doesn't expose a cross-platform file-list
-    // API yet.  On Windows, CF_HDROP is not surfaced by arboard.  We log and
-    // return None; when chunked file transfer is implemented this is the hook.
-    debug!("Clipboard contains no readable text or image (may be files or empty)");
+    debug!("Clipboard contains no readable files, text or image");
     Ok(None)
 }
 
+/// RTF is only read natively on Windows; elsewhere the plain text is used.
+#[cfg(not(target_os = "windows"))]
+fn read_rtf() -> Option<String> {
+    None
+}
+
+/// Change notifications are Windows-only; the polling loop never waits here.
+#[cfg(not(target_os = "windows"))]
+async fn clipboard_updated() {
+    std::future::pending().await
+}
+
 /// Encode a flat RGBA byte slice to PNG in memory (raw, no extra compression).
-fn encode_rgba_to_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+pub(crate) fn encode_rgba_to_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
     use image::{ImageBuffer, Rgba};
 
     let img: ImageBuffer<Rgba<u8>, Vec<u8>> =
@@ -152,42 +784,243 @@ fn encode_rgba_to_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
     Ok(buf.into_inner())
 }
 
+/// Encode a flat RGBA byte slice to WebP, lossy at `quality` (0–100) or
+/// lossless when `None`.
+pub(crate) fn encode_rgba_to_webp(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    quality: Option<f32>,
+) -> Result<Vec<u8>> {
+    if rgba.len() != width as usize * height as usize * 4 {
+        return Err(anyhow::anyhow!(
+            "Invalid RGBA dimensions ({width}x{height})"
+        ));
+    }
+    let encoder = webp::Encoder::from_rgba(rgba, width, height);
+    let memory = match quality {
+        Some(q) => encoder.encode(q),
+        None => encoder.encode_lossless(),
+    };
+    Ok(memory.to_vec())
+}
+
+/// Decode a clipboard image payload to RGBA pixels and its dimensions.
+fn decode_image(data: &[u8], format: ImageFormat) -> Result<(Vec<u8>, u32, u32)> {
+    match format {
+        ImageFormat::Png => {
+            let img = image::load_from_memory(data)?;
+            let (w, h) = (img.width(), img.height());
+            Ok((img.to_rgba8().into_raw(), w, h))
+        }
+        ImageFormat::Webp => {
+            let img = webp::Decoder::new(data)
+                .decode()
+                .ok_or_else(|| anyhow::anyhow!("Invalid WebP image"))?;
+            let rgba = if img.is_alpha() {
+                img.to_vec()
+            } else {
+                img.chunks(3)
+                    .flat_map(|p| [p[0], p[1], p[2], 255])
+                    .collect()
+            };
+            Ok((rgba, img.width(), img.height()))
+        }
+    }
+}
+
 // ── Write to host clipboard (called when a client pushes clipboard to us) ────
 
 /// Apply clipboard content received from a client to the host clipboard.
 /// Records the hash so the capture loop skips the resulting echo.
 pub fn apply_to_clipboard(content: &ClipboardContent, hash: &str) -> Result<()> {
-    let mut clipboard = arboard::Clipboard::new()?;
+    let _writing = WriteGuard::begin();
+    // A client push is worth an immediate retry even while backing off.
+    with_clipboard(true, |clipboard| {
+        // Record the hash the capture loop will read back *before* writing,
+        // so no read can see the new content without it; undo on failure.
+        let previous = LAST_SET_HASH
+            .lock()
+            .unwrap()
+            .replace(echo_hash(content, hash));
+        let result = apply_with(clipboard, content, hash);
+        if result.is_err() {
+            *LAST_SET_HASH.lock().unwrap() = previous;
+        }
+        result
+    })
+    .unwrap_or_else(|| Err(anyhow!("host clipboard unavailable")))
+}
+
+/// The hash the capture loop computes when it reads `content` back.
+fn echo_hash(content: &ClipboardContent, hash: &str) -> String {
+    match content {
+        // The capture loop hashes the local paths, not the sender's hash.
+        ClipboardContent::Files(files) => files_hash(files),
+        // Only written as plain text here; read back as such.
+        #[cfg(not(target_os = "windows"))]
+        ClipboardContent::Rtf { text, .. } => blake3::hash(text.as_bytes()).to_hex().to_string(),
+        _ => hash.to_string(),
+    }
+}
 
+fn apply_with(
+    clipboard: &mut arboard::Clipboard,
+    content: &ClipboardContent,
+    hash: &str,
+) -> Result<()> {
     match content {
         ClipboardContent::Text(text) => {
             clipboard.set_text(text.clone())?;
             info!("Applied clipboard text from client ({} chars)", text.len());
         }
-        ClipboardContent::Image { png_data, width: _, height: _ } => {
-            // Decode PNG → RGBA for arboard.  Dimensions come from the PNG header.
-            let img = image::load_from_memory(png_data)?;
-            let (w, h) = (img.width(), img.height());
-            let rgba = img.to_rgba8();
+        ClipboardContent::Html { html, text } => {
+            clipboard.set_html(html.as_str(), Some(text.as_str()))?;
+            info!("Applied clipboard HTML from client ({} bytes)", html.len());
+        }
+        ClipboardContent::Rtf { rtf, text } => {
+            #[cfg(target_os = "windows")]
+            {
+                write_rtf(rtf, text)?;
+                info!("Applied clipboard RTF from client ({} bytes)", rtf.len());
+            }
+            #[cfg(not(target_os = "windows"))]
+            {
+                clipboard.set_text(text.clone())?;
+                info!(
+                    "Applied clipboard RTF from client as plain text ({} chars, {} bytes RTF dropped)",
+                    text.len(),
+                    rtf.len()
+                );
+            }
+        }
+        ClipboardContent::Image {
+            data,
+            format,
+            width: _,
+            height: _,
+        } => {
+            // Decode → RGBA for arboard.  Dimensions come from the image header.
+            let (rgba, w, h) = decode_image(data, *format)?;
+            // Reading the image back re-encodes it, which need not reproduce
+            // the client's bytes (or hash); seed the re-encode cache so the
+            // read yields the client's event and is recognised as the echo.
+            let fingerprint = image_fingerprint(w as usize, h as usize, &rgba);
+            *LAST_IMAGE.lock().unwrap() = Some((
+                fingerprint,
+                ClipboardEvent {
+                    content: ClipboardContent::Image {
+                        data: data.clone(),
+                        format: *format,
+                        width: w,
+                        height: h,
+                    },
+                    content_hash: hash.to_string(),
+                },
+            ));
             let img_data = arboard::ImageData {
-                bytes: rgba.into_raw().into(),
+                bytes: rgba.into(),
                 width: w as usize,
                 height: h as usize,
             };
             clipboard.set_image(img_data)?;
             info!("Applied clipboard image from client ({}x{})", w, h);
         }
-        ClipboardContent::Files(names) => {
-            // Stub: file transfer not yet implemented.
-            warn!(
-                "File transfer not yet implemented — received file list from client: {:?}",
-                names
+        ClipboardContent::Files(files) => {
+            let paths: Vec<&PathBuf> = files.iter().map(|f| &f.path).collect();
+            clipboard.set().file_list(&paths)?;
+            info!(
+                "Applied clipboard file list from client ({} files)",
+                files.len()
             );
-            let fallback = format!("[Files — transfer not implemented]\n{}", names.join("\n"));
-            clipboard.set_text(fallback)?;
         }
     }
-
-    record_set_hash(hash);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{decode_webp, gradient};
+    use std::sync::atomic::AtomicU32;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    /// The write gate is process-wide; keep its tests from overlapping.
+    static GATE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[tokio::test]
+    async fn read_waits_for_write_in_progress() {
+        let _serial = GATE.lock().await;
+        let reads = Arc::new(AtomicU32::new(0));
+        let counter = reads.clone();
+
+        let write = WriteGuard::begin();
+        let read = tokio::spawn(read_between_writes(move || {
+            counter.fetch_add(1, Ordering::SeqCst)
+        }));
+        tokio::time::sleep(WRITE_SETTLE * 3).await;
+        assert_eq!(
+            reads.load(Ordering::SeqCst),
+            0,
+            "read while a write was in progress"
+        );
+
+        let ended = Instant::now();
+        drop(write);
+        read.await.unwrap().unwrap();
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+        assert!(
+            ended.elapsed() >= WRITE_SETTLE,
+            "read before the write settled"
+        );
+    }
+
+    #[tokio::test]
+    async fn write_during_read_forces_reread() {
+        let _serial = GATE.lock().await;
+        let reads = Arc::new(AtomicU32::new(0));
+        let counter = reads.clone();
+
+        let generation = WRITE_GENERATION.load(Ordering::SeqCst);
+        let seen = read_between_writes(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            if n == 0 {
+                // A client write starts and ends while the first read runs
+                drop(WriteGuard::begin());
+            }
+            n
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(seen, 1, "stale first read was returned");
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+        assert_eq!(WRITE_GENERATION.load(Ordering::SeqCst), generation + 2);
+    }
+
+    #[test]
+    fn lossless_webp_round_trips_exactly() {
+        let (w, h) = (40, 20);
+        let rgba = gradient(w, h);
+        let webp = encode_rgba_to_webp(&rgba, w, h, None).unwrap();
+        assert_eq!(decode_webp(&webp, w, h), rgba);
+    }
+
+    fn decode_png(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+        let image = image::load_from_memory(data).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (width, height));
+        image.into_raw()
+    }
+
+    #[test]
+    fn png_round_trips_exactly() {
+        let (w, h) = (40, 20);
+        let rgba = gradient(w, h);
+        let png = encode_rgba_to_png(&rgba, w, h).unwrap();
+        assert_eq!(decode_png(&png, w, h), rgba);
+
+        let png = encode_rgba_to_png(&[0; 4], 1, 1).unwrap();
+        assert_eq!(decode_png(&png, 1, 1), [0; 4]);
+    }
+}
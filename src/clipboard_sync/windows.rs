@@ -0,0 +1,199 @@
+//! Windows clipboard specifics: native RTF access (arboard only covers text,
+//! HTML and images) and change notification via `AddClipboardFormatListener`.
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Notify;
+use tracing::debug;
+use windows::core::w;
+use windows::Win32::Foundation::{GlobalFree, HANDLE, HGLOBAL, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::DataExchange::{
+    AddClipboardFormatListener, CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard,
+    RegisterClipboardFormatW, RemoveClipboardFormatListener, SetClipboardData,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Memory::{
+    GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE,
+};
+use windows::Win32::System::Ole::CF_UNICODETEXT;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+    TranslateMessage, HWND_MESSAGE, MSG, WINDOW_EX_STYLE, WM_CLIPBOARDUPDATE, WNDCLASSW,
+    WS_OVERLAPPED,
+};
+
+/// Keeps the clipboard open for the guard's lifetime.
+struct ClipboardGuard;
+
+impl ClipboardGuard {
+    fn open() -> Result<Self> {
+        unsafe { OpenClipboard(HWND::default()) }.map_err(|e| anyhow!("OpenClipboard: {}", e))?;
+        Ok(ClipboardGuard)
+    }
+}
+
+impl Drop for ClipboardGuard {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseClipboard();
+        }
+    }
+}
+
+fn rtf_format() -> u32 {
+    unsafe { RegisterClipboardFormatW(w!("Rich Text Format")) }
+}
+
+/// Read the "Rich Text Format" clipboard entry, if present.
+pub(super) fn read_rtf() -> Option<String> {
+    let format = rtf_format();
+    if format == 0 {
+        return None;
+    }
+    let _guard = ClipboardGuard::open().ok()?;
+    unsafe {
+        let handle = GetClipboardData(format).ok()?;
+        let hglobal = HGLOBAL(handle.0);
+        let ptr = GlobalLock(hglobal) as *const u8;
+        if ptr.is_null() {
+            return None;
+        }
+        let bytes = std::slice::from_raw_parts(ptr, GlobalSize(hglobal));
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let rtf = String::from_utf8_lossy(&bytes[..end]).into_owned();
+        let _ = GlobalUnlock(hglobal);
+        (!rtf.is_empty()).then_some(rtf)
+    }
+}
+
+/// Replace the clipboard with RTF plus its plain-text alternative.
+pub(super) fn write_rtf(rtf: &str, text: &str) -> Result<()> {
+    let format = rtf_format();
+    if format == 0 {
+        return Err(anyhow!("RegisterClipboardFormatW failed"));
+    }
+
+    let mut rtf_bytes = rtf.as_bytes().to_vec();
+    rtf_bytes.push(0);
+    let text_bytes: Vec<u8> = text
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect();
+
+    let _guard = ClipboardGuard::open()?;
+    unsafe {
+        EmptyClipboard().map_err(|e| anyhow!("EmptyClipboard: {}", e))?;
+        set_global(CF_UNICODETEXT.0 as u32, &text_bytes)?;
+        set_global(format, &rtf_bytes)?;
+    }
+    Ok(())
+}
+
+// ── Change notification ─────────────────────────────────────────────────────
+
+/// Signalled on every `WM_CLIPBOARDUPDATE` (and once at startup).
+static CLIPBOARD_UPDATED: Notify = Notify::const_new();
+
+/// Window procedure for the hidden clipboard listener window.
+unsafe extern "system" fn clipboard_listener_wnd_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_CLIPBOARDUPDATE {
+        debug!("WM_CLIPBOARDUPDATE received");
+        CLIPBOARD_UPDATED.notify_one();
+        return LRESULT(0);
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// Spawn a thread owning a message-only window registered with
+/// `AddClipboardFormatListener`.  Returns once the listener is registered, or
+/// with the reason it could not be.
+pub(super) fn spawn_clipboard_listener() -> Result<()> {
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<()>>();
+    std::thread::Builder::new()
+        .name("clipboard-listener".into())
+        .spawn(move || unsafe {
+            let hwnd = match create_listener_window() {
+                Ok(hwnd) => hwnd,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            if let Err(e) = AddClipboardFormatListener(hwnd) {
+                let _ = ready_tx.send(Err(anyhow!("AddClipboardFormatListener: {}", e)));
+                return;
+            }
+            // Read whatever is on the clipboard already.
+            CLIPBOARD_UPDATED.notify_one();
+            let _ = ready_tx.send(Ok(()));
+
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+            let _ = RemoveClipboardFormatListener(hwnd);
+        })?;
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("clipboard listener thread exited"))?
+}
+
+unsafe fn create_listener_window() -> Result<HWND> {
+    let instance = GetModuleHandleW(None).map_err(|e| anyhow!("GetModuleHandleW: {}", e))?;
+    let class_name = w!("DeragabuClipboardListener");
+    let wc = WNDCLASSW {
+        lpfnWndProc: Some(clipboard_listener_wnd_proc),
+        hInstance: instance.into(),
+        lpszClassName: class_name,
+        ..Default::default()
+    };
+    if RegisterClassW(&wc) == 0 {
+        return Err(anyhow!("RegisterClassW failed"));
+    }
+    CreateWindowExW(
+        WINDOW_EX_STYLE(0),
+        class_name,
+        w!(""),
+        WS_OVERLAPPED,
+        0,
+        0,
+        0,
+        0,
+        HWND_MESSAGE,
+        None,
+        instance,
+        None,
+    )
+    .map_err(|e| anyhow!("CreateWindowExW: {}", e))
+}
+
+/// Wait for the next clipboard change notification.
+pub(super) async fn clipboard_updated() {
+    CLIPBOARD_UPDATED.notified().await;
+}
+
+/// Copy `data` into a movable global block and hand it to the clipboard,
+/// which takes ownership on success.
+unsafe fn set_global(format: u32, data: &[u8]) -> Result<()> {
+    let hglobal =
+        GlobalAlloc(GMEM_MOVEABLE, data.len()).map_err(|e| anyhow!("GlobalAlloc: {}", e))?;
+    let ptr = GlobalLock(hglobal) as *mut u8;
+    if ptr.is_null() {
+        let _ = GlobalFree(hglobal);
+        return Err(anyhow!("GlobalLock failed"));
+    }
+    std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+    let _ = GlobalUnlock(hglobal);
+
+    if let Err(e) = SetClipboardData(format, HANDLE(hglobal.0)) {
+        let _ = GlobalFree(hglobal);
+        return Err(anyhow!("SetClipboardData: {}", e));
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Embeddable cursor capture, independent of the WebRTC transport.
+
+use anyhow::{anyhow, Result};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::{get_cached_cursor, get_last_cursor_id, run_cursor_capture, CachedCursor, CursorEvent};
+
+/// Events buffered between the capture loop and the consumer.
+const EVENT_QUEUE: usize = 32;
+
+/// Runs the platform cursor capture loop and hands its events to the caller.
+///
+/// ```no_run
+/// # async fn demo() {
+/// use deragabu_agent::cursor_capture::{CursorCapturer, CursorEvent};
+///
+/// let mut capturer = CursorCapturer::start();
+/// while let Some(event) = capturer.next_event().await {
+///     if let CursorEvent::CursorChanged { cursor_id: id, .. } = event {
+///         let cursor = capturer.get_cursor(&id).expect("changed cursors are cached");
+///         println!("{}x{} cursor, {} bytes of WebP", cursor.width, cursor.height, cursor.webp_data.len());
+///     }
+/// }
+/// # }
+/// ```
+///
+/// Capture state (the cursor cache in particular) is process-wide, so run at
+/// most one capturer, and not alongside [`crate::start_all_subsystems`].
+/// Dropping the capturer stops the loop.
+pub struct CursorCapturer {
+    events: mpsc::Receiver<CursorEvent>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl CursorCapturer {
+    /// Start capturing on the current Tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Outside a Tokio runtime.
+    pub fn start() -> Self {
+        let (tx, events) = mpsc::channel(EVENT_QUEUE);
+        let task = tokio::spawn(run_cursor_capture(tx));
+        CursorCapturer { events, task }
+    }
+
+    /// Wait for the next cursor change, hide or move.  `None` once the
+    /// capture loop has exited; [`CursorCapturer::stop`] reports why.
+    pub async fn next_event(&mut self) -> Option<CursorEvent> {
+        self.events.recv().await
+    }
+
+    /// The image for a `CursorChanged` id.  Ids stay valid while the cursor
+    /// remains in the cache, which holds the most recently used cursors.
+    pub fn get_cursor(&self, id: &str) -> Option<CachedCursor> {
+        get_cached_cursor(id)
+    }
+
+    /// The cursor currently shown, if any has been captured.
+    pub fn current_cursor(&self) -> Option<CachedCursor> {
+        get_cached_cursor(&get_last_cursor_id()?)
+    }
+
+    /// Stop the capture loop, returning its error if it had already failed.
+    pub async fn stop(mut self) -> Result<()> {
+        self.task.abort();
+        match (&mut self.task).await {
+            Ok(result) => result,
+            Err(e) if e.is_cancelled() => Ok(()),
+            Err(e) => Err(anyhow!("cursor capture task panicked: {}", e)),
+        }
+    }
+}
+
+impl Drop for CursorCapturer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
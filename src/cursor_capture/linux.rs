@@ -1,22 +1,37 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use std::sync::Mutex;
 use tokio::sync::mpsc;
 use tokio::time::{interval, Duration};
 use tracing::{debug, info, warn};
 
 use x11rb::connection::Connection;
-use x11rb::protocol::xfixes::{self, ConnectionExt as XFixesConnectionExt};
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::protocol::randr::{self, ConnectionExt as RandrConnectionExt};
+use x11rb::protocol::xfixes::ConnectionExt as XFixesConnectionExt;
+use x11rb::protocol::xproto::{ConnectionExt as XprotoConnectionExt, Window};
+use x11rb::protocol::Event;
 use x11rb::rust_connection::RustConnection;
 
+use super::linux_wayland::WaylandCursorCapture;
 use super::{
-    CachedCursor, CursorEvent, LAST_CURSOR_ID,
-    cache_cursor, encode_static_webp, init_cache,
+    cache_cursor, check_cursor_dimensions, clear_cache, cursor_hash, encode_static_webp,
+    evict_current_cursor, get_cached_cursor, id_from_hash, init_cache, notify_display_change,
+    poll_interval_ms, position_event, refresh_dpi_scale, take_display_change,
+    take_recapture_request, wait_next_poll, CachedCursor, CursorEvent, DEFAULT_SEAT,
+    LAST_CURSOR_ID,
 };
 
 // ─── Platform state ─────────────────────────────────────────────────────────
 
-/// Last cursor serial for detecting changes
-static LAST_CURSOR_SERIAL: Mutex<u32> = Mutex::new(0);
+/// XFixes serial and pixel hash of the last cursor, for detecting changes.
+/// Serials alone are unreliable: the server recycles them, and a serial can
+/// change while the image stays the same.
+static LAST_CURSOR: Mutex<Option<(u32, blake3::Hash)>> = Mutex::new(None);
+
+/// First reconnect delay after the X11 connection drops; doubled on each
+/// failed attempt up to [`MAX_RECONNECT_BACKOFF`].
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
 
 // ─── Public API ─────────────────────────────────────────────────────────────
 
@@ -39,28 +54,138 @@ pub fn get_dpi_scale() -> f32 {
     1.0
 }
 
+/// Whether to try the Wayland backend: a Wayland session with no X display.
+fn prefer_wayland() -> bool {
+    let set = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+    set("WAYLAND_DISPLAY") && !set("DISPLAY")
+}
+
 /// Run cursor capture loop (Linux / X11 implementation).
 ///
-/// Requires the XFixes extension (version ≥ 2). On systems using pure Wayland
-/// without XWayland this will fail at connection time.
+/// Requires the XFixes extension (version ≥ 2).  In a Wayland session
+/// without `$DISPLAY` the Wayland backend is used instead when the
+/// compositor supports `ext-image-copy-capture-v1`; otherwise this falls
+/// through to X11 and fails at connection time.
 ///
 /// System packages needed for building:
 ///   Debian/Ubuntu: `libxcb1-dev libxcb-xfixes0-dev`
 ///   Fedora/RHEL:   `libxcb-devel`
 ///   Arch:          `libxcb` (usually installed by default)
 pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
+    if prefer_wayland() {
+        match WaylandCursorCapture::connect() {
+            Ok(capture) => return capture.run(tx).await,
+            Err(e) => warn!("Wayland cursor capture unavailable, trying X11: {:#}", e),
+        }
+    }
+
     init_cache();
 
-    let dpi_scale = get_dpi_scale();
-    info!("Starting cursor capture on Linux/X11 (DPI scale: {:.2})", dpi_scale);
+    let dpi_scale = refresh_dpi_scale();
+    let poll_ms = poll_interval_ms();
+    info!(
+        "Starting cursor capture on Linux/X11 (DPI scale: {:.2}, poll: {} ms)",
+        dpi_scale, poll_ms
+    );
+
+    let mut x11 = Some(connect_x11()?);
+    // Reconnect state once the connection has dropped
+    let mut failures = 0u32;
+    let mut retry_at = tokio::time::Instant::now();
+
+    let mut poll_interval = interval(Duration::from_millis(poll_ms));
+
+    loop {
+        if !wait_next_poll(&mut poll_interval).await {
+            info!("Shutdown requested, stopping cursor capture");
+            break;
+        }
 
-    // Connect to X11
-    let (conn, _screen_num) = x11rb::connect(None)
-        .map_err(|e| anyhow!(
+        if x11.is_none() {
+            if tokio::time::Instant::now() < retry_at {
+                continue;
+            }
+            match connect_x11() {
+                Ok(session) => {
+                    info!("Reconnected to X11 display (attempt {})", failures + 1);
+                    failures = 0;
+                    // The new server numbers cursors afresh
+                    *LAST_CURSOR.lock().unwrap() = None;
+                    refresh_dpi_scale();
+                    x11 = Some(session);
+                }
+                Err(e) => {
+                    debug!("X11 reconnect attempt {} failed: {:#}", failures + 1, e);
+                    let backoff = RECONNECT_BACKOFF
+                        .saturating_mul(1 << failures.min(5))
+                        .min(MAX_RECONNECT_BACKOFF);
+                    failures += 1;
+                    retry_at = tokio::time::Instant::now() + backoff;
+                    continue;
+                }
+            }
+        }
+        let Some(X11Session { conn, root }) = &x11 else {
+            continue;
+        };
+
+        drain_x11_events(conn);
+        if take_display_change() {
+            handle_display_change();
+        }
+        if take_recapture_request() {
+            debug!("Re-capturing live cursor on client request");
+            evict_current_cursor();
+            *LAST_CURSOR.lock().unwrap() = None;
+        }
+
+        match capture_cursor(conn) {
+            Ok(Some(event)) => {
+                if tx.send(event).await.is_err() {
+                    warn!("Receiver closed, stopping cursor capture");
+                    break;
+                }
+            }
+            Ok(None) => {}
+            Err(e) if is_connection_error(&e) => {
+                warn!("Lost X11 connection, reconnecting: {:#}", e);
+                x11 = None;
+                retry_at = tokio::time::Instant::now() + RECONNECT_BACKOFF;
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to capture cursor: {:#}", e);
+            }
+        }
+        if let Some(event) = cursor_position(conn, *root).and_then(|(x, y)| position_event(x, y)) {
+            if tx.send(event).await.is_err() {
+                warn!("Receiver closed, stopping cursor capture");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// An X11 connection with XFixes initialised.
+struct X11Session {
+    conn: RustConnection,
+    root: Window,
+}
+
+/// Connect to `$DISPLAY`, check for XFixes ≥ 2 and subscribe to RandR
+/// screen changes.
+fn connect_x11() -> Result<X11Session> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| {
+        anyhow!(
             "Failed to connect to X11 display: {}. \
              Make sure $DISPLAY is set. Pure Wayland (without XWayland) is not supported.",
             e
-        ))?;
+        )
+    })?;
 
     // Initialise XFixes extension
     let xfixes_ver = conn
@@ -81,58 +206,95 @@ pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
         ));
     }
 
-    let mut poll_interval = interval(Duration::from_millis(16)); // ~60 fps
+    // Subscribe to RandR screen-change notifications (resolution / layout / DPI)
+    if let Err(e) = select_screen_change_events(&conn, screen_num) {
+        warn!(
+            "RandR unavailable, display changes will not be detected: {}",
+            e
+        );
+    }
 
-    loop {
-        poll_interval.tick().await;
+    let root = conn.setup().roots[screen_num].root;
+    Ok(X11Session { conn, root })
+}
 
-        match capture_cursor(&conn) {
-            Ok(Some(event)) => {
-                if tx.send(event).await.is_err() {
-                    warn!("Receiver closed, stopping cursor capture");
-                    break;
-                }
-            }
-            Ok(None) => {}
-            Err(e) => {
-                warn!("Failed to capture cursor: {}", e);
-            }
+/// Whether `e` means the X11 connection itself is gone (server restarted or
+/// the socket closed), as opposed to a failed request.
+fn is_connection_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        cause.is::<ConnectionError>()
+            || matches!(
+                cause.downcast_ref::<ReplyError>(),
+                Some(ReplyError::ConnectionError(_))
+            )
+    })
+}
+
+/// Ask the X server to deliver `RRScreenChangeNotify` for the root window.
+fn select_screen_change_events(conn: &RustConnection, screen_num: usize) -> Result<()> {
+    conn.randr_query_version(1, 2)?
+        .reply()
+        .map_err(|e| anyhow!("RandR query version failed: {}", e))?;
+    let root = conn
+        .setup()
+        .roots
+        .get(screen_num)
+        .ok_or_else(|| anyhow!("X11 screen {} not found", screen_num))?
+        .root;
+    conn.randr_select_input(root, randr::NotifyMask::SCREEN_CHANGE)?;
+    conn.flush()?;
+    Ok(())
+}
+
+/// Drain queued X11 events, flagging a display change on RandR notifications.
+fn drain_x11_events(conn: &RustConnection) {
+    while let Ok(Some(event)) = conn.poll_for_event() {
+        if let Event::RandrScreenChangeNotify(ev) = event {
+            debug!(
+                "RandR screen change: {}x{} px, {}x{} mm",
+                ev.width, ev.height, ev.mwidth, ev.mheight
+            );
+            notify_display_change();
         }
     }
+}
 
-    Ok(())
+/// Invalidate cached cursors and force the current cursor to be re-captured
+/// (and re-emitted) at the new scale.
+fn handle_display_change() {
+    let dpi_scale = refresh_dpi_scale();
+    info!(
+        "Display configuration changed (DPI scale now {:.2}), re-capturing cursor",
+        dpi_scale
+    );
+    clear_cache();
+    *LAST_CURSOR.lock().unwrap() = None;
 }
 
-// ─── Internal helpers ───────────────────────────────────────────────────────
+/// Current pointer position relative to the root window.
+fn cursor_position(conn: &RustConnection, root: Window) -> Option<(i32, i32)> {
+    let reply = conn.query_pointer(root).ok()?.reply().ok()?;
+    Some((reply.root_x as i32, reply.root_y as i32))
+}
 
 /// Capture current cursor and return event if changed.
 fn capture_cursor(conn: &RustConnection) -> Result<Option<CursorEvent>> {
-    // XFixesGetCursorImage returns the current cursor image + metadata
+    // XFixesGetCursorImageAndName returns the current cursor image + metadata,
+    // plus the theme name the cursor was loaded under (if any)
     let reply = conn
-        .xfixes_get_cursor_image()
-        .map_err(|e| anyhow!("XFixesGetCursorImage request failed: {}", e))?
+        .xfixes_get_cursor_image_and_name()
+        .context("XFixesGetCursorImageAndName request failed")?
         .reply()
-        .map_err(|e| anyhow!("XFixesGetCursorImage reply failed: {}", e))?;
+        .context("XFixesGetCursorImageAndName reply failed")?;
 
     let serial = reply.cursor_serial;
 
-    // Quick-check: same cursor?
-    {
-        let mut last = LAST_CURSOR_SERIAL.lock().unwrap();
-        if serial == *last {
-            return Ok(None);
-        }
-        *last = serial;
-    }
-
     let width = reply.width as u32;
     let height = reply.height as u32;
     let hotspot_x = reply.xhot as i32;
     let hotspot_y = reply.yhot as i32;
 
-    if width == 0 || height == 0 {
-        return Err(anyhow!("Cursor has zero dimensions"));
-    }
+    check_cursor_dimensions(width, height)?;
 
     // Convert ARGB u32 pixels → straight RGBA u8 array
     let pixels = &reply.cursor_image;
@@ -171,19 +333,54 @@ fn capture_cursor(conn: &RustConnection) -> Result<Option<CursorEvent>> {
         rgba[i * 4 + 3] = a;
     }
 
+    // Same cursor?  Decided by the pixels and hotspot; the serial only adds
+    // context.
+    let hash = cursor_hash(&rgba, width, height, hotspot_x, hotspot_y);
+    {
+        let mut last = LAST_CURSOR.lock().unwrap();
+        match *last {
+            Some((last_serial, last_hash)) if last_hash == hash => {
+                if last_serial != serial {
+                    debug!(
+                        "Cursor serial {} → {} with an unchanged image",
+                        last_serial, serial
+                    );
+                    *last = Some((serial, hash));
+                }
+                return Ok(None);
+            }
+            Some((last_serial, _)) if last_serial == serial => {
+                debug!("Cursor serial {} reused for a different image", serial);
+            }
+            _ => {}
+        }
+        *last = Some((serial, hash));
+    }
+
     // Detect "invisible" cursor (all pixels transparent → cursor hidden)
     if all_transparent {
         let mut last_id = LAST_CURSOR_ID.lock().unwrap();
         if last_id.is_some() {
             *last_id = None;
             debug!("Cursor appears hidden (fully transparent)");
-            return Ok(Some(CursorEvent::CursorHidden));
+            return Ok(Some(CursorEvent::CursorHidden { seat: DEFAULT_SEAT }));
         }
         return Ok(None);
     }
 
-    // Hash → cache → event
-    let cursor_id = format!("cur_{}", &blake3::hash(&rgba).to_hex()[..12]);
+    // Hash → cache → event.  A cursor seen before only needs its id re-sent.
+    let cursor_id = id_from_hash("cur", &hash);
+    if get_cached_cursor(&cursor_id).is_some() {
+        debug!(
+            "Cursor {} already cached, re-signalling without re-encoding",
+            cursor_id
+        );
+        *LAST_CURSOR_ID.lock().unwrap() = Some(cursor_id.clone());
+        return Ok(Some(CursorEvent::CursorChanged {
+            seat: DEFAULT_SEAT,
+            cursor_id,
+        }));
+    }
     let webp_data = encode_static_webp(&rgba, width, height)?;
 
     let cached = CachedCursor {
@@ -196,8 +393,12 @@ fn capture_cursor(conn: &RustConnection) -> Result<Option<CursorEvent>> {
         is_animated: false,
         frame_count: 1,
         frame_delay_ms: 0,
+        name: String::from_utf8_lossy(&reply.name).into_owned(),
     };
 
     let (cursor_id, _) = cache_cursor(cached);
-    Ok(Some(CursorEvent::CursorChanged(cursor_id)))
+    Ok(Some(CursorEvent::CursorChanged {
+        seat: DEFAULT_SEAT,
+        cursor_id,
+    }))
 }
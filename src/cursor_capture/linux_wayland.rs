@@ -0,0 +1,495 @@
+//! Cursor capture on Wayland compositors via `ext-image-copy-capture-v1`.
+//!
+//! A pointer cursor session on the first output delivers the cursor image
+//! (into a shared-memory buffer), its hotspot and its position whenever the
+//! compositor repaints it, which maps directly onto the X11 XFixes path.
+//! Compositors without the protocol are reported as an error from
+//! [`WaylandCursorCapture::connect`] so the caller can fall back to X11.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs::File;
+use std::os::fd::{AsFd, AsRawFd};
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use tokio::io::unix::AsyncFd;
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration};
+use tracing::{debug, info, warn};
+
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{
+    wl_buffer::WlBuffer, wl_output::WlOutput, wl_pointer::WlPointer, wl_registry::WlRegistry,
+    wl_seat::WlSeat, wl_shm, wl_shm::WlShm, wl_shm_pool::WlShmPool,
+};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle, WEnum};
+use wayland_protocols::ext::image_capture_source::v1::client::{
+    ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+    ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1,
+};
+use wayland_protocols::ext::image_copy_capture::v1::client::{
+    ext_image_copy_capture_cursor_session_v1::{self, ExtImageCopyCaptureCursorSessionV1},
+    ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1},
+    ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1,
+    ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+};
+
+use super::{
+    cache_cursor, check_cursor_dimensions, clear_cache, cursor_hash, encode_static_webp,
+    evict_current_cursor, id_from_hash, init_cache, poll_interval_ms, position_event,
+    take_display_change, take_recapture_request, wait_next_poll, CachedCursor, CursorEvent,
+    DEFAULT_SEAT, LAST_CURSOR_ID,
+};
+
+// ─── Protocol state ─────────────────────────────────────────────────────────
+
+/// Outcome of the in-flight frame capture.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum FrameStatus {
+    #[default]
+    Idle,
+    Pending,
+    Ready,
+    Failed,
+}
+
+/// Event-driven state updated by the Wayland dispatch handlers.
+#[derive(Default)]
+struct State {
+    /// Buffer size announced by the session (the cursor image size)
+    buffer_size: (u32, u32),
+    /// Whether ARGB8888 is among the supported shm formats
+    argb_supported: bool,
+    /// Set on the session's `done` event: buffer constraints are final
+    constraints_changed: bool,
+    /// The session was stopped by the compositor
+    stopped: bool,
+    hotspot: (i32, i32),
+    /// Cursor position relative to the output, while it is on the output
+    position: Option<(i32, i32)>,
+    frame_status: FrameStatus,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as wayland_client::Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use ext_image_copy_capture_session_v1::Event;
+        match event {
+            Event::BufferSize { width, height } => state.buffer_size = (width, height),
+            Event::ShmFormat {
+                format: WEnum::Value(wl_shm::Format::Argb8888),
+            } => {
+                state.argb_supported = true;
+            }
+            Event::Done => state.constraints_changed = true,
+            Event::Stopped => state.stopped = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureCursorSessionV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtImageCopyCaptureCursorSessionV1,
+        event: ext_image_copy_capture_cursor_session_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use ext_image_copy_capture_cursor_session_v1::Event;
+        match event {
+            Event::Position { x, y } => state.position = Some((x, y)),
+            Event::Hotspot { x, y } => state.hotspot = (x, y),
+            Event::Leave => state.position = None,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use ext_image_copy_capture_frame_v1::Event;
+        match event {
+            Event::Ready => state.frame_status = FrameStatus::Ready,
+            Event::Failed { reason } => {
+                debug!("Wayland cursor frame failed: {:?}", reason);
+                state.frame_status = FrameStatus::Failed;
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: ignore WlShm);
+delegate_noop!(State: ignore WlSeat);
+delegate_noop!(State: ignore WlPointer);
+delegate_noop!(State: ignore WlOutput);
+delegate_noop!(State: ignore WlBuffer);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: ExtOutputImageCaptureSourceManagerV1);
+delegate_noop!(State: ExtImageCaptureSourceV1);
+delegate_noop!(State: ExtImageCopyCaptureManagerV1);
+
+// ─── Shared-memory buffer ───────────────────────────────────────────────────
+
+/// An ARGB8888 `wl_buffer` backed by an unlinked file.
+struct ShmBuffer {
+    file: File,
+    buffer: WlBuffer,
+    width: u32,
+    height: u32,
+}
+
+impl ShmBuffer {
+    fn new(shm: &WlShm, qh: &QueueHandle<State>, width: u32, height: u32) -> Result<Self> {
+        let size = width as usize * height as usize * 4;
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        let path = dir.join(format!("deragabu-cursor-{}", std::process::id()));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .with_context(|| format!("create {:?}", path))?;
+        // The compositor only needs the fd; don't leave the file behind.
+        let _ = std::fs::remove_file(&path);
+        file.set_len(size as u64)?;
+
+        let pool = shm.create_pool(file.as_fd(), size as i32, qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            width as i32,
+            height as i32,
+            (width * 4) as i32,
+            wl_shm::Format::Argb8888,
+            qh,
+            (),
+        );
+        pool.destroy();
+        Ok(ShmBuffer {
+            file,
+            buffer,
+            width,
+            height,
+        })
+    }
+
+    /// Read the buffer back as straight-alpha RGBA.
+    fn read_rgba(&self) -> Result<Vec<u8>> {
+        let mut pixels = vec![0u8; self.width as usize * self.height as usize * 4];
+        self.file.read_exact_at(&mut pixels, 0)?;
+        // ARGB8888 is little-endian (bytes B, G, R, A) and premultiplied
+        for px in pixels.chunks_exact_mut(4) {
+            let (b, g, r, a) = (px[0] as u16, px[1] as u16, px[2] as u16, px[3]);
+            let unpremultiply = |c: u16| -> u8 {
+                if a > 0 && a < 255 {
+                    let af = a as u16;
+                    ((c * 255 + af / 2) / af).min(255) as u8
+                } else {
+                    c as u8
+                }
+            };
+            px[0] = unpremultiply(r);
+            px[1] = unpremultiply(g);
+            px[2] = unpremultiply(b);
+        }
+        Ok(pixels)
+    }
+}
+
+impl Drop for ShmBuffer {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+    }
+}
+
+// ─── Capture ────────────────────────────────────────────────────────────────
+
+/// A pointer cursor session on the first Wayland output.
+pub struct WaylandCursorCapture {
+    conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    shm: WlShm,
+    session: ExtImageCopyCaptureSessionV1,
+    // Kept alive for the lifetime of the session
+    _cursor_session: ExtImageCopyCaptureCursorSessionV1,
+}
+
+impl WaylandCursorCapture {
+    /// Connect to `$WAYLAND_DISPLAY` and open a pointer cursor session.
+    ///
+    /// Fails when the compositor does not offer `ext-image-copy-capture-v1`
+    /// and `ext-output-image-capture-source-v1`.
+    pub fn connect() -> Result<Self> {
+        let conn = Connection::connect_to_env().context("connect to Wayland display")?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+
+        let shm: WlShm = globals
+            .bind(&qh, 1..=1, ())
+            .context("wl_shm not available")?;
+        let seat: WlSeat = globals
+            .bind(&qh, 1..=1, ())
+            .context("wl_seat not available")?;
+        let output: WlOutput = globals.bind(&qh, 1..=1, ()).context("no wl_output")?;
+        let source_manager: ExtOutputImageCaptureSourceManagerV1 = globals
+            .bind(&qh, 1..=1, ())
+            .context("compositor lacks ext_output_image_capture_source_manager_v1")?;
+        let copy_manager: ExtImageCopyCaptureManagerV1 = globals
+            .bind(&qh, 1..=1, ())
+            .context("compositor lacks ext_image_copy_capture_manager_v1")?;
+
+        let pointer = seat.get_pointer(&qh, ());
+        let source = source_manager.create_source(&output, &qh, ());
+        let cursor_session = copy_manager.create_pointer_cursor_session(&source, &pointer, &qh, ());
+        let session = cursor_session.get_capture_session(&qh, ());
+
+        let mut state = State::default();
+        queue.roundtrip(&mut state)?;
+        if state.stopped {
+            bail!("Wayland cursor session stopped immediately");
+        }
+
+        Ok(WaylandCursorCapture {
+            conn,
+            queue,
+            state,
+            shm,
+            session,
+            _cursor_session: cursor_session,
+        })
+    }
+
+    /// Run the cursor capture loop (Wayland implementation).
+    ///
+    /// A frame is always outstanding; the compositor completes it when the
+    /// cursor image changes.  Position and hotspot arrive as session events.
+    pub async fn run(mut self, tx: mpsc::Sender<CursorEvent>) -> Result<()> {
+        init_cache();
+        let poll_ms = poll_interval_ms();
+        info!(
+            "Starting cursor capture on Linux/Wayland (poll: {} ms)",
+            poll_ms
+        );
+
+        let socket = AsyncFd::new(self.conn.backend().poll_fd().as_raw_fd())
+            .context("register Wayland socket")?;
+        let mut poll_interval = interval(Duration::from_millis(poll_ms));
+        let mut buffer: Option<ShmBuffer> = None;
+        let mut frame: Option<ExtImageCopyCaptureFrameV1> = None;
+        let mut last_hash: Option<blake3::Hash> = None;
+        let mut last_position: Option<(i32, i32)> = None;
+
+        loop {
+            self.queue.dispatch_pending(&mut self.state)?;
+            self.queue.flush()?;
+            let Some(guard) = self.queue.prepare_read() else {
+                continue;
+            };
+
+            tokio::select! {
+                readable = socket.readable() => {
+                    let mut readable = readable?;
+                    match guard.read() {
+                        Ok(_) => {}
+                        Err(wayland_client::backend::WaylandError::Io(e))
+                            if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                        Err(e) => return Err(anyhow!("Wayland read failed: {}", e)),
+                    }
+                    readable.clear_ready();
+                }
+                proceed = wait_next_poll(&mut poll_interval) => {
+                    drop(guard);
+                    if !proceed {
+                        info!("Shutdown requested, stopping cursor capture");
+                        break;
+                    }
+                    if take_display_change() {
+                        info!("Display configuration changed, re-capturing cursor");
+                        clear_cache();
+                        last_hash = None;
+                    }
+                    if take_recapture_request() {
+                        debug!("Re-capturing live cursor on client request");
+                        evict_current_cursor();
+                        last_hash = None;
+                    }
+                }
+            }
+            self.queue.dispatch_pending(&mut self.state)?;
+
+            if self.state.stopped {
+                return Err(anyhow!("Wayland cursor session stopped by the compositor"));
+            }
+
+            if std::mem::take(&mut self.state.constraints_changed) {
+                if !self.state.argb_supported {
+                    return Err(anyhow!(
+                        "Wayland cursor session does not offer ARGB8888 buffers"
+                    ));
+                }
+                let (width, height) = self.state.buffer_size;
+                debug!("Wayland cursor buffer size: {}x{}", width, height);
+                // Any in-flight frame targets the old buffer; start over
+                if let Some(old) = frame.take() {
+                    old.destroy();
+                }
+                self.state.frame_status = FrameStatus::Idle;
+                buffer = if width > 0 && height > 0 {
+                    match check_cursor_dimensions(width, height) {
+                        Ok(()) => Some(ShmBuffer::new(
+                            &self.shm,
+                            &self.queue.handle(),
+                            width,
+                            height,
+                        )?),
+                        // Treated like an empty cursor until the size changes again
+                        Err(e) => {
+                            warn!("Ignoring Wayland cursor buffer: {}", e);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+                // An empty cursor image means the cursor is hidden
+                if buffer.is_none() && LAST_CURSOR_ID.lock().unwrap().take().is_some() {
+                    last_hash = None;
+                    debug!("Cursor hidden (empty cursor buffer)");
+                    if tx
+                        .send(CursorEvent::CursorHidden { seat: DEFAULT_SEAT })
+                        .await
+                        .is_err()
+                    {
+                        warn!("Receiver closed, stopping cursor capture");
+                        break;
+                    }
+                }
+            }
+
+            match self.state.frame_status {
+                FrameStatus::Ready => {
+                    if let Some(buf) = &buffer {
+                        match self.cursor_event(buf, &mut last_hash) {
+                            Ok(Some(event)) => {
+                                if tx.send(event).await.is_err() {
+                                    warn!("Receiver closed, stopping cursor capture");
+                                    break;
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(e) => warn!("Failed to capture cursor: {}", e),
+                        }
+                    }
+                    self.state.frame_status = FrameStatus::Idle;
+                }
+                // Retry on the next pass; a constraints change arrives as `done`
+                FrameStatus::Failed => self.state.frame_status = FrameStatus::Idle,
+                FrameStatus::Idle | FrameStatus::Pending => {}
+            }
+
+            if self.state.frame_status == FrameStatus::Idle {
+                if let Some(old) = frame.take() {
+                    old.destroy();
+                }
+                if let Some(buf) = &buffer {
+                    let next = self.session.create_frame(&self.queue.handle(), ());
+                    next.attach_buffer(&buf.buffer);
+                    next.damage_buffer(0, 0, buf.width as i32, buf.height as i32);
+                    next.capture();
+                    frame = Some(next);
+                    self.state.frame_status = FrameStatus::Pending;
+                }
+            }
+
+            if self.state.position != last_position {
+                last_position = self.state.position;
+                if let Some(event) = last_position.and_then(|(x, y)| position_event(x, y)) {
+                    if tx.send(event).await.is_err() {
+                        warn!("Receiver closed, stopping cursor capture");
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turn a completed frame into an event if the cursor image changed.
+    fn cursor_event(
+        &self,
+        buffer: &ShmBuffer,
+        last_hash: &mut Option<blake3::Hash>,
+    ) -> Result<Option<CursorEvent>> {
+        let rgba = buffer.read_rgba()?;
+        let (hotspot_x, hotspot_y) = self.state.hotspot;
+        let hash = cursor_hash(&rgba, buffer.width, buffer.height, hotspot_x, hotspot_y);
+        if *last_hash == Some(hash) {
+            return Ok(None);
+        }
+        *last_hash = Some(hash);
+
+        // Detect "invisible" cursor (all pixels transparent → cursor hidden)
+        if !rgba.chunks(4).any(|p| p[3] > 0) {
+            let mut last_id = LAST_CURSOR_ID.lock().unwrap();
+            if last_id.is_some() {
+                *last_id = None;
+                debug!("Cursor appears hidden (fully transparent)");
+                return Ok(Some(CursorEvent::CursorHidden { seat: DEFAULT_SEAT }));
+            }
+            return Ok(None);
+        }
+
+        let cursor_id = id_from_hash("cur", &hash);
+        let webp_data = encode_static_webp(&rgba, buffer.width, buffer.height)?;
+
+        let cached = CachedCursor {
+            id: cursor_id,
+            webp_data,
+            width: buffer.width,
+            height: buffer.height,
+            hotspot_x,
+            hotspot_y,
+            is_animated: false,
+            frame_count: 1,
+            frame_delay_ms: 0,
+            name: String::new(),
+        };
+
+        let (cursor_id, _) = cache_cursor(cached);
+        Ok(Some(CursorEvent::CursorChanged {
+            seat: DEFAULT_SEAT,
+            cursor_id,
+        }))
+    }
+}
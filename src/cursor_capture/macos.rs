@@ -1,13 +1,19 @@
 use anyhow::{anyhow, Result};
+use std::collections::HashMap;
 use std::os::raw::c_int;
 use std::sync::Mutex;
+use std::time::Instant;
 use tokio::sync::mpsc;
 use tokio::time::{interval, Duration};
 use tracing::{debug, info, warn};
 
+use super::scale::{scale_filter, scale_rgba};
 use super::{
-    CachedCursor, CursorEvent, LAST_CURSOR_ID,
-    cache_cursor, encode_static_webp, init_cache,
+    cache_cursor, check_cursor_dimensions, clear_cache, cursor_hash, cursor_id, dpi_scale,
+    encode_animated_webp, encode_static_webp, estimate_frame_delay, evict_current_cursor,
+    get_cached_cursor, get_last_cursor_id, init_cache, notify_display_change, poll_interval_ms,
+    polls_for, position_event, refresh_dpi_scale, take_display_change, take_recapture_request,
+    wait_next_poll, CachedCursor, CursorEvent, DEFAULT_SEAT, LAST_CURSOR_ID,
 };
 
 // ─── CoreGraphics type definitions ──────────────────────────────────────────
@@ -58,6 +64,7 @@ extern "C" {
         components: *mut c_int,
         bits_per_component: *mut c_int,
     ) -> c_int;
+    fn CGSGetCurrentCursorLocation(connection: c_int, point: *mut CGPoint) -> c_int;
 
     // Public display-mode APIs for DPI detection
     fn CGMainDisplayID() -> u32;
@@ -65,8 +72,32 @@ extern "C" {
     fn CGDisplayModeGetPixelWidth(mode: *const std::ffi::c_void) -> usize;
     fn CGDisplayModeGetWidth(mode: *const std::ffi::c_void) -> usize;
     fn CGDisplayModeRelease(mode: *mut std::ffi::c_void);
+
+    // Display reconfiguration notifications (resolution, scaling, hot-plug)
+    fn CGDisplayRegisterReconfigurationCallback(
+        callback: extern "C" fn(display: u32, flags: u32, user_info: *mut std::ffi::c_void),
+        user_info: *mut std::ffi::c_void,
+    ) -> i32;
+
+    // Keyboard activity, to tell typing auto-hide from a genuine hide
+    fn CGEventSourceSecondsSinceLastEventType(state_id: i32, event_type: u32) -> f64;
+    fn CGEventSourceFlagsState(state_id: i32) -> u64;
 }
 
+// kCGDisplayBeginConfigurationFlag — sent before the change is applied
+const DISPLAY_BEGIN_CONFIGURATION_FLAG: u32 = 1 << 0;
+
+// kCGEventSourceStateHIDSystemState
+const EVENT_SOURCE_HID_SYSTEM_STATE: i32 = 1;
+// kCGEventKeyDown
+const EVENT_KEY_DOWN: u32 = 10;
+// kCGEventFlagMaskShift | Control | Alternate | Command
+const MODIFIER_FLAGS_MASK: u64 = 0x001F_0000;
+
+/// A transparent cursor within this long of a key press is AppKit's typing
+/// auto-hide rather than a hidden cursor.
+const TYPING_AUTO_HIDE_WINDOW_SECS: f64 = 2.0;
+
 // ─── Objective-C runtime & AppKit bindings (Retina cursor images) ───────────
 
 #[link(name = "AppKit", kind = "framework")]
@@ -86,7 +117,8 @@ extern "C" {
 #[allow(clashing_extern_declarations)]
 extern "C" {
     #[link_name = "objc_msgSend"]
-    fn msg_send_id(obj: *mut std::ffi::c_void, sel: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
+    fn msg_send_id(obj: *mut std::ffi::c_void, sel: *mut std::ffi::c_void)
+        -> *mut std::ffi::c_void;
 
     #[link_name = "objc_msgSend"]
     fn msg_send_id_ptr3(
@@ -99,10 +131,7 @@ extern "C" {
 
     /// Returns NSPoint / NSSize ({f64, f64}) – fits in two FP registers.
     #[link_name = "objc_msgSend"]
-    fn msg_send_point(
-        obj: *mut std::ffi::c_void,
-        sel: *mut std::ffi::c_void,
-    ) -> CGPoint;
+    fn msg_send_point(obj: *mut std::ffi::c_void, sel: *mut std::ffi::c_void) -> CGPoint;
 }
 
 // Additional CoreGraphics APIs for rendering a CGImage into a bitmap.
@@ -120,11 +149,7 @@ extern "C" {
         space: *mut std::ffi::c_void,
         bitmap_info: u32,
     ) -> *mut std::ffi::c_void;
-    fn CGContextDrawImage(
-        ctx: *mut std::ffi::c_void,
-        rect: CGRect,
-        image: *const std::ffi::c_void,
-    );
+    fn CGContextDrawImage(ctx: *mut std::ffi::c_void, rect: CGRect, image: *const std::ffi::c_void);
     fn CGContextRelease(ctx: *mut std::ffi::c_void);
 }
 
@@ -136,6 +161,199 @@ const BITMAP_INFO_RGBA_PREMUL: u32 = 1; // kCGImageAlphaPremultipliedLast
 /// Last cursor seed for detecting changes
 static LAST_CURSOR_SEED: Mutex<c_int> = Mutex::new(-1);
 
+/// Seed changes on a same-shaped cursor closer together than this are
+/// treated as frames of one animation (the spinning wait cursor advances
+/// roughly every 50–100 ms).
+const ANIM_MAX_FRAME_GAP: Duration = Duration::from_millis(250);
+
+/// An animation must loop back to its first frame within this window,
+/// otherwise the frames are sent as ordinary static cursors.
+const ANIM_CONFIRM_WINDOW: Duration = Duration::from_millis(1000);
+
+/// Upper bound on frames collected while probing for an animation.
+const ANIM_MAX_FRAMES: usize = 64;
+
+/// Frames of a cursor that may be animating, collected while its seed keeps
+/// changing rapidly.
+///
+/// macOS has no API exposing animation frames, so an animation is inferred
+/// the way the Windows backend probes `GetCursorFrameInfo`: collect distinct
+/// frames until the first one repeats, then encode the cycle.
+struct AnimationProbe {
+    width: u32,
+    height: u32,
+    hotspot: (i32, i32),
+    frames: Vec<Vec<u8>>,
+    hashes: Vec<blake3::Hash>,
+    started: Instant,
+    last_frame: Instant,
+    /// Running estimate of the interval between frames (0 until measured)
+    frame_delay_ms: u32,
+}
+
+/// Outcome of feeding a captured frame to the animation tracker.
+enum AnimationStep {
+    /// Not (yet) part of a confirmed animation; send as a static cursor.
+    Static,
+    /// An animation was confirmed or re-entered; make it the current cursor.
+    Animated(CachedCursor),
+    /// Another frame of the animation the client is already playing.
+    Playing,
+}
+
+static ANIMATION_PROBE: Mutex<Option<AnimationProbe>> = Mutex::new(None);
+
+/// Frame hash → id of the animated cursor containing that frame, so a
+/// returning animation is recognised from any of its frames.
+static KNOWN_ANIMATIONS: Mutex<Option<HashMap<blake3::Hash, String>>> = Mutex::new(None);
+
+/// Forget the frames being probed (e.g. after a display change).
+fn reset_animation_probe() {
+    *ANIMATION_PROBE.lock().unwrap() = None;
+}
+
+/// Feed a freshly captured cursor frame to the animation tracker.
+fn track_animation(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    hotspot_x: i32,
+    hotspot_y: i32,
+) -> AnimationStep {
+    let hash = cursor_hash(rgba, width, height, hotspot_x, hotspot_y);
+    let now = Instant::now();
+
+    let known_id = KNOWN_ANIMATIONS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|known| known.get(&hash).cloned());
+    if let Some(id) = known_id {
+        reset_animation_probe();
+        if get_last_cursor_id().as_deref() == Some(id.as_str()) {
+            return AnimationStep::Playing;
+        }
+        if let Some(cached) = get_cached_cursor(&id) {
+            return AnimationStep::Animated(cached);
+        }
+        // Evicted from the cursor cache; probe it again from scratch.
+        if let Some(known) = KNOWN_ANIMATIONS.lock().unwrap().as_mut() {
+            known.retain(|_, v| *v != id);
+        }
+    }
+
+    let mut probe_guard = ANIMATION_PROBE.lock().unwrap();
+    let continues = probe_guard.as_ref().is_some_and(|p| {
+        p.width == width
+            && p.height == height
+            && p.hotspot == (hotspot_x, hotspot_y)
+            && now.duration_since(p.last_frame) <= ANIM_MAX_FRAME_GAP
+            && now.duration_since(p.started) <= ANIM_CONFIRM_WINDOW
+            && p.frames.len() < ANIM_MAX_FRAMES
+    });
+    if !continues {
+        *probe_guard = Some(AnimationProbe {
+            width,
+            height,
+            hotspot: (hotspot_x, hotspot_y),
+            frames: vec![rgba.to_vec()],
+            hashes: vec![hash],
+            started: now,
+            last_frame: now,
+            frame_delay_ms: 0,
+        });
+        return AnimationStep::Static;
+    }
+
+    let probe = probe_guard.as_mut().unwrap();
+    let interval = now.duration_since(probe.last_frame).as_millis() as u32;
+    probe.frame_delay_ms = estimate_frame_delay(probe.frame_delay_ms, interval);
+    match probe.hashes.iter().position(|h| *h == hash) {
+        Some(0) if probe.frames.len() >= 2 => {
+            // Back at the first frame: one full cycle observed.
+            let probe = probe_guard.take().unwrap();
+            drop(probe_guard);
+            match encode_animation(probe) {
+                Ok(cached) => AnimationStep::Animated(cached),
+                Err(e) => {
+                    warn!("Failed to encode animated cursor: {}", e);
+                    AnimationStep::Static
+                }
+            }
+        }
+        Some(_) => {
+            // A repeat that does not close the cycle; keep waiting.
+            probe.last_frame = now;
+            AnimationStep::Static
+        }
+        None => {
+            probe.frames.push(rgba.to_vec());
+            probe.hashes.push(hash);
+            probe.last_frame = now;
+            AnimationStep::Static
+        }
+    }
+}
+
+/// Encode a completed probe as an animated cursor and remember its frames.
+///
+/// The frame delay is the probe's smoothed estimate of the intervals between
+/// the frames it saw.
+fn encode_animation(probe: AnimationProbe) -> Result<CachedCursor> {
+    let frame_count = probe.frames.len() as u32;
+    let frame_delay = probe.frame_delay_ms;
+
+    let mut hasher_input = Vec::new();
+    for hash in &probe.hashes {
+        hasher_input.extend_from_slice(hash.as_bytes());
+    }
+    let (hotspot_x, hotspot_y) = probe.hotspot;
+    let cursor_id = cursor_id(
+        "ani",
+        &hasher_input,
+        probe.width,
+        probe.height,
+        hotspot_x,
+        hotspot_y,
+    );
+
+    let webp_data =
+        encode_animated_webp(&probe.frames, probe.width, probe.height, frame_delay as i32)?;
+
+    info!(
+        "Animated cursor detected: {} frames, {}x{}, delay={}ms, webp={} bytes",
+        frame_count,
+        probe.width,
+        probe.height,
+        frame_delay,
+        webp_data.len()
+    );
+
+    let mut known_guard = KNOWN_ANIMATIONS.lock().unwrap();
+    let known = known_guard.get_or_insert_with(HashMap::new);
+    for hash in probe.hashes {
+        known.insert(hash, cursor_id.clone());
+    }
+
+    Ok(CachedCursor {
+        id: cursor_id,
+        webp_data,
+        width: probe.width,
+        height: probe.height,
+        hotspot_x: probe.hotspot.0,
+        hotspot_y: probe.hotspot.1,
+        is_animated: true,
+        frame_count,
+        frame_delay_ms: frame_delay,
+        name: String::new(),
+    })
+}
+
+/// Interval between fallback DPI checks.
+/// The reconfiguration callback is only delivered while a main run loop is
+/// running (true inside Sunshine, not for the standalone binary).
+const DPI_RECHECK_MS: u64 = 1000;
+
 // ─── Public API ─────────────────────────────────────────────────────────────
 
 /// Get system DPI scale factor (Retina = 2.0, non-Retina = 1.0).
@@ -159,8 +377,12 @@ pub fn get_dpi_scale() -> f32 {
 pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
     init_cache();
 
-    let dpi_scale = get_dpi_scale();
-    info!("Starting cursor capture on macOS (DPI scale: {:.2})", dpi_scale);
+    let dpi_scale = refresh_dpi_scale();
+    let poll_ms = poll_interval_ms();
+    info!(
+        "Starting cursor capture on macOS (DPI scale: {:.2}, poll: {} ms)",
+        dpi_scale, poll_ms
+    );
 
     // Verify CGS connection
     let conn = unsafe { CGSMainConnectionID() };
@@ -171,10 +393,52 @@ pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
     }
     info!("CGS connection established (id: {})", conn);
 
-    let mut poll_interval = interval(Duration::from_millis(16)); // ~60 fps
+    let err = unsafe {
+        CGDisplayRegisterReconfigurationCallback(on_display_reconfigured, std::ptr::null_mut())
+    };
+    if err != 0 {
+        warn!(
+            "CGDisplayRegisterReconfigurationCallback failed (error={})",
+            err
+        );
+    }
+
+    let mut poll_interval = interval(Duration::from_millis(poll_ms));
+    let dpi_recheck_polls = polls_for(DPI_RECHECK_MS);
+    let mut last_dpi = dpi_scale;
+    let mut polls_since_dpi_check = 0u32;
 
     loop {
-        poll_interval.tick().await;
+        if !wait_next_poll(&mut poll_interval).await {
+            info!("Shutdown requested, stopping cursor capture");
+            break;
+        }
+
+        polls_since_dpi_check += 1;
+        if polls_since_dpi_check >= dpi_recheck_polls {
+            polls_since_dpi_check = 0;
+            let dpi = refresh_dpi_scale();
+            if (dpi - last_dpi).abs() > 0.01 {
+                notify_display_change();
+            }
+        }
+        if take_display_change() {
+            last_dpi = refresh_dpi_scale();
+            info!(
+                "Display configuration changed (DPI scale now {:.2}), re-capturing cursor",
+                last_dpi
+            );
+            clear_cache();
+            reset_animation_probe();
+            *KNOWN_ANIMATIONS.lock().unwrap() = None;
+            *LAST_CURSOR_SEED.lock().unwrap() = -1;
+        }
+        if take_recapture_request() {
+            debug!("Re-capturing live cursor on client request");
+            evict_current_cursor();
+            reset_animation_probe();
+            *LAST_CURSOR_SEED.lock().unwrap() = -1;
+        }
 
         match capture_cursor() {
             Ok(Some(event)) => {
@@ -188,6 +452,12 @@ pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
                 warn!("Failed to capture cursor: {}", e);
             }
         }
+        if let Some(event) = cursor_position(conn).and_then(|(x, y)| position_event(x, y)) {
+            if tx.send(event).await.is_err() {
+                warn!("Receiver closed, stopping cursor capture");
+                break;
+            }
+        }
     }
 
     Ok(())
@@ -195,6 +465,30 @@ pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
 
 // ─── Internal helpers ───────────────────────────────────────────────────────
 
+/// CoreGraphics display reconfiguration callback.  Ignores the "begin"
+/// notification and flags the change once it has been applied.
+extern "C" fn on_display_reconfigured(
+    display_id: u32,
+    flags: u32,
+    _user_info: *mut std::ffi::c_void,
+) {
+    if flags & DISPLAY_BEGIN_CONFIGURATION_FLAG == 0 {
+        debug!("Display {} reconfigured (flags=0x{:x})", display_id, flags);
+        notify_display_change();
+    }
+}
+
+/// Current cursor location in global display coordinates, converted from
+/// points to pixels so it matches the (DPI-scaled) cursor images.
+fn cursor_position(conn: c_int) -> Option<(i32, i32)> {
+    let mut pt = CGPoint::default();
+    if unsafe { CGSGetCurrentCursorLocation(conn, &mut pt) } != 0 {
+        return None;
+    }
+    let scale = dpi_scale() as f64;
+    Some(((pt.x * scale).round() as i32, (pt.y * scale).round() as i32))
+}
+
 /// Try to obtain the current cursor image at native Retina resolution
 /// via the Cocoa NSCursor API.  Returns (rgba, w, h, hotspot_x, hotspot_y)
 /// with straight (un-premultiplied) alpha, or `None` on failure.
@@ -210,33 +504,34 @@ fn try_get_nscursor_rgba() -> Option<(Vec<u8>, u32, u32, i32, i32)> {
 unsafe fn try_get_nscursor_rgba_inner() -> Option<(Vec<u8>, u32, u32, i32, i32)> {
     // 1. [NSCursor currentSystemCursor]
     let cls = objc_getClass(b"NSCursor\0".as_ptr() as *const _);
-    if cls.is_null() { return None; }
-    let cursor = msg_send_id(cls, sel_registerName(b"currentSystemCursor\0".as_ptr() as *const _));
+    if cls.is_null() {
+        return None;
+    }
+    let cursor = msg_send_id(
+        cls,
+        sel_registerName(b"currentSystemCursor\0".as_ptr() as *const _),
+    );
     if cursor.is_null() {
         debug!("NSCursor.currentSystemCursor returned nil");
         return None;
     }
 
     // 2. Hotspot (in points)
-    let hotspot: CGPoint = msg_send_point(
-        cursor,
-        sel_registerName(b"hotSpot\0".as_ptr() as *const _),
-    );
+    let hotspot: CGPoint =
+        msg_send_point(cursor, sel_registerName(b"hotSpot\0".as_ptr() as *const _));
 
     // 3. [cursor image] -> NSImage
-    let image = msg_send_id(
-        cursor,
-        sel_registerName(b"image\0".as_ptr() as *const _),
-    );
-    if image.is_null() { return None; }
+    let image = msg_send_id(cursor, sel_registerName(b"image\0".as_ptr() as *const _));
+    if image.is_null() {
+        return None;
+    }
 
     // 4. Image logical size in points
     //    NSSize has the same binary layout as CGPoint: {f64, f64}
-    let ns_size: CGPoint = msg_send_point(
-        image,
-        sel_registerName(b"size\0".as_ptr() as *const _),
-    );
-    if ns_size.x <= 0.0 || ns_size.y <= 0.0 { return None; }
+    let ns_size: CGPoint = msg_send_point(image, sel_registerName(b"size\0".as_ptr() as *const _));
+    if ns_size.x <= 0.0 || ns_size.y <= 0.0 {
+        return None;
+    }
 
     // 5. Request CGImage at the best available resolution.
     //    CGImageForProposedRect takes a rect in *points*.  On a Retina
@@ -267,11 +562,15 @@ unsafe fn try_get_nscursor_rgba_inner() -> Option<(Vec<u8>, u32, u32, i32, i32)>
     // 6. Pixel dimensions of the CGImage (these are actual pixels, not points)
     let cg_w = CGImageGetWidth(cgimage);
     let cg_h = CGImageGetHeight(cgimage);
-    if cg_w == 0 || cg_h == 0 { return None; }
+    if cg_w == 0 || cg_h == 0 {
+        return None;
+    }
 
     // 7. Render CGImage into a premultiplied-RGBA bitmap context
     let color_space = CGColorSpaceCreateDeviceRGB();
-    if color_space.is_null() { return None; }
+    if color_space.is_null() {
+        return None;
+    }
     let row_bytes = cg_w * 4;
     let mut rgba = vec![0u8; cg_h * row_bytes];
     let ctx = CGBitmapContextCreate(
@@ -284,11 +583,16 @@ unsafe fn try_get_nscursor_rgba_inner() -> Option<(Vec<u8>, u32, u32, i32, i32)>
         BITMAP_INFO_RGBA_PREMUL,
     );
     CGColorSpaceRelease(color_space);
-    if ctx.is_null() { return None; }
+    if ctx.is_null() {
+        return None;
+    }
 
     let draw_rect = CGRect {
         origin: CGPoint { x: 0.0, y: 0.0 },
-        size: CGSize { width: cg_w as f64, height: cg_h as f64 },
+        size: CGSize {
+            width: cg_w as f64,
+            height: cg_h as f64,
+        },
     };
     CGContextDrawImage(ctx, draw_rect, cgimage);
     CGContextRelease(ctx);
@@ -297,7 +601,7 @@ unsafe fn try_get_nscursor_rgba_inner() -> Option<(Vec<u8>, u32, u32, i32, i32)>
     for i in (0..rgba.len()).step_by(4) {
         let a = rgba[i + 3] as u16;
         if a > 0 && a < 255 {
-            rgba[i]     = ((rgba[i]     as u16 * 255 + a / 2) / a).min(255) as u8;
+            rgba[i] = ((rgba[i] as u16 * 255 + a / 2) / a).min(255) as u8;
             rgba[i + 1] = ((rgba[i + 1] as u16 * 255 + a / 2) / a).min(255) as u8;
             rgba[i + 2] = ((rgba[i + 2] as u16 * 255 + a / 2) / a).min(255) as u8;
         }
@@ -316,42 +620,74 @@ unsafe fn try_get_nscursor_rgba_inner() -> Option<(Vec<u8>, u32, u32, i32, i32)>
     Some((rgba, cg_w as u32, cg_h as u32, hx, hy))
 }
 
-/// Bilinear-interpolation upscale (fallback when NSCursor is unavailable).
-fn bilinear_scale(src: &[u8], sw: u32, sh: u32, dw: u32, dh: u32) -> Vec<u8> {
-    let mut dst = vec![0u8; (dw * dh * 4) as usize];
-    let x_ratio = sw as f64 / dw as f64;
-    let y_ratio = sh as f64 / dh as f64;
-    for y in 0..dh {
-        let gy = y as f64 * y_ratio;
-        let yi = (gy as u32).min(sh - 1);
-        let yi1 = (yi + 1).min(sh - 1);
-        let yw = gy - yi as f64;
-        for x in 0..dw {
-            let gx = x as f64 * x_ratio;
-            let xi = (gx as u32).min(sw - 1);
-            let xi1 = (xi + 1).min(sw - 1);
-            let xw = gx - xi as f64;
-
-            let idx = |ix: u32, iy: u32| (iy * sw + ix) as usize * 4;
-            let di = (y * dw + x) as usize * 4;
-
-            for c in 0..4 {
-                let c00 = src[idx(xi, yi) + c] as f64;
-                let c10 = src[idx(xi1, yi) + c] as f64;
-                let c01 = src[idx(xi, yi1) + c] as f64;
-                let c11 = src[idx(xi1, yi1) + c] as f64;
-                let v = c00 * (1.0 - xw) * (1.0 - yw)
-                    + c10 * xw * (1.0 - yw)
-                    + c01 * (1.0 - xw) * yw
-                    + c11 * xw * yw;
-                dst[di + c] = v.round().clamp(0.0, 255.0) as u8;
+/// Whether a key was pressed recently or a modifier is held, i.e. a blank
+/// cursor is most likely AppKit hiding it while the user types.
+fn typing_recently() -> bool {
+    unsafe {
+        CGEventSourceFlagsState(EVENT_SOURCE_HID_SYSTEM_STATE) & MODIFIER_FLAGS_MASK != 0
+            || CGEventSourceSecondsSinceLastEventType(EVENT_SOURCE_HID_SYSTEM_STATE, EVENT_KEY_DOWN)
+                < TYPING_AUTO_HIDE_WINDOW_SECS
+    }
+}
+
+/// Capture current cursor and return event if changed.
+/// Layout of the pixels `CGSGetGlobalCursorData` returns, from its
+/// `components` / `bits_per_component`.  All are premultiplied, alpha first,
+/// in host (little-endian) order, so channels sit in memory low to high.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CgsPixelFormat {
+    /// 4 × 8 bit: B, G, R, A
+    Argb8,
+    /// 4 × 16 bit: B, G, R, A
+    Argb16,
+    /// 2 × 8 bit: gray, A
+    GrayAlpha8,
+    /// 2 × 16 bit: gray, A
+    GrayAlpha16,
+    /// 1 × 8 bit coverage mask, drawn black
+    Alpha8,
+}
+
+impl CgsPixelFormat {
+    fn from_cgs(components: c_int, bits_per_component: c_int) -> Option<Self> {
+        match (components, bits_per_component) {
+            (4, 8) => Some(Self::Argb8),
+            (4, 16) => Some(Self::Argb16),
+            (2, 8) => Some(Self::GrayAlpha8),
+            (2, 16) => Some(Self::GrayAlpha16),
+            (1, 8) => Some(Self::Alpha8),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Argb8 => 4,
+            Self::Argb16 => 8,
+            Self::GrayAlpha8 => 2,
+            Self::GrayAlpha16 => 4,
+            Self::Alpha8 => 1,
+        }
+    }
+
+    /// Premultiplied `[r, g, b, a]` at 8 bits per channel.
+    fn read(self, px: &[u8]) -> [u8; 4] {
+        // Round 16-bit channels to the nearest 8-bit value.
+        let c16 =
+            |i: usize| ((u16::from_le_bytes([px[i * 2], px[i * 2 + 1]]) as u32 + 128) / 257) as u8;
+        match self {
+            Self::Argb8 => [px[2], px[1], px[0], px[3]],
+            Self::Argb16 => [c16(2), c16(1), c16(0), c16(3)],
+            Self::GrayAlpha8 => [px[0], px[0], px[0], px[1]],
+            Self::GrayAlpha16 => {
+                let gray = c16(0);
+                [gray, gray, gray, c16(1)]
             }
+            Self::Alpha8 => [0, 0, 0, px[0]],
         }
     }
-    dst
 }
 
-/// Capture current cursor and return event if changed.
 fn capture_cursor() -> Result<Option<CursorEvent>> {
     unsafe {
         // NOTE: We intentionally skip CGCursorIsVisible() on macOS.
@@ -410,7 +746,15 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             return Err(anyhow!("CGSGetGlobalCursorData failed (error={})", err));
         }
 
-        let bytes_per_pixel = ((components * bits_per_component + 7) / 8) as usize;
+        let Some(format) = CgsPixelFormat::from_cgs(components, bits_per_component) else {
+            return Err(anyhow!(
+                "Unsupported cursor pixel format: {} components x {} bits (depth {})",
+                components,
+                bits_per_component,
+                depth
+            ));
+        };
+        let bytes_per_pixel = format.bytes_per_pixel();
 
         // Derive ACTUAL pixel dimensions from the data buffer layout.
         // On macOS Retina, rect.size may report logical-point dimensions
@@ -439,9 +783,7 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             (rect_w, rect_h)
         };
 
-        if width == 0 || height == 0 {
-            return Err(anyhow!("Cursor has zero dimensions ({}x{})", width, height));
-        }
+        check_cursor_dimensions(width, height)?;
 
         let hotspot_x = hotspot.x as i32;
         let hotspot_y = hotspot.y as i32;
@@ -452,20 +794,23 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
         let (hotspot_x, hotspot_y) = if rect_w > 0 && rect_w != width {
             let sx = width as f64 / rect_w as f64;
             let sy = height as f64 / rect_h.max(1) as f64;
-            ((hotspot_x as f64 * sx).round() as i32,
-             (hotspot_y as f64 * sy).round() as i32)
+            (
+                (hotspot_x as f64 * sx).round() as i32,
+                (hotspot_y as f64 * sy).round() as i32,
+            )
         } else {
             (hotspot_x, hotspot_y)
         };
 
         debug!(
-            "macOS cursor: {}x{} px (rect {}x{}), depth={}, comp={}, bpc={}, row_bytes={}, bpp={}, hotspot=({},{}), data_size={}",
-            width, height, rect_w, rect_h, depth, components, bits_per_component,
-            row_bytes, bytes_per_pixel, hotspot_x, hotspot_y, actual_size
+            "macOS cursor: {}x{} px (rect {}x{}), depth={}, format={:?}, row_bytes={}, hotspot=({},{}), data_size={}",
+            width, height, rect_w, rect_h, depth, format,
+            row_bytes, hotspot_x, hotspot_y, actual_size
         );
 
-        // Step 3 – convert from premultiplied ARGB (BGRA in LE memory) → straight RGBA
+        // Step 3 – convert from premultiplied (see CgsPixelFormat) → straight RGBA
         let mut rgba = vec![0u8; (width * height * 4) as usize];
+        let mut all_transparent = true;
 
         for y in 0..height {
             for x in 0..width {
@@ -476,35 +821,49 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
                     continue;
                 }
 
-                if bytes_per_pixel >= 4 {
-                    // macOS CoreGraphics stores premultiplied ARGB as 0xAARRGGBB.
-                    // On little-endian (Intel / Apple Silicon) the memory layout is:
-                    //   byte 0 = B, byte 1 = G, byte 2 = R, byte 3 = A
-                    let b = data[src] as u16;
-                    let g = data[src + 1] as u16;
-                    let r = data[src + 2] as u16;
-                    let a = data[src + 3];
-
-                    // Un-premultiply
-                    let (r, g, b) = if a > 0 && a < 255 {
-                        let af = a as u16;
-                        (
-                            ((r * 255 + af / 2) / af).min(255) as u8,
-                            ((g * 255 + af / 2) / af).min(255) as u8,
-                            ((b * 255 + af / 2) / af).min(255) as u8,
-                        )
-                    } else {
-                        (r as u8, g as u8, b as u8)
-                    };
-
-                    rgba[dst] = r;
-                    rgba[dst + 1] = g;
-                    rgba[dst + 2] = b;
-                    rgba[dst + 3] = a;
+                let [r, g, b, a] = format.read(&data[src..src + bytes_per_pixel]);
+                if a > 0 {
+                    all_transparent = false;
                 }
+
+                // Un-premultiply
+                let (r, g, b) = if a > 0 && a < 255 {
+                    let af = a as u16;
+                    let (r, g, b) = (r as u16, g as u16, b as u16);
+                    (
+                        ((r * 255 + af / 2) / af).min(255) as u8,
+                        ((g * 255 + af / 2) / af).min(255) as u8,
+                        ((b * 255 + af / 2) / af).min(255) as u8,
+                    )
+                } else {
+                    (r, g, b)
+                };
+
+                rgba[dst] = r;
+                rgba[dst + 1] = g;
+                rgba[dst + 2] = b;
+                rgba[dst + 3] = a;
             }
         }
 
+        // Detect "invisible" cursor (all pixels transparent → cursor hidden),
+        // as during full-screen video where CGCursorIsVisible() still reports
+        // a visible cursor.  Keyboard auto-hide also blanks the cursor; that
+        // case is left to the skip below so the client keeps the last shape.
+        // Forget the seed so the cursor is re-read every poll: it may come
+        // back without the seed changing.
+        if all_transparent && !typing_recently() {
+            *LAST_CURSOR_SEED.lock().unwrap() = -1;
+            reset_animation_probe();
+            let mut last_id = LAST_CURSOR_ID.lock().unwrap();
+            if last_id.is_some() {
+                *last_id = None;
+                debug!("Cursor appears hidden (fully transparent)");
+                return Ok(Some(CursorEvent::CursorHidden { seat: DEFAULT_SEAT }));
+            }
+            return Ok(None);
+        }
+
         // Step 4 – obtain the best-resolution cursor image.
         //
         // On Retina displays CGS typically returns 1× (point) pixel data.
@@ -512,7 +871,7 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
         //   a) check whether CGS already delivered native-res data,
         //   b) try the Cocoa NSCursor API which provides native @2× images,
         //   c) fall back to bilinear interpolation if NSCursor fails.
-        let dpi = get_dpi_scale();
+        let dpi = dpi_scale();
         let scale = dpi.round() as u32; // 1 or 2
 
         // Did CGS already return data at display resolution?
@@ -528,15 +887,20 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
                 );
                 (ns_rgba, ns_w, ns_h, ns_hx, ns_hy)
             } else {
-                // NSCursor unavailable (custom cursor?) – bilinear upscale.
+                // NSCursor unavailable (custom cursor?) – interpolated upscale.
                 let sw = width * scale;
                 let sh = height * scale;
-                let scaled = bilinear_scale(&rgba, width, height, sw, sh);
+                let scaled = scale_rgba(&rgba, width, height, sw, sh);
                 let shx = hotspot_x * scale as i32;
                 let shy = hotspot_y * scale as i32;
                 debug!(
-                    "Bilinear upscaled cursor {}x{} -> {}x{} (DPI scale {})",
-                    width, height, sw, sh, scale
+                    "{:?} upscaled cursor {}x{} -> {}x{} (DPI scale {})",
+                    scale_filter(),
+                    width,
+                    height,
+                    sw,
+                    sh,
+                    scale
                 );
                 (scaled, sw, sh, shx, shy)
             }
@@ -557,7 +921,19 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             return Ok(None);
         }
 
-        let cursor_id = format!("cur_{}", &blake3::hash(&final_rgba).to_hex()[..12]);
+        match track_animation(&final_rgba, final_w, final_h, final_hx, final_hy) {
+            AnimationStep::Playing => return Ok(None),
+            AnimationStep::Animated(cached) => {
+                let (cursor_id, _) = cache_cursor(cached);
+                return Ok(Some(CursorEvent::CursorChanged {
+                    seat: DEFAULT_SEAT,
+                    cursor_id,
+                }));
+            }
+            AnimationStep::Static => {}
+        }
+
+        let cursor_id = cursor_id("cur", &final_rgba, final_w, final_h, final_hx, final_hy);
         let webp_data = encode_static_webp(&final_rgba, final_w, final_h)?;
 
         let cached = CachedCursor {
@@ -570,9 +946,13 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             is_animated: false,
             frame_count: 1,
             frame_delay_ms: 0,
+            name: String::new(),
         };
 
         let (cursor_id, _) = cache_cursor(cached);
-        Ok(Some(CursorEvent::CursorChanged(cursor_id)))
+        Ok(Some(CursorEvent::CursorChanged {
+            seat: DEFAULT_SEAT,
+            cursor_id,
+        }))
     }
 }
@@ -1,36 +1,72 @@
 use anyhow::Result;
 use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Interval};
 use tracing::debug;
 
 use crate::cursor::{
-    cursor_message::Payload, CursorData, CursorMessage, MessageType,
+    cursor_message::Payload, CursorData, CursorHide, CursorMessage, CursorPosition, ImageCodec,
+    MessageType,
 };
 
+mod capturer;
+mod scale;
+pub use self::capturer::CursorCapturer;
+
 // Platform-specific modules
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use self::windows::{run_cursor_capture, get_dpi_scale};
+pub use self::windows::{get_dpi_scale, run_cursor_capture};
 
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
-pub use self::macos::{run_cursor_capture, get_dpi_scale};
+pub use self::macos::{get_dpi_scale, run_cursor_capture};
 
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "linux")]
-pub use self::linux::{run_cursor_capture, get_dpi_scale};
+mod linux_wayland;
+#[cfg(target_os = "linux")]
+pub use self::linux::{get_dpi_scale, run_cursor_capture};
+
+/// Pointer a cursor event belongs to, for sessions with several cursors.
+pub type SeatId = u32;
+
+/// The seat of the system pointer.  Every current platform backend captures
+/// just this one.
+pub const DEFAULT_SEAT: SeatId = 0;
 
 /// Cursor event for broadcasting to clients
 #[derive(Clone, Debug)]
 pub enum CursorEvent {
     /// Cursor changed - carries cursor_id
-    CursorChanged(String),
+    CursorChanged { seat: SeatId, cursor_id: String },
     /// Cursor hidden
-    CursorHidden,
+    CursorHidden { seat: SeatId },
+    /// Cursor moved - screen position in physical pixels
+    Moved { seat: SeatId, x: i32, y: i32 },
+}
+
+impl CursorEvent {
+    pub fn seat(&self) -> SeatId {
+        match self {
+            CursorEvent::CursorChanged { seat, .. }
+            | CursorEvent::CursorHidden { seat }
+            | CursorEvent::Moved { seat, .. } => *seat,
+        }
+    }
+
+    /// `CursorChanged` for `id`, or `CursorHidden` without one, on `seat`.
+    pub fn current(seat: SeatId, id: Option<String>) -> Self {
+        match id {
+            Some(cursor_id) => CursorEvent::CursorChanged { seat, cursor_id },
+            None => CursorEvent::CursorHidden { seat },
+        }
+    }
 }
 
 /// Cached cursor data with pre-encoded WebP (static or animated)
@@ -46,23 +82,277 @@ pub struct CachedCursor {
     pub is_animated: bool,
     pub frame_count: u32,
     pub frame_delay_ms: u32,
+    /// Native cursor name (X11 cursor theme name); empty where unavailable
+    pub name: String,
+}
+
+/// Cache size that triggers eviction.
+const CACHE_MAX_ENTRIES: usize = 50;
+/// Entries kept after an eviction pass.
+const CACHE_TRIM_TO: usize = 25;
+
+/// Cursor cache with least-recently-used eviction.  Each lookup or insert
+/// stamps the entry with a monotonically increasing tick; eviction drops the
+/// oldest stamps, so the cursor currently on screen is never the one removed.
+/// Holds cursors by default; other per-cursor data (encodings) can be kept
+/// under the same policy.
+pub(crate) struct CursorCache<T = CachedCursor> {
+    entries: HashMap<String, (T, u64)>,
+    tick: u64,
+}
+
+impl<T> CursorCache<T> {
+    fn new() -> Self {
+        CursorCache {
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Look up a cursor, marking it as recently used.
+    fn get(&mut self, cursor_id: &str) -> Option<&T> {
+        let tick = self.next_tick();
+        let (cursor, last_used) = self.entries.get_mut(cursor_id)?;
+        *last_used = tick;
+        Some(cursor)
+    }
+
+    /// Insert `value` under `key` as most recently used, evicting the least
+    /// recently used entries once the cache exceeds `CACHE_MAX_ENTRIES`.
+    fn insert_as(&mut self, key: String, value: T) {
+        let tick = self.next_tick();
+        self.entries.insert(key, (value, tick));
+
+        if self.entries.len() > CACHE_MAX_ENTRIES {
+            let mut by_age: Vec<(u64, String)> = self
+                .entries
+                .iter()
+                .map(|(id, (_, last_used))| (*last_used, id.clone()))
+                .collect();
+            by_age.sort_unstable();
+            let excess = self.entries.len() - CACHE_TRIM_TO;
+            for (_, id) in by_age.into_iter().take(excess) {
+                self.entries.remove(&id);
+            }
+            debug!("Cache trimmed to {} entries (LRU)", self.entries.len());
+        }
+    }
+
+    fn remove(&mut self, cursor_id: &str) -> bool {
+        self.entries.remove(cursor_id).is_some()
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl CursorCache {
+    /// Insert a cursor under its id; see [`CursorCache::insert_as`].
+    fn insert(&mut self, cursor: CachedCursor) {
+        self.insert_as(cursor.id.clone(), cursor);
+    }
 }
 
 /// Global cursor cache: cursor_id -> cached cursor (with pre-encoded WebP)
-pub(crate) static CURSOR_CACHE: Mutex<Option<HashMap<String, CachedCursor>>> = Mutex::new(None);
+pub(crate) static CURSOR_CACHE: Mutex<Option<CursorCache>> = Mutex::new(None);
+
+/// Copies of cached cursors resized for a client's `preferred_cursor_size`,
+/// keyed `<cursor_id>@<size>`.
+static SIZED_CACHE: Mutex<Option<CursorCache>> = Mutex::new(None);
+
+/// AVIF encodings of cached (possibly resized or padded) cursors, keyed
+/// `<cursor_id>@<hash of the WebP>`.
+static AVIF_CACHE: Mutex<Option<CursorCache<Vec<u8>>>> = Mutex::new(None);
 
-/// Last cursor_id for detecting changes
+/// Last cursor_id for detecting changes ([`DEFAULT_SEAT`])
 pub(crate) static LAST_CURSOR_ID: Mutex<Option<String>> = Mutex::new(None);
 
-/// Get the last cursor_id
+/// Default cursor poll interval (~60 fps).
+const DEFAULT_POLL_MS: u64 = 16;
+/// Accepted range for `CURSOR_POLL_MS`.
+const MIN_POLL_MS: u64 = 4;
+const MAX_POLL_MS: u64 = 1000;
+
+/// Cursor poll interval in milliseconds, from `CURSOR_POLL_MS` (default 16,
+/// clamped to 4–1000).  Read once on first use.
+pub(crate) fn poll_interval_ms() -> u64 {
+    static POLL_MS: OnceLock<u64> = OnceLock::new();
+    *POLL_MS.get_or_init(|| {
+        let Ok(raw) = std::env::var("CURSOR_POLL_MS") else {
+            return DEFAULT_POLL_MS;
+        };
+        match raw.trim().parse::<u64>() {
+            Ok(ms) => {
+                let clamped = ms.clamp(MIN_POLL_MS, MAX_POLL_MS);
+                if clamped != ms {
+                    tracing::warn!("CURSOR_POLL_MS={} out of range, using {} ms", ms, clamped);
+                }
+                clamped
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "Invalid CURSOR_POLL_MS={:?}, using {} ms",
+                    raw,
+                    DEFAULT_POLL_MS
+                );
+                DEFAULT_POLL_MS
+            }
+        }
+    })
+}
+
+/// Poll interval used while no client is connected.
+const IDLE_POLL_MS: u64 = 250;
+
+/// Wait for the next capture poll.  Ticks `active` (the configured interval)
+/// while clients are connected; otherwise backs off to `IDLE_POLL_MS`, waking
+/// early as soon as the first client connects so its initial cursor isn't
+/// delayed.  Returns `false` once shutdown is requested.
+pub(crate) async fn wait_next_poll(active: &mut Interval) -> bool {
+    if crate::metrics::connected_clients() > 0 {
+        tokio::select! {
+            _ = active.tick() => return true,
+            _ = crate::shutdown::requested() => return false,
+        }
+    }
+
+    let idle = Duration::from_millis(IDLE_POLL_MS.max(poll_interval_ms()));
+    tokio::select! {
+        _ = tokio::time::sleep(idle) => {}
+        _ = crate::metrics::client_arrived() => {
+            debug!("Client connected, resuming full-rate cursor polling");
+        }
+        _ = crate::shutdown::requested() => return false,
+    }
+    active.reset();
+    true
+}
+
+/// Number of polls spanning at least `ms` milliseconds at the configured
+/// interval (never less than one).
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+pub(crate) fn polls_for(ms: u64) -> u32 {
+    ms.div_ceil(poll_interval_ms()).max(1) as u32
+}
+
+/// Bounds for an animated cursor's single `frame_delay_ms`.
+pub(crate) const MIN_FRAME_DELAY_MS: u32 = 16;
+pub(crate) const MAX_FRAME_DELAY_MS: u32 = 1000;
+
+/// Fold a measured frame interval into an animation's frame-delay estimate:
+/// an exponential moving average giving the new sample a quarter of the
+/// weight, so one late poll barely moves it, clamped to
+/// [`MIN_FRAME_DELAY_MS`]..=[`MAX_FRAME_DELAY_MS`].  A `prev` of 0 means no
+/// estimate yet and takes the sample as is.
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+pub(crate) fn estimate_frame_delay(prev: u32, sample: u32) -> u32 {
+    if prev == 0 {
+        return sample.clamp(MIN_FRAME_DELAY_MS, MAX_FRAME_DELAY_MS);
+    }
+    // Round the step away from zero so steady samples are reached exactly.
+    let diff = sample as i64 - prev as i64;
+    let average = prev as i64 + (diff + diff.signum() * 3) / 4;
+    average.clamp(MIN_FRAME_DELAY_MS as i64, MAX_FRAME_DELAY_MS as i64) as u32
+}
+
+/// Minimum interval between position events (caps them at ~60/s).
+const MIN_POSITION_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Last position emitted as `CursorEvent::Moved`, and when.
+static LAST_POSITION: Mutex<Option<((i32, i32), Instant)>> = Mutex::new(None);
+
+/// Turn a sampled cursor position into a `Moved` event, if it differs from
+/// the last one sent and the throttle interval has elapsed.  A position held
+/// back by the throttle is picked up by a later poll.
+pub(crate) fn position_event(x: i32, y: i32) -> Option<CursorEvent> {
+    let mut last = LAST_POSITION.lock().unwrap();
+    if let Some((pos, at)) = *last {
+        if pos == (x, y) || at.elapsed() < MIN_POSITION_INTERVAL {
+            return None;
+        }
+    }
+    *last = Some(((x, y), Instant::now()));
+    Some(CursorEvent::Moved {
+        seat: DEFAULT_SEAT,
+        x,
+        y,
+    })
+}
+
+/// Set by the platform display-change listeners (resolution, scaling or
+/// monitor layout changed); consumed by the capture loop.
+static DISPLAY_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Flag a display-configuration change.  Safe to call from any thread,
+/// including OS callbacks.
+pub(crate) fn notify_display_change() {
+    DISPLAY_CHANGED.store(true, Ordering::SeqCst);
+}
+
+/// Consume a pending display-configuration change, returning whether one occurred.
+pub(crate) fn take_display_change() -> bool {
+    DISPLAY_CHANGED.swap(false, Ordering::SeqCst)
+}
+
+/// System DPI scale as `f32` bits, cached so hot paths skip the platform
+/// query; 0 until first read.
+static DPI_SCALE: AtomicU32 = AtomicU32::new(0);
+
+/// System DPI scale factor (1.0 = 96 DPI), read from the platform on first
+/// use and cached until the next [`refresh_dpi_scale`].
+pub fn dpi_scale() -> f32 {
+    match DPI_SCALE.load(Ordering::Relaxed) {
+        0 => refresh_dpi_scale(),
+        bits => f32::from_bits(bits),
+    }
+}
+
+/// Re-read the system DPI scale and cache it.  The capture loops call this
+/// when they start and on display changes.
+pub fn refresh_dpi_scale() -> f32 {
+    let scale = get_dpi_scale();
+    DPI_SCALE.store(scale.to_bits(), Ordering::Relaxed);
+    scale
+}
+
+/// Set when a client asks for the live cursor to be re-captured from the OS;
+/// consumed by the capture loop.
+static RECAPTURE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Ask the capture loop to re-capture the current cursor, bypassing the cache.
+pub fn request_recapture() {
+    RECAPTURE_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Consume a pending re-capture request, returning whether one occurred.
+pub(crate) fn take_recapture_request() -> bool {
+    RECAPTURE_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Get the last cursor_id of [`DEFAULT_SEAT`]
 pub fn get_last_cursor_id() -> Option<String> {
     LAST_CURSOR_ID.lock().unwrap().clone()
 }
 
 /// Get cached cursor by id
 pub fn get_cached_cursor(cursor_id: &str) -> Option<CachedCursor> {
-    let cache_guard = CURSOR_CACHE.lock().unwrap();
-    cache_guard.as_ref()?.get(cursor_id).cloned()
+    let mut cache_guard = CURSOR_CACHE.lock().unwrap();
+    cache_guard.as_mut()?.get(cursor_id).cloned()
+}
+
+/// Number of cursors currently held in the cache
+pub(crate) fn cache_len() -> usize {
+    CURSOR_CACHE.lock().unwrap().as_ref().map_or(0, |c| c.len())
 }
 
 /// Create a CursorMessage with the cursor image.
@@ -71,39 +361,150 @@ pub fn get_cached_cursor(cursor_id: &str) -> Option<CachedCursor> {
 /// - macOS: CGS 1× data upscaled by DPI in `capture_cursor()`.
 /// - Other platforms: physical-pixel data as-is.
 ///
-/// Width/height/hotspot are sent as-is. `dpi_scale` is set to 1.0
-/// because the image already matches the intended display size.
-pub fn create_scaled_cursor_message(cursor_id: &str, _client_dpr: f32) -> Option<CursorMessage> {
-    let cached = get_cached_cursor(cursor_id)?;
+/// Width/height/hotspot are sent as-is. On Windows `dpi_scale` is the DPI
+/// scale of the monitor the cursor is on, so mixed-DPI setups size the
+/// cursor correctly; elsewhere it is 1.0 because the image already matches
+/// the intended display size.
+///
+/// With a `preferred_size` the image is instead resized to fit a
+/// `preferred_size`×`preferred_size` box (see `resized_cursor`) and sent
+/// with `dpi_scale` 1.0.
+///
+/// With `CURSOR_PAD_POT` the (resized) image is then padded to a
+/// power-of-two canvas (see `pad_to_power_of_two`); the content rect in
+/// the message says where the cursor sits in it.
+///
+/// `codec` is the client's negotiated codec; animated cursors always go out
+/// as WebP.
+pub fn create_scaled_cursor_message(
+    seat: SeatId,
+    cursor_id: &str,
+    _client_dpr: f32,
+    preferred_size: Option<u32>,
+    codec: CursorCodec,
+    quality: CursorQuality,
+) -> Option<CursorMessage> {
+    let mut cached = get_cached_cursor(cursor_id)?;
+
+    #[cfg(target_os = "windows")]
+    let mut dpi_scale = self::windows::cached_cursor_monitor_dpi_scale();
+    #[cfg(not(target_os = "windows"))]
+    let mut dpi_scale = 1.0;
+
+    if let Some(size) = preferred_size {
+        match resized_cursor(&cached, size) {
+            Ok(resized) => {
+                cached = resized;
+                dpi_scale = 1.0;
+            }
+            Err(e) => tracing::warn!(
+                "Cannot resize cursor {} to {}px, sending as-is: {:#}",
+                cursor_id,
+                size,
+                e
+            ),
+        }
+    }
+
+    if quality == CursorQuality::Preview && cached.width.max(cached.height) > PREVIEW_CURSOR_SIZE {
+        match resized_cursor(&cached, PREVIEW_CURSOR_SIZE) {
+            Ok(resized) => {
+                cached = resized;
+                dpi_scale = 1.0;
+            }
+            Err(e) => tracing::warn!(
+                "Cannot shrink cursor {} for preview, sending as-is: {:#}",
+                cursor_id,
+                e
+            ),
+        }
+    }
+
+    let mut content = (0, 0, cached.width, cached.height);
+    if let Some(placement) = pot_placement() {
+        match pad_to_power_of_two(&cached, placement) {
+            Ok((padded, (x, y))) => {
+                content = (x, y, cached.width, cached.height);
+                cached = padded;
+            }
+            Err(e) => tracing::warn!(
+                "Cannot pad cursor {} to a power of two, sending as-is: {:#}",
+                cursor_id,
+                e
+            ),
+        }
+    }
+
+    let (image_data, codec) = match codec {
+        CursorCodec::Avif if !cached.is_animated => match avif_image(&cached) {
+            Ok(data) => (data, CursorCodec::Avif),
+            Err(e) => {
+                tracing::warn!(
+                    "Cannot encode cursor {} as AVIF, sending WebP: {:#}",
+                    cursor_id,
+                    e
+                );
+                (cached.webp_data.clone(), CursorCodec::Webp)
+            }
+        },
+        _ => (cached.webp_data.clone(), CursorCodec::Webp),
+    };
 
     debug!(
-        "Cursor message: id={}, {}x{}, webp={} bytes, animated={}, frames={}",
-        cached.id, cached.width, cached.height,
-        cached.webp_data.len(), cached.is_animated, cached.frame_count
+        "Cursor message: id={}, {}x{}, {:?} {} bytes, animated={}, frames={}",
+        cached.id,
+        cached.width,
+        cached.height,
+        codec,
+        image_data.len(),
+        cached.is_animated,
+        cached.frame_count
     );
 
     Some(CursorMessage {
         r#type: MessageType::CursorData.into(),
         payload: Some(Payload::CursorData(CursorData {
             cursor_id: cached.id.clone(),
-            image_data: cached.webp_data.clone(),
+            image_data,
+            codec: codec.to_proto().into(),
             width: cached.width as i32,
             height: cached.height as i32,
             hotspot_x: cached.hotspot_x,
             hotspot_y: cached.hotspot_y,
-            dpi_scale: 1.0,
+            dpi_scale,
             is_animated: cached.is_animated,
             frame_delay_ms: cached.frame_delay_ms,
+            name: cached.name.clone(),
+            seat_id: seat,
+            content_x: content.0,
+            content_y: content.1,
+            content_width: content.2,
+            content_height: content.3,
         })),
         timestamp: get_timestamp(),
     })
 }
 
+/// Create cursor position message
+pub fn create_position_message(seat: SeatId, x: i32, y: i32) -> CursorMessage {
+    let timestamp = get_timestamp();
+    CursorMessage {
+        r#type: MessageType::CursorPosition.into(),
+        payload: Some(Payload::CursorPosition(CursorPosition {
+            x,
+            y,
+            timestamp,
+            seat_id: seat,
+        })),
+        timestamp,
+    }
+}
+
 /// Create cursor hide message
-pub fn create_hide_message() -> CursorMessage {
+pub fn create_hide_message(seat: SeatId) -> CursorMessage {
     CursorMessage {
         r#type: MessageType::CursorHide.into(),
-        payload: None,
+        payload: Some(Payload::CursorHide(CursorHide { seat_id: seat })),
         timestamp: get_timestamp(),
     }
 }
@@ -112,11 +513,247 @@ pub fn create_hide_message() -> CursorMessage {
 pub(crate) fn init_cache() {
     let mut cache = CURSOR_CACHE.lock().unwrap();
     if cache.is_none() {
-        *cache = Some(HashMap::new());
+        *cache = Some(CursorCache::new());
         tracing::info!("Cursor cache initialized");
     }
 }
 
+/// Drop every cached cursor.  Used after a display change, when cached
+/// images were captured at a now-stale scale.
+pub(crate) fn clear_cache() {
+    if let Some(cache) = CURSOR_CACHE.lock().unwrap().as_mut() {
+        let dropped = cache.len();
+        cache.clear();
+        debug!("Cursor cache cleared ({} entries dropped)", dropped);
+    }
+    if let Some(sized) = SIZED_CACHE.lock().unwrap().as_mut() {
+        sized.clear();
+    }
+    *AVIF_CACHE.lock().unwrap() = None;
+}
+
+/// Drop the current cursor's cache entry so the next capture re-encodes it
+/// instead of reusing possibly bad bytes.
+pub(crate) fn evict_current_cursor() {
+    let Some(id) = get_last_cursor_id() else {
+        return;
+    };
+    if let Some(cache) = CURSOR_CACHE.lock().unwrap().as_mut() {
+        if cache.remove(&id) {
+            debug!("Evicted cursor {} for re-capture", id);
+        }
+    }
+    let is_variant = |key: &str| key.split_once('@').map(|(base, _)| base) == Some(id.as_str());
+    if let Some(sized) = SIZED_CACHE.lock().unwrap().as_mut() {
+        sized.entries.retain(|key, _| !is_variant(key));
+    }
+    if let Some(avif) = AVIF_CACHE.lock().unwrap().as_mut() {
+        avif.entries.retain(|key, _| !is_variant(key));
+    }
+}
+
+/// `cached` resized to fit a `size`×`size` box, keeping its aspect ratio,
+/// with the hotspot scaled to match.  Animated cursors keep their frame
+/// timing.  The id is unchanged, so signals for the cursor still apply.
+/// Results are memoized per size.
+fn resized_cursor(cached: &CachedCursor, size: u32) -> Result<CachedCursor> {
+    check_cursor_dimensions(size, size)?;
+    let factor = size as f64 / cached.width.max(cached.height) as f64;
+    let dw = ((cached.width as f64 * factor).round() as u32).clamp(1, size);
+    let dh = ((cached.height as f64 * factor).round() as u32).clamp(1, size);
+    if (dw, dh) == (cached.width, cached.height) {
+        return Ok(cached.clone());
+    }
+
+    // Only the lookup and insert hold the lock; the encode runs without it
+    // so other clients' lookups don't wait on it.
+    let key = format!("{}@{}", cached.id, size);
+    if let Some(hit) = SIZED_CACHE
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|c| c.get(&key))
+    {
+        return Ok(hit.clone());
+    }
+
+    let webp_data = if cached.is_animated {
+        let decoder = webp_animation::Decoder::new(&cached.webp_data)
+            .map_err(|e| anyhow::anyhow!("Invalid animated WebP: {:?}", e))?;
+        let mut frames = Vec::new();
+        let mut delays = Vec::new();
+        // Decoder timestamps mark the end of each frame
+        let mut prev_end = 0;
+        for frame in decoder {
+            frames.push(scale::scale_rgba(
+                frame.data(),
+                cached.width,
+                cached.height,
+                dw,
+                dh,
+            ));
+            delays.push(frame.timestamp() - prev_end);
+            prev_end = frame.timestamp();
+        }
+        encode_animated_webp_with_delays(&frames, dw, dh, &delays)?
+    } else {
+        let rgba = decode_cursor_rgba(cached)?;
+        encode_static_webp(
+            &scale::scale_rgba(&rgba, cached.width, cached.height, dw, dh),
+            dw,
+            dh,
+        )?
+    };
+
+    let scale_hotspot = |v: i32, from: u32, to: u32| {
+        ((v as f64 * to as f64 / from as f64).round() as i32).clamp(0, to as i32 - 1)
+    };
+    let resized = CachedCursor {
+        webp_data,
+        width: dw,
+        height: dh,
+        hotspot_x: scale_hotspot(cached.hotspot_x, cached.width, dw),
+        hotspot_y: scale_hotspot(cached.hotspot_y, cached.height, dh),
+        ..cached.clone()
+    };
+    debug!(
+        "Resized cursor {} {}x{} -> {}x{} ({} bytes)",
+        cached.id,
+        cached.width,
+        cached.height,
+        dw,
+        dh,
+        resized.webp_data.len()
+    );
+    SIZED_CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(CursorCache::new)
+        .insert(CachedCursor {
+            id: key,
+            ..resized.clone()
+        });
+    Ok(resized)
+}
+
+/// Where a cursor sits on a power-of-two canvas (`CURSOR_PAD_POT`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PotPlacement {
+    TopLeft,
+    Center,
+}
+
+/// `CURSOR_PAD_POT`: `top-left` (or `1`/`true`) or `center` pads every
+/// cursor to a power-of-two canvas for clients that want such textures.
+/// Unset or `0`/`off` leaves cursors as captured.  Read once.
+fn pot_placement() -> Option<PotPlacement> {
+    static PLACEMENT: OnceLock<Option<PotPlacement>> = OnceLock::new();
+    *PLACEMENT.get_or_init(|| {
+        let placement = match std::env::var("CURSOR_PAD_POT") {
+            Err(_) => None,
+            Ok(v) => match v.trim().to_ascii_lowercase().as_str() {
+                "" | "0" | "false" | "off" | "no" => None,
+                "1" | "true" | "on" | "yes" | "top-left" | "topleft" => Some(PotPlacement::TopLeft),
+                "center" | "centre" => Some(PotPlacement::Center),
+                _ => {
+                    tracing::warn!(
+                        "Invalid CURSOR_PAD_POT={:?} (top-left or center), not padding",
+                        v
+                    );
+                    None
+                }
+            },
+        };
+        if let Some(placement) = placement {
+            tracing::info!("Padding cursors to power-of-two canvases ({:?})", placement);
+        }
+        placement
+    })
+}
+
+/// `cached` on a transparent canvas whose sides are the next powers of two,
+/// placed per `placement`, with the hotspot shifted by the same offset (as
+/// [`expand_canvas`] padding does for XOR outlines).  Returns the padded
+/// cursor and the offset of the original image in it; the id is unchanged.
+/// Results are memoized.
+fn pad_to_power_of_two(
+    cached: &CachedCursor,
+    placement: PotPlacement,
+) -> Result<(CachedCursor, (u32, u32))> {
+    let (pw, ph) = (
+        cached.width.next_power_of_two(),
+        cached.height.next_power_of_two(),
+    );
+    check_cursor_dimensions(pw, ph)?;
+    let (ox, oy) = match placement {
+        PotPlacement::TopLeft => (0, 0),
+        PotPlacement::Center => ((pw - cached.width) / 2, (ph - cached.height) / 2),
+    };
+    if (pw, ph) == (cached.width, cached.height) {
+        return Ok((cached.clone(), (0, 0)));
+    }
+
+    let key = format!(
+        "{}@{}x{}+{},{}@pot",
+        cached.id, cached.width, cached.height, ox, oy
+    );
+    // Encode outside the lock, as in `resized_cursor`
+    if let Some(hit) = SIZED_CACHE
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|c| c.get(&key))
+    {
+        return Ok((
+            CachedCursor {
+                id: cached.id.clone(),
+                ..hit.clone()
+            },
+            (ox, oy),
+        ));
+    }
+
+    let place = |rgba: &[u8]| place_on_canvas(rgba, cached.width, cached.height, pw, ph, ox, oy);
+    let webp_data = if cached.is_animated {
+        let decoder = webp_animation::Decoder::new(&cached.webp_data)
+            .map_err(|e| anyhow::anyhow!("Invalid animated WebP: {:?}", e))?;
+        let mut frames = Vec::new();
+        let mut delays = Vec::new();
+        // Decoder timestamps mark the end of each frame
+        let mut prev_end = 0;
+        for frame in decoder {
+            frames.push(place(frame.data())?);
+            delays.push(frame.timestamp() - prev_end);
+            prev_end = frame.timestamp();
+        }
+        encode_animated_webp_with_delays(&frames, pw, ph, &delays)?
+    } else {
+        encode_static_webp(&place(&decode_cursor_rgba(cached)?)?, pw, ph)?
+    };
+
+    let padded = CachedCursor {
+        webp_data,
+        width: pw,
+        height: ph,
+        hotspot_x: cached.hotspot_x + ox as i32,
+        hotspot_y: cached.hotspot_y + oy as i32,
+        ..cached.clone()
+    };
+    debug!(
+        "Padded cursor {} {}x{} -> {}x{} at {},{}",
+        cached.id, cached.width, cached.height, pw, ph, ox, oy
+    );
+    SIZED_CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(CursorCache::new)
+        .insert(CachedCursor {
+            id: key,
+            ..padded.clone()
+        });
+    Ok((padded, (ox, oy)))
+}
+
 /// Store a cursor in cache and return cursor_id. Returns (cursor_id, is_new).
 pub(crate) fn cache_cursor(cached: CachedCursor) -> (String, bool) {
     let cursor_id = cached.id.clone();
@@ -126,9 +763,9 @@ pub(crate) fn cache_cursor(cached: CachedCursor) -> (String, bool) {
 
     // Check if already in cache
     {
-        let cache_guard = CURSOR_CACHE.lock().unwrap();
-        let cache = cache_guard.as_ref().unwrap();
-        if cache.contains_key(&cursor_id) {
+        let mut cache_guard = CURSOR_CACHE.lock().unwrap();
+        let cache = cache_guard.as_mut().unwrap();
+        if cache.get(&cursor_id).is_some() {
             debug!("Cursor already cached: {}", cursor_id);
             return (cursor_id, false);
         }
@@ -137,83 +774,696 @@ pub(crate) fn cache_cursor(cached: CachedCursor) -> (String, bool) {
     // Cache the new cursor
     tracing::info!(
         "New cursor: id={}, {}x{}, animated={}, frames={}, webp={} bytes",
-        cursor_id, cached.width, cached.height,
-        cached.is_animated, cached.frame_count, cached.webp_data.len()
+        cursor_id,
+        cached.width,
+        cached.height,
+        cached.is_animated,
+        cached.frame_count,
+        cached.webp_data.len()
     );
 
     {
         let mut cache_guard = CURSOR_CACHE.lock().unwrap();
         let cache = cache_guard.as_mut().unwrap();
-        cache.insert(cursor_id.clone(), cached);
+        cache.insert(cached);
+    }
+
+    (cursor_id, true)
+}
 
-        // Trim cache if too large
-        if cache.len() > 50 {
-            let keys: Vec<_> = cache.keys().cloned().collect();
-            for key in keys.iter().take(25) {
-                cache.remove(key);
+/// Hash of everything a client needs to draw a cursor: `content` (RGBA for
+/// a static cursor, the frame hashes for an animated one) plus its size and
+/// hotspot.  Identical pixels with a different hotspot hash differently.
+pub(crate) fn cursor_hash(
+    content: &[u8],
+    width: u32,
+    height: u32,
+    hotspot_x: i32,
+    hotspot_y: i32,
+) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(content);
+    hasher.update(&width.to_le_bytes());
+    hasher.update(&height.to_le_bytes());
+    hasher.update(&hotspot_x.to_le_bytes());
+    hasher.update(&hotspot_y.to_le_bytes());
+    hasher.finalize()
+}
+
+/// Cache id (`<prefix>_<hash>`) for a cursor; see [`cursor_hash`].
+pub(crate) fn cursor_id(
+    prefix: &str,
+    content: &[u8],
+    width: u32,
+    height: u32,
+    hotspot_x: i32,
+    hotspot_y: i32,
+) -> String {
+    id_from_hash(
+        prefix,
+        &cursor_hash(content, width, height, hotspot_x, hotspot_y),
+    )
+}
+
+pub(crate) fn id_from_hash(prefix: &str, hash: &blake3::Hash) -> String {
+    format!("{}_{}", prefix, &hash.to_hex()[..12])
+}
+
+/// Image codec of a `CursorData` payload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorCodec {
+    #[default]
+    Webp,
+    /// Static cursors only (needs the `avif` feature)
+    Avif,
+}
+
+impl CursorCodec {
+    /// Parse a codec name (`webp`, `avif`) as used by `CURSOR_IMAGE_CODEC`
+    /// and client configs.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "webp" => Some(CursorCodec::Webp),
+            "avif" => Some(CursorCodec::Avif),
+            _ => None,
+        }
+    }
+
+    pub fn to_proto(self) -> ImageCodec {
+        match self {
+            CursorCodec::Webp => ImageCodec::Webp,
+            CursorCodec::Avif => ImageCodec::Avif,
+        }
+    }
+}
+
+/// Longest side of cursors sent to `preview` quality clients.
+pub(crate) const PREVIEW_CURSOR_SIZE: u32 = 24;
+
+/// Cursor resolution a client asks for with `cursor_quality`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorQuality {
+    #[default]
+    Full,
+    /// Cursors larger than 24x24 (`PREVIEW_CURSOR_SIZE`) are scaled down
+    /// to fit, for clients on constrained links
+    Preview,
+}
+
+impl CursorQuality {
+    /// Parse a quality name (`full`, `preview`) from a client config.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(CursorQuality::Full),
+            "preview" => Some(CursorQuality::Preview),
+            _ => None,
+        }
+    }
+}
+
+/// Server codec preference from `CURSOR_IMAGE_CODEC`: `webp` (default) or
+/// `avif`, which needs a build with the `avif` feature.  Read once on first
+/// use.
+fn preferred_codec() -> CursorCodec {
+    static CODEC: OnceLock<CursorCodec> = OnceLock::new();
+    *CODEC.get_or_init(|| {
+        let Ok(raw) = std::env::var("CURSOR_IMAGE_CODEC") else {
+            return CursorCodec::Webp;
+        };
+        match CursorCodec::from_name(&raw) {
+            Some(CursorCodec::Avif) if !cfg!(feature = "avif") => {
+                tracing::warn!(
+                    "CURSOR_IMAGE_CODEC=avif needs a build with the avif feature, using webp"
+                );
+                CursorCodec::Webp
+            }
+            Some(codec) => {
+                tracing::info!(
+                    "Cursor image codec: {:?} (for clients that accept it)",
+                    codec
+                );
+                codec
+            }
+            None => {
+                tracing::warn!("Invalid CURSOR_IMAGE_CODEC={:?}, using webp", raw);
+                CursorCodec::Webp
             }
-            debug!("Cache trimmed to {} entries", cache.len());
         }
+    })
+}
+
+/// Codecs the server may send, preferred first.
+pub fn cursor_codecs() -> Vec<CursorCodec> {
+    match preferred_codec() {
+        CursorCodec::Webp => vec![CursorCodec::Webp],
+        preferred => vec![preferred, CursorCodec::Webp],
     }
+}
 
-    (cursor_id, true)
+/// The codec for a client accepting the codec names in `accepted`: the
+/// server's preferred codec if the client lists it, else WebP (which every
+/// client decodes).
+pub fn negotiate_cursor_codec(accepted: &[String]) -> CursorCodec {
+    let preferred = preferred_codec();
+    if accepted
+        .iter()
+        .any(|name| CursorCodec::from_name(name) == Some(preferred))
+    {
+        preferred
+    } else {
+        CursorCodec::Webp
+    }
+}
+
+/// `cached` (static) as AVIF, memoized per cursor variant.
+fn avif_image(cached: &CachedCursor) -> Result<Vec<u8>> {
+    let key = format!(
+        "{}@{}",
+        cached.id,
+        &blake3::hash(&cached.webp_data).to_hex()[..16]
+    );
+    if let Some(hit) = AVIF_CACHE
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|c| c.get(&key))
+    {
+        return Ok(hit.clone());
+    }
+    let data = encode_static_avif(&decode_cursor_rgba(cached)?, cached.width, cached.height)?;
+    AVIF_CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(CursorCache::new)
+        .insert_as(key, data.clone());
+    Ok(data)
+}
+
+/// Build a cache entry from an externally supplied cursor image (PNG, static
+/// or animated WebP).  PNG input is re-encoded to WebP; WebP input is kept as-is.
+pub fn build_external_cursor(
+    image_data: &[u8],
+    hotspot_x: i32,
+    hotspot_y: i32,
+) -> Result<CachedCursor> {
+    use anyhow::{anyhow, bail};
+
+    let (webp_data, width, height, is_animated, frame_count, frame_delay_ms) = if image_data
+        .starts_with(b"\x89PNG")
+    {
+        // Check the header's size before decoding anything.
+        let (w, h) = image::ImageReader::new(std::io::Cursor::new(image_data))
+            .with_guessed_format()?
+            .into_dimensions()?;
+        check_cursor_dimensions(w, h)?;
+        let rgba = image::load_from_memory(image_data)?.to_rgba8();
+        let (w, h) = rgba.dimensions();
+        (encode_static_webp(rgba.as_raw(), w, h)?, w, h, false, 1, 0)
+    } else if image_data.len() >= 12 && &image_data[..4] == b"RIFF" && &image_data[8..12] == b"WEBP"
+    {
+        let features = webp::BitstreamFeatures::new(image_data)
+            .ok_or_else(|| anyhow!("Invalid WebP bitstream"))?;
+        // The canvas size is in the header; check it before decoding frames.
+        check_cursor_dimensions(features.width(), features.height())?;
+        if features.has_animation() {
+            let decoder = webp_animation::Decoder::new(image_data)
+                .map_err(|e| anyhow!("Invalid animated WebP: {:?}", e))?;
+            let (w, h) = decoder.dimensions();
+            let timestamps: Vec<i32> = decoder.into_iter().map(|f| f.timestamp()).collect();
+            let frame_count = timestamps.len() as u32;
+            // Decoder timestamps mark the end of each frame
+            let delay = timestamps.first().copied().unwrap_or(0).max(0) as u32;
+            (
+                image_data.to_vec(),
+                w,
+                h,
+                frame_count > 1,
+                frame_count.max(1),
+                delay,
+            )
+        } else {
+            (
+                image_data.to_vec(),
+                features.width(),
+                features.height(),
+                false,
+                1,
+                0,
+            )
+        }
+    } else {
+        bail!("Unsupported image format (expected PNG or WebP)");
+    };
+
+    check_cursor_dimensions(width, height)?;
+    if hotspot_x < 0 || hotspot_y < 0 || hotspot_x >= width as i32 || hotspot_y >= height as i32 {
+        bail!(
+            "Hotspot ({}, {}) outside {}x{} image",
+            hotspot_x,
+            hotspot_y,
+            width,
+            height
+        );
+    }
+
+    let id = cursor_id("inj", &webp_data, width, height, hotspot_x, hotspot_y);
+
+    Ok(CachedCursor {
+        id,
+        webp_data,
+        width,
+        height,
+        hotspot_x,
+        hotspot_y,
+        is_animated,
+        frame_count,
+        frame_delay_ms,
+        name: String::new(),
+    })
+}
+
+/// Insert an external cursor into the cache and make it the current cursor,
+/// as if the OS cursor had changed.  Returns the cursor_id; the caller is
+/// responsible for broadcasting `CursorEvent::CursorChanged`.
+pub fn inject_cursor(cached: CachedCursor) -> String {
+    init_cache();
+    cache_cursor(cached).0
+}
+
+/// Cursors no larger than this in both dimensions are always encoded
+/// losslessly; artefacts are most visible on small cursors and they are cheap
+/// to send anyway.
+const LOSSLESS_MAX_DIM: u32 = 48;
+
+/// Lossy WebP quality from `CURSOR_WEBP_QUALITY` (0–100), or `None` for
+/// lossless (the default).  Read once on first use.
+fn webp_quality() -> Option<f32> {
+    static QUALITY: OnceLock<Option<f32>> = OnceLock::new();
+    *QUALITY.get_or_init(|| {
+        let Ok(raw) = std::env::var("CURSOR_WEBP_QUALITY") else {
+            tracing::info!("Cursor WebP encoding: lossless");
+            return None;
+        };
+        match raw.trim().parse::<f32>() {
+            Ok(q) if (0.0..=100.0).contains(&q) => {
+                tracing::info!(
+                    "Cursor WebP encoding: lossy q={} (lossless up to {}x{})",
+                    q,
+                    LOSSLESS_MAX_DIM,
+                    LOSSLESS_MAX_DIM
+                );
+                Some(q)
+            }
+            _ => {
+                tracing::warn!("Invalid CURSOR_WEBP_QUALITY={:?}, using lossless", raw);
+                None
+            }
+        }
+    })
 }
 
-/// Encode RGBA pixels as a static (single-frame) lossless WebP
-pub(crate) fn encode_static_webp(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+/// Quality to encode a cursor of the given size with, `None` meaning lossless.
+fn lossy_quality_for(width: u32, height: u32) -> Option<f32> {
+    if width <= LOSSLESS_MAX_DIM && height <= LOSSLESS_MAX_DIM {
+        return None;
+    }
+    webp_quality()
+}
+
+/// Encode RGBA pixels as a static (single-frame) WebP: lossless unless
+/// `CURSOR_WEBP_QUALITY` is set and the cursor is larger than 48x48.
+pub fn encode_static_webp(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
     let encoder = webp::Encoder::from_rgba(rgba, width, height);
-    let memory = encoder.encode_lossless();
+    let quality = lossy_quality_for(width, height);
+    let memory = match quality {
+        Some(q) => encoder.encode(q),
+        None => encoder.encode_lossless(),
+    };
+    debug!(
+        "Encoded {}x{} static WebP ({}): {} bytes",
+        width,
+        height,
+        quality.map_or("lossless".to_string(), |q| format!("lossy q={}", q)),
+        memory.len()
+    );
     Ok(memory.to_vec())
 }
 
-/// Encode multiple RGBA frames as an animated WebP
-pub(crate) fn encode_animated_webp(
+/// AVIF quality for cursors (0–100).  ravif has no lossless mode; at this
+/// level edges stay clean.
+#[cfg(feature = "avif")]
+const AVIF_QUALITY: u8 = 90;
+/// rav1e speed (1 = slowest, 10 = fastest); cursors are tiny and encoded once.
+#[cfg(feature = "avif")]
+const AVIF_SPEED: u8 = 4;
+
+/// Encode RGBA pixels as a still AVIF image.
+#[cfg(feature = "avif")]
+pub fn encode_static_avif(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    use image::ImageEncoder;
+
+    let mut out = Vec::new();
+    image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut out, AVIF_SPEED, AVIF_QUALITY)
+        .write_image(rgba, width, height, image::ExtendedColorType::Rgba8)?;
+    debug!(
+        "Encoded {}x{} AVIF (q={}): {} bytes",
+        width,
+        height,
+        AVIF_QUALITY,
+        out.len()
+    );
+    Ok(out)
+}
+
+#[cfg(not(feature = "avif"))]
+pub fn encode_static_avif(_rgba: &[u8], _width: u32, _height: u32) -> Result<Vec<u8>> {
+    anyhow::bail!("built without the avif feature")
+}
+
+/// Encode multiple RGBA frames as an animated WebP, lossy under the same
+/// conditions as [`encode_static_webp`].
+///
+/// A run of identical consecutive frames is written once, shown for the
+/// whole run, so cursors that pause between cycles stay small.
+pub fn encode_animated_webp(
     frames: &[Vec<u8>],
     width: u32,
     height: u32,
     frame_delay_ms: i32,
 ) -> Result<Vec<u8>> {
-    use anyhow::anyhow;
+    encode_animated_webp_with_delays(frames, width, height, &vec![frame_delay_ms; frames.len()])
+}
+
+/// Like [`encode_animated_webp`], but with each frame shown for its own
+/// `delays_ms[i]`.
+pub fn encode_animated_webp_with_delays(
+    frames: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    delays_ms: &[i32],
+) -> Result<Vec<u8>> {
+    use anyhow::{anyhow, ensure};
+
+    ensure!(
+        delays_ms.len() == frames.len(),
+        "{} frame delays for {} frames",
+        delays_ms.len(),
+        frames.len()
+    );
 
-    let mut encoder = webp_animation::Encoder::new((width, height))
-        .map_err(|e| anyhow!("Failed to create animated WebP encoder: {:?}", e))?;
+    use webp_animation::{EncoderOptions, EncodingConfig, EncodingType, LossyEncodingConfig};
 
-    for (i, frame_rgba) in frames.iter().enumerate() {
-        let timestamp_ms = (i as i32) * frame_delay_ms;
-        encoder.add_frame(frame_rgba, timestamp_ms)
-            .map_err(|e| anyhow!("Failed to add frame {}: {:?}", i, e))?;
+    let quality = lossy_quality_for(width, height);
+    let mut encoder = match quality {
+        Some(q) => webp_animation::Encoder::new_with_options(
+            (width, height),
+            EncoderOptions {
+                encoding_config: Some(EncodingConfig {
+                    quality: q,
+                    encoding_type: EncodingType::Lossy(LossyEncodingConfig {
+                        alpha_compression: true,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ),
+        None => webp_animation::Encoder::new((width, height)),
     }
+    .map_err(|e| anyhow!("Failed to create animated WebP encoder: {:?}", e))?;
 
-    let final_timestamp = frames.len() as i32 * frame_delay_ms;
-    let webp_data = encoder.finalize(final_timestamp)
+    let mut timestamp_ms = 0;
+    let mut written = 0;
+    for (i, (frame_rgba, delay)) in frames.iter().zip(delays_ms).enumerate() {
+        // A repeat of the previous frame just extends its display time.
+        if i == 0 || frames[i - 1] != *frame_rgba {
+            encoder
+                .add_frame(frame_rgba, timestamp_ms)
+                .map_err(|e| anyhow!("Failed to add frame {}: {:?}", i, e))?;
+            written += 1;
+        }
+        timestamp_ms += delay;
+    }
+
+    let final_timestamp = timestamp_ms;
+    let webp_data = encoder
+        .finalize(final_timestamp)
         .map_err(|e| anyhow!("Failed to finalize animated WebP: {:?}", e))?;
+    debug!(
+        "Encoded {}x{} animated WebP, {} frames ({} distinct, {}): {} bytes",
+        width,
+        height,
+        frames.len(),
+        written,
+        quality.map_or("lossless".to_string(), |q| format!("lossy q={}", q)),
+        webp_data.len()
+    );
 
     Ok(webp_data.to_vec())
 }
 
+/// Decode a cached cursor back to RGBA pixels (the first frame of an
+/// animated cursor).
+pub fn decode_cursor_rgba(cached: &CachedCursor) -> Result<Vec<u8>> {
+    use anyhow::anyhow;
+
+    if cached.is_animated {
+        let decoder = webp_animation::Decoder::new(&cached.webp_data)
+            .map_err(|e| anyhow!("Invalid animated WebP: {:?}", e))?;
+        let frame = decoder
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Animated WebP has no frames"))?;
+        return Ok(frame.data().to_vec());
+    }
+
+    let image = webp::Decoder::new(&cached.webp_data)
+        .decode()
+        .ok_or_else(|| anyhow!("Invalid WebP image"))?;
+    Ok(if image.is_alpha() {
+        image.to_vec()
+    } else {
+        image
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect()
+    })
+}
+
+/// Recovered alpha below which a pixel is dropped as fully transparent.
+/// Colour recovery divides by alpha, so at these levels 8-bit rounding in
+/// the renders turns into random colour fringes.
+pub(crate) const MIN_RECOVERED_ALPHA: u8 = 8;
+
+/// Straight RGBA for one pixel drawn onto black and onto white (the
+/// dual-background capture used for cursors without readable alpha).  XOR
+/// pixels, brighter on black than on white, must be handled by the caller.
+///
+/// Alpha is the smallest white-minus-black spread left over from 255.  The
+/// premultiplied colour is averaged from both renders, which halves the
+/// rounding error before it is divided by alpha.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn recover_dual_render_pixel(on_black: [u8; 3], on_white: [u8; 3]) -> [u8; 4] {
+    let alpha = (0..3)
+        .map(|c| 255 - (on_white[c] as i32 - on_black[c] as i32))
+        .min()
+        .unwrap_or(0)
+        .clamp(0, 255);
+    if alpha < MIN_RECOVERED_ALPHA as i32 {
+        return [0, 0, 0, 0];
+    }
+
+    let a = alpha as f32;
+    let mut rgba = [0, 0, 0, alpha as u8];
+    for c in 0..3 {
+        // On white the background contributes 255 - alpha on top of the
+        // premultiplied colour.
+        let from_white = on_white[c] as f32 - (255.0 - a);
+        let premultiplied = (on_black[c] as f32 + from_white) / 2.0;
+        rgba[c] = (premultiplied * 255.0 / a).round().clamp(0.0, 255.0) as u8;
+    }
+    rgba
+}
+
+/// Largest cursor accepted in either dimension, including outline padding.
+/// Real cursors top out at 256 px; a larger reported size means a corrupt
+/// image and would otherwise lead to a huge allocation.
+pub(crate) const MAX_CURSOR_DIM: u32 = 1024;
+
+/// Reject cursors that are empty or exceed [`MAX_CURSOR_DIM`].
+pub(crate) fn check_cursor_dimensions(width: u32, height: u32) -> Result<()> {
+    if width == 0 || height == 0 {
+        anyhow::bail!("Cursor has zero dimensions ({}x{})", width, height);
+    }
+    if width > MAX_CURSOR_DIM || height > MAX_CURSOR_DIM {
+        anyhow::bail!(
+            "Cursor {}x{} exceeds the {}x{} limit",
+            width,
+            height,
+            MAX_CURSOR_DIM,
+            MAX_CURSOR_DIM
+        );
+    }
+    Ok(())
+}
+
 /// Expand the canvas by `pad` pixels on each side, copying original pixels to the center.
-/// Returns the new RGBA buffer with updated dimensions.
-pub(crate) fn expand_canvas(rgba: &[u8], width: u32, height: u32, pad: u32) -> (Vec<u8>, u32, u32) {
+/// Returns the new RGBA buffer with updated dimensions, or an error if the
+/// padded size overflows or exceeds [`MAX_CURSOR_DIM`].
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn expand_canvas(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    pad: u32,
+) -> Result<(Vec<u8>, u32, u32)> {
+    let padded = |dim: u32| pad.checked_mul(2).and_then(|p| dim.checked_add(p));
+    let (Some(padded_w), Some(padded_h)) = (padded(width), padded(height)) else {
+        anyhow::bail!("Cursor {}x{} with padding {} overflows", width, height, pad);
+    };
+    check_cursor_dimensions(padded_w, padded_h)?;
+
+    let new_rgba = place_on_canvas(rgba, width, height, padded_w, padded_h, pad, pad)?;
+    Ok((new_rgba, padded_w, padded_h))
+}
+
+/// Copy a `width`×`height` RGBA image onto a transparent
+/// `canvas_w`×`canvas_h` canvas with its top-left corner at (`x`, `y`).
+fn place_on_canvas(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    canvas_w: u32,
+    canvas_h: u32,
+    x: u32,
+    y: u32,
+) -> Result<Vec<u8>> {
     let old_w = width as usize;
     let old_h = height as usize;
-    let new_w = old_w + (pad as usize) * 2;
-    let new_h = old_h + (pad as usize) * 2;
-    let mut new_rgba = vec![0u8; new_w * new_h * 4];
-
-    for y in 0..old_h {
-        for x in 0..old_w {
-            let src = (y * old_w + x) * 4;
-            let dst = ((y + pad as usize) * new_w + (x + pad as usize)) * 4;
-            new_rgba[dst..dst + 4].copy_from_slice(&rgba[src..src + 4]);
-        }
+    anyhow::ensure!(
+        rgba.len() >= old_w * old_h * 4,
+        "Cursor buffer holds {} bytes, {}x{} needs {}",
+        rgba.len(),
+        width,
+        height,
+        old_w * old_h * 4
+    );
+    anyhow::ensure!(
+        x + width <= canvas_w && y + height <= canvas_h,
+        "{}x{} at {},{} does not fit a {}x{} canvas",
+        width,
+        height,
+        x,
+        y,
+        canvas_w,
+        canvas_h
+    );
+    let new_w = canvas_w as usize;
+    let mut new_rgba = vec![0u8; new_w * canvas_h as usize * 4];
+
+    for (row, src) in rgba.chunks_exact(old_w * 4).take(old_h).enumerate() {
+        let dst = ((row + y as usize) * new_w + x as usize) * 4;
+        new_rgba[dst..dst + old_w * 4].copy_from_slice(src);
     }
 
-    (new_rgba, new_w as u32, new_h as u32)
+    Ok(new_rgba)
+}
+
+/// Outline drawn around XOR cursors, from `CURSOR_OUTLINE_COLOR` (hex RGB)
+/// and `CURSOR_OUTLINE_RADIUS` (pixels, default 4).  Without an explicit
+/// colour the outline is white, or black on a light desktop theme.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OutlineStyle {
+    pub color: [u8; 3],
+    /// Outline width; also the padding added around the cursor canvas
+    pub radius: u32,
+}
+
+const DEFAULT_OUTLINE_COLOR: [u8; 3] = [255, 255, 255];
+const LIGHT_THEME_OUTLINE_COLOR: [u8; 3] = [0, 0, 0];
+const DEFAULT_OUTLINE_RADIUS: u32 = 4;
+const MAX_OUTLINE_RADIUS: u32 = 16;
+
+/// Parse `rrggbb` or `#rrggbb`.
+fn parse_hex_rgb(s: &str) -> Option<[u8; 3]> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
 }
 
-/// Add a white outline of `radius` pixels around opaque pixels for better visibility.
-/// This helps XOR cursors (rendered as dark pixels) be visible on dark backgrounds.
-pub(crate) fn add_white_outline(rgba: &mut [u8], width: u32, height: u32, radius: i32) {
+/// Whether the host desktop uses a light theme; picks the default outline
+/// colour.
+static LIGHT_DESKTOP: AtomicBool = AtomicBool::new(false);
+
+/// Record the host desktop theme.  On a change, cached cursors are dropped
+/// and the current one re-captured so outlines pick up the new default.
+pub fn set_light_desktop(light: bool) {
+    if LIGHT_DESKTOP.swap(light, Ordering::SeqCst) == light {
+        return;
+    }
+    debug!(
+        "Desktop theme is now {}, re-capturing cursors",
+        if light { "light" } else { "dark" }
+    );
+    clear_cache();
+    request_recapture();
+}
+
+/// XOR cursor outline style.  The environment is read once on first use; the
+/// default colour follows [`set_light_desktop`].
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn outline_style() -> OutlineStyle {
+    static STYLE: OnceLock<(Option<[u8; 3]>, u32)> = OnceLock::new();
+    let (color, radius) = *STYLE.get_or_init(|| {
+        let color = match std::env::var("CURSOR_OUTLINE_COLOR") {
+            Err(_) => None,
+            Ok(v) => parse_hex_rgb(&v).or_else(|| {
+                tracing::warn!(
+                    "Invalid CURSOR_OUTLINE_COLOR={:?}, using the theme default",
+                    v
+                );
+                None
+            }),
+        };
+        let radius = match std::env::var("CURSOR_OUTLINE_RADIUS") {
+            Err(_) => DEFAULT_OUTLINE_RADIUS,
+            Ok(v) => match v.trim().parse::<u32>() {
+                Ok(r) if r <= MAX_OUTLINE_RADIUS => r,
+                _ => {
+                    tracing::warn!(
+                        "Invalid CURSOR_OUTLINE_RADIUS={:?} (0–{}), using {}",
+                        v,
+                        MAX_OUTLINE_RADIUS,
+                        DEFAULT_OUTLINE_RADIUS
+                    );
+                    DEFAULT_OUTLINE_RADIUS
+                }
+            },
+        };
+        debug!("XOR cursor outline: {:?}, radius {}", color, radius);
+        (color, radius)
+    });
+    let color = color.unwrap_or(if LIGHT_DESKTOP.load(Ordering::SeqCst) {
+        LIGHT_THEME_OUTLINE_COLOR
+    } else {
+        DEFAULT_OUTLINE_COLOR
+    });
+    OutlineStyle { color, radius }
+}
+
+/// Add an outline of `style.radius` pixels in `style.color` around opaque
+/// pixels for better visibility.  This helps XOR cursors (rendered as dark
+/// pixels) be visible on dark backgrounds.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn add_outline(rgba: &mut [u8], width: u32, height: u32, style: OutlineStyle) {
+    if style.radius == 0 {
+        return;
+    }
+    let radius = style.radius as i32;
     let w = width as usize;
     let h = height as usize;
     let r2 = (radius * radius) as f32;
@@ -264,11 +1514,9 @@ pub(crate) fn add_white_outline(rgba: &mut [u8], width: u32, height: u32, radius
         }
     }
 
-    // Second pass: set outline pixels to white with distance-based alpha
+    // Second pass: set outline pixels to the outline colour with distance-based alpha
     for (idx, alpha) in outline_pixels {
-        rgba[idx] = 255;     // R
-        rgba[idx + 1] = 255; // G
-        rgba[idx + 2] = 255; // B
+        rgba[idx..idx + 3].copy_from_slice(&style.color);
         rgba[idx + 3] = alpha;
     }
 }
@@ -280,3 +1528,373 @@ pub(crate) fn get_timestamp() -> u64 {
         .map(|d| d.as_millis() as u64)
         .unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assert_samples, decode_webp, gradient, solid};
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = CursorCache::new();
+        cache.insert_as("kept".to_string(), 0);
+        cache.insert_as("first".to_string(), 1);
+        for i in 2..=60 {
+            cache.insert_as(format!("cursor-{}", i), i);
+            assert_eq!(
+                cache.get("kept"),
+                Some(&0),
+                "touched cursor evicted at {}",
+                i
+            );
+            assert!(cache.len() <= CACHE_MAX_ENTRIES);
+        }
+        assert!(cache.get("first").is_none(), "LRU cursor not evicted");
+        assert!(cache.get("cursor-60").is_some());
+    }
+
+    #[test]
+    fn cursor_dimensions_are_bounded() {
+        assert!(check_cursor_dimensions(1, 1).is_ok());
+        assert!(check_cursor_dimensions(MAX_CURSOR_DIM, MAX_CURSOR_DIM).is_ok());
+        for (w, h) in [
+            (0, 1),
+            (1, 0),
+            (MAX_CURSOR_DIM + 1, 1),
+            (1, MAX_CURSOR_DIM + 1),
+            (u32::MAX, u32::MAX),
+        ] {
+            assert!(
+                check_cursor_dimensions(w, h).is_err(),
+                "{}x{} accepted",
+                w,
+                h
+            );
+        }
+    }
+
+    #[test]
+    fn expand_canvas_rejects_overflow() {
+        let rgba = vec![255; 2 * 2 * 4];
+        let (expanded, w, h) = expand_canvas(&rgba, 2, 2, (MAX_CURSOR_DIM - 2) / 2).unwrap();
+        assert_eq!((w, h), (MAX_CURSOR_DIM, MAX_CURSOR_DIM));
+        assert_eq!(expanded.len(), (w * h * 4) as usize);
+
+        assert!(expand_canvas(&rgba, 2, 2, MAX_CURSOR_DIM / 2).is_err());
+        assert!(expand_canvas(&rgba, 2, 2, u32::MAX / 2 + 1).is_err());
+        assert!(expand_canvas(&rgba, u32::MAX, 2, 1).is_err());
+        // A buffer shorter than its stated size is an error, not a panic
+        assert!(expand_canvas(&rgba, 4, 4, 1).is_err());
+    }
+
+    #[test]
+    fn oversized_external_cursor_rejected() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(MAX_CURSOR_DIM + 1, 1)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        assert!(build_external_cursor(&png, 0, 0).is_err());
+
+        let (w, h) = (MAX_CURSOR_DIM + 1, 1);
+        let frames = [
+            solid(w, h, [0xFF, 0, 0, 0xFF]),
+            solid(w, h, [0, 0, 0xFF, 0xFF]),
+        ];
+        let webp = encode_animated_webp(&frames, w, h, 100).unwrap();
+        assert!(build_external_cursor(&webp, 0, 0).is_err());
+    }
+
+    #[test]
+    fn cursor_id_covers_hotspot_and_size() {
+        let (w, h) = (32, 32);
+        let rgba = vec![0x80; (w * h * 4) as usize];
+        let id = cursor_id("cur", &rgba, w, h, 0, 0);
+        assert_eq!(id, cursor_id("cur", &rgba, w, h, 0, 0));
+        assert_ne!(id, cursor_id("cur", &rgba, w, h, 16, 16), "hotspot");
+        assert_ne!(id, cursor_id("cur", &rgba, h * 2, w / 2, 0, 0), "size");
+
+        let webp = encode_static_webp(&rgba, w, h).unwrap();
+        let a = build_external_cursor(&webp, 0, 0).unwrap();
+        let b = build_external_cursor(&webp, 16, 16).unwrap();
+        assert_ne!(a.id, b.id);
+    }
+
+    /// `CursorData` a client asking for `size` and `quality` would get.
+    fn cursor_data(cursor_id: &str, size: Option<u32>, quality: CursorQuality) -> CursorData {
+        let msg = create_scaled_cursor_message(
+            DEFAULT_SEAT,
+            cursor_id,
+            1.0,
+            size,
+            CursorCodec::Webp,
+            quality,
+        )
+        .expect("cursor not cached");
+        match msg.payload {
+            Some(Payload::CursorData(data)) => data,
+            other => panic!("unexpected payload {:?}", other),
+        }
+    }
+
+    #[test]
+    fn preferred_size_scales_static_cursor_and_hotspot() {
+        let (w, h) = (64, 48);
+        let webp = encode_static_webp(&gradient(w, h), w, h).unwrap();
+        let id = inject_cursor(build_external_cursor(&webp, 32, 24).unwrap());
+
+        let data = cursor_data(&id, Some(32), CursorQuality::Full);
+        assert_eq!((data.width, data.height), (32, 24));
+        assert_eq!((data.hotspot_x, data.hotspot_y), (16, 12));
+        assert_eq!(data.dpi_scale, 1.0);
+        decode_webp(&data.image_data, 32, 24);
+
+        let full = cursor_data(&id, Some(64), CursorQuality::Full);
+        assert_eq!((full.width, full.height), (64, 48));
+    }
+
+    #[test]
+    fn preferred_size_keeps_animation_timing() {
+        let (w, h) = (64, 64);
+        let frames: Vec<Vec<u8>> = (0..3u8)
+            .map(|i| solid(w, h, [i * 80, 40, 200, 255]))
+            .collect();
+        let webp = encode_animated_webp(&frames, w, h, 70).unwrap();
+        let id = inject_cursor(build_external_cursor(&webp, 8, 8).unwrap());
+
+        let data = cursor_data(&id, Some(16), CursorQuality::Full);
+        assert!(data.is_animated);
+        assert_eq!((data.hotspot_x, data.hotspot_y), (2, 2));
+        let decoder = webp_animation::Decoder::new(&data.image_data).unwrap();
+        assert_eq!(decoder.dimensions(), (16, 16));
+        let timestamps: Vec<i32> = decoder.into_iter().map(|f| f.timestamp()).collect();
+        assert_eq!(timestamps, [70, 140, 210]);
+    }
+
+    #[cfg(feature = "avif")]
+    #[test]
+    fn avif_is_a_still_image_container() {
+        let data = encode_static_avif(&gradient(32, 32), 32, 32).unwrap();
+        assert_eq!(&data[4..8], b"ftyp");
+        assert_eq!(&data[8..12], b"avif");
+    }
+
+    #[test]
+    fn frame_delay_is_clamped() {
+        assert_eq!(estimate_frame_delay(0, 1), MIN_FRAME_DELAY_MS);
+        assert_eq!(estimate_frame_delay(0, 60_000), MAX_FRAME_DELAY_MS);
+        assert_eq!(estimate_frame_delay(0, u32::MAX), MAX_FRAME_DELAY_MS);
+        assert_eq!(estimate_frame_delay(u32::MAX, u32::MAX), MAX_FRAME_DELAY_MS);
+        assert_eq!(estimate_frame_delay(0, 70), 70, "first sample taken as is");
+    }
+
+    #[test]
+    fn frame_delay_is_averaged() {
+        // One late poll moves the estimate a quarter of the way
+        assert_eq!(estimate_frame_delay(100, 500), 200);
+        // Steady samples converge on the true interval
+        assert_eq!((0..30).fold(0, |avg, _| estimate_frame_delay(avg, 50)), 50);
+        assert_eq!(
+            (0..30).fold(400, |avg, _| estimate_frame_delay(avg, 40)),
+            40
+        );
+    }
+
+    #[test]
+    fn animated_webp_merges_repeated_frames() {
+        let (w, h) = (16, 16);
+        let a = solid(w, h, [255, 0, 0, 255]);
+        let b = solid(w, h, [0, 0, 255, 255]);
+        let frames = vec![a.clone(), a.clone(), a, b.clone(), b];
+        let webp = encode_animated_webp(&frames, w, h, 50).unwrap();
+
+        let decoded: Vec<_> = webp_animation::Decoder::new(&webp)
+            .unwrap()
+            .into_iter()
+            .collect();
+        // Decoder timestamps mark the end of each frame
+        let timestamps: Vec<i32> = decoded.iter().map(|f| f.timestamp()).collect();
+        assert_eq!(timestamps, [150, 250]);
+        assert_samples(&frames[3], decoded[1].data(), w, h);
+    }
+
+    #[test]
+    fn pot_padding_keeps_hotspot_on_its_pixel() {
+        let (w, h) = (24, 20);
+        let rgba = gradient(w, h);
+        let webp = encode_static_webp(&rgba, w, h).unwrap();
+        let cached = build_external_cursor(&webp, 5, 7).unwrap();
+
+        let (padded, offset) = pad_to_power_of_two(&cached, PotPlacement::Center).unwrap();
+        assert_eq!((padded.width, padded.height), (32, 32));
+        assert_eq!(offset, (4, 6));
+        assert_eq!((padded.hotspot_x, padded.hotspot_y), (9, 13));
+        assert_eq!(padded.id, cached.id);
+
+        let out = decode_cursor_rgba(&padded).unwrap();
+        assert_eq!(out[3], 0, "corner not transparent");
+        let at = |buf: &[u8], stride: u32, x: u32, y: u32| {
+            let i = ((y * stride + x) * 4) as usize;
+            buf[i..i + 4].to_vec()
+        };
+        assert_eq!(at(&out, 32, 4 + 5, 6 + 7), at(&rgba, w, 5, 7));
+
+        let (same, offset) = pad_to_power_of_two(&padded, PotPlacement::TopLeft).unwrap();
+        assert_eq!((same.width, offset), (32, (0, 0)), "already power of two");
+    }
+
+    #[test]
+    fn preview_quality_caps_size_and_hotspot() {
+        let (w, h) = (48, 32);
+        let webp = encode_static_webp(&gradient(w, h), w, h).unwrap();
+        let id = inject_cursor(build_external_cursor(&webp, 12, 30).unwrap());
+
+        let data = cursor_data(&id, None, CursorQuality::Preview);
+        assert_eq!((data.width, data.height), (PREVIEW_CURSOR_SIZE as i32, 16));
+        assert_eq!((data.hotspot_x, data.hotspot_y), (6, 15));
+        decode_webp(&data.image_data, 24, 16);
+
+        let full = cursor_data(&id, None, CursorQuality::Full);
+        assert_eq!((full.width, full.height), (48, 32));
+        // Already within the cap: sent as is
+        let small = cursor_data(&id, Some(16), CursorQuality::Preview);
+        assert_eq!(small.width.max(small.height), 16);
+    }
+
+    /// Reference cursor: an orange disc with a two-pixel soft edge over a
+    /// faint, wide blue glow, so every alpha level from 0 up appears.
+    fn soft_cursor(width: u32, height: u32) -> Vec<u8> {
+        let coverage =
+            |d: f32, radius: f32, soft: f32| ((radius + soft / 2.0 - d) / soft).clamp(0.0, 1.0);
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+                let disc = coverage(((fx - 13.0).powi(2) + (fy - 13.0).powi(2)).sqrt(), 9.0, 2.0);
+                let glow = 0.35
+                    * coverage(
+                        ((fx - 17.0).powi(2) + (fy - 17.0).powi(2)).sqrt(),
+                        8.0,
+                        10.0,
+                    );
+                // Disc over its glow, in straight alpha
+                let alpha = disc + glow * (1.0 - disc);
+                let mut color = [0; 3];
+                if alpha > 0.0 {
+                    let (orange, blue) = ([250.0, 140.0, 30.0], [40.0, 90.0, 250.0]);
+                    for c in 0..3 {
+                        color[c] = ((orange[c] * disc + blue[c] * glow * (1.0 - disc)) / alpha)
+                            .round() as u8;
+                    }
+                }
+                rgba.extend(color);
+                rgba.push((alpha * 255.0).round() as u8);
+            }
+        }
+        rgba
+    }
+
+    /// Largest per-channel colour difference, ignoring alpha.
+    fn color_error(a: &[u8], b: &[u8]) -> u8 {
+        (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn dual_render_recovery_drops_faint_speckle() {
+        let reference = soft_cursor(32, 32);
+        let mut worst_new = 0;
+        let mut worst_naive = 0;
+        for (i, px) in reference.chunks(4).enumerate() {
+            // What GDI draws: the premultiplied colour, plus the background
+            // showing through on white.
+            let a = px[3] as u32;
+            let premultiplied = |c: u8| ((c as u32 * a + 127) / 255) as u8;
+            let on_black = [0, 1, 2].map(|c| premultiplied(px[c]));
+            let on_white = on_black.map(|p| p + (255 - a) as u8);
+            let got = recover_dual_render_pixel(on_black, on_white);
+
+            if a < MIN_RECOVERED_ALPHA as u32 {
+                assert_eq!(got, [0; 4], "pixel {} with alpha {}", i, a);
+                // Dividing by a tiny alpha lands anywhere in 0..=255
+                if a > 0 {
+                    let naive = on_black.map(|p| (p as u32 * 255 / a).min(255) as u8);
+                    worst_naive = worst_naive.max(color_error(&naive, px));
+                }
+                continue;
+            }
+            assert_eq!(got[3], px[3], "pixel {} alpha", i);
+            worst_new = worst_new.max(color_error(&got, px));
+            // Blended over mid grey the recovered pixel must look the same
+            let over_grey = |p: &[u8], c: usize| (p[c] as u32 * a + 128 * (255 - a) + 127) / 255;
+            for c in 0..3 {
+                let diff = over_grey(&got, c).abs_diff(over_grey(px, c));
+                assert!(
+                    diff <= 1,
+                    "pixel {} channel {} off by {} over grey",
+                    i,
+                    c,
+                    diff
+                );
+            }
+        }
+        // Rounding can be amplified at most 255 / (2 * MIN_RECOVERED_ALPHA)
+        let bound = 255 / (2 * MIN_RECOVERED_ALPHA) + 1;
+        assert!(
+            worst_new <= bound,
+            "colour error {} (bound {})",
+            worst_new,
+            bound
+        );
+        assert!(
+            worst_naive > bound,
+            "reference has no faint edge pixels (naive error {})",
+            worst_naive
+        );
+    }
+
+    #[test]
+    fn cursor_rgba_survives_png_upload() {
+        let (w, h) = (32, 32);
+        let rgba = gradient(w, h);
+        let png = crate::clipboard_sync::encode_rgba_to_png(&rgba, w, h).unwrap();
+        let cached = build_external_cursor(&png, 4, 4).unwrap();
+        assert_eq!((cached.width, cached.height), (w, h));
+        assert_samples(&rgba, &decode_cursor_rgba(&cached).unwrap(), w, h);
+    }
+
+    #[test]
+    fn static_webp_round_trips() {
+        for (w, h, rgba) in [
+            (32, 32, solid(32, 32, [200, 40, 90, 255])),
+            (48, 24, gradient(48, 24)),
+        ] {
+            let webp = encode_static_webp(&rgba, w, h).unwrap();
+            assert_samples(&rgba, &decode_webp(&webp, w, h), w, h);
+        }
+    }
+
+    #[test]
+    fn static_webp_keeps_transparent_pixel() {
+        let webp = encode_static_webp(&[0; 4], 1, 1).unwrap();
+        assert_eq!(decode_webp(&webp, 1, 1)[3], 0);
+    }
+
+    #[test]
+    fn animated_webp_round_trips() {
+        let (w, h) = (16, 16);
+        let delay = 50;
+        let frames: Vec<Vec<u8>> = (0..4u8)
+            .map(|i| solid(w, h, [i * 60, 255 - i * 60, 128, 255]))
+            .collect();
+
+        let webp = encode_animated_webp(&frames, w, h, delay).unwrap();
+        let decoder = webp_animation::Decoder::new(&webp).unwrap();
+        assert_eq!(decoder.dimensions(), (w, h));
+        let decoded: Vec<_> = decoder.into_iter().collect();
+        assert_eq!(decoded.len(), frames.len());
+        for (i, (frame, original)) in decoded.iter().zip(&frames).enumerate() {
+            // Decoder timestamps mark the end of each frame
+            assert_eq!(frame.timestamp(), (i as i32 + 1) * delay, "frame {}", i);
+            assert_samples(original, frame.data(), w, h);
+        }
+    }
+}
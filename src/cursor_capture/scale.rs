@@ -0,0 +1,244 @@
+//! RGBA resampling for cursor images: the macOS fallback upscale of 1x CGS
+//! data, and client-requested fixed cursor sizes.
+
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+/// Bilinear-interpolation resample.
+fn bilinear_scale(src: &[u8], sw: u32, sh: u32, dw: u32, dh: u32) -> Vec<u8> {
+    let mut dst = vec![0u8; (dw * dh * 4) as usize];
+    let x_ratio = sw as f64 / dw as f64;
+    let y_ratio = sh as f64 / dh as f64;
+    for y in 0..dh {
+        let gy = y as f64 * y_ratio;
+        let yi = (gy as u32).min(sh - 1);
+        let yi1 = (yi + 1).min(sh - 1);
+        let yw = gy - yi as f64;
+        for x in 0..dw {
+            let gx = x as f64 * x_ratio;
+            let xi = (gx as u32).min(sw - 1);
+            let xi1 = (xi + 1).min(sw - 1);
+            let xw = gx - xi as f64;
+
+            let idx = |ix: u32, iy: u32| (iy * sw + ix) as usize * 4;
+            let di = (y * dw + x) as usize * 4;
+
+            for c in 0..4 {
+                let c00 = src[idx(xi, yi) + c] as f64;
+                let c10 = src[idx(xi1, yi) + c] as f64;
+                let c01 = src[idx(xi, yi1) + c] as f64;
+                let c11 = src[idx(xi1, yi1) + c] as f64;
+                let v = c00 * (1.0 - xw) * (1.0 - yw)
+                    + c10 * xw * (1.0 - yw)
+                    + c01 * (1.0 - xw) * yw
+                    + c11 * xw * yw;
+                dst[di + c] = v.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    dst
+}
+
+/// Resampling filter for cursor rescaling (`CURSOR_SCALE_FILTER`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ScaleFilter {
+    Bilinear,
+    Lanczos,
+}
+
+/// Read `CURSOR_SCALE_FILTER` once (`bilinear` default, or `lanczos`).
+pub(crate) fn scale_filter() -> ScaleFilter {
+    static FILTER: OnceLock<ScaleFilter> = OnceLock::new();
+    *FILTER.get_or_init(|| {
+        let filter = match std::env::var("CURSOR_SCALE_FILTER") {
+            Err(_) => ScaleFilter::Bilinear,
+            Ok(v) => match v.trim().to_ascii_lowercase().as_str() {
+                "bilinear" => ScaleFilter::Bilinear,
+                "lanczos" | "lanczos3" => ScaleFilter::Lanczos,
+                _ => {
+                    warn!("Invalid CURSOR_SCALE_FILTER={:?}, using bilinear", v);
+                    ScaleFilter::Bilinear
+                }
+            },
+        };
+        info!("Cursor scale filter: {:?}", filter);
+        filter
+    })
+}
+
+/// Lanczos kernel with a = 3.
+fn lanczos3(x: f32) -> f32 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= 3.0 {
+        return 0.0;
+    }
+    let px = std::f32::consts::PI * x;
+    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+}
+
+/// Per-output-sample (first source index, normalized weights) for resampling
+/// `src_len` samples to `dst_len` along one axis.
+fn lanczos_weights(src_len: u32, dst_len: u32) -> Vec<(usize, Vec<f32>)> {
+    let ratio = src_len as f32 / dst_len as f32;
+    // Widen the kernel when downscaling so it low-passes the source.
+    let filter_scale = ratio.max(1.0);
+    let support = 3.0 * filter_scale;
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * ratio;
+            let first = (center - support).floor().max(0.0) as usize;
+            let last = ((center + support).ceil() as usize).min(src_len as usize);
+            let mut weights: Vec<f32> = (first..last)
+                .map(|j| lanczos3((j as f32 + 0.5 - center) / filter_scale))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum != 0.0 {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            }
+            (first, weights)
+        })
+        .collect()
+}
+
+/// Separable Lanczos-3 resample of straight-alpha RGBA.  Colour is
+/// premultiplied by alpha before filtering and un-premultiplied afterwards,
+/// so transparent pixels' (usually black) colour can't bleed into edges as a
+/// dark fringe.
+fn lanczos_scale(src: &[u8], sw: u32, sh: u32, dw: u32, dh: u32) -> Vec<u8> {
+    let premul: Vec<f32> = src
+        .chunks_exact(4)
+        .flat_map(|p| {
+            let a = p[3] as f32 / 255.0;
+            [
+                p[0] as f32 * a,
+                p[1] as f32 * a,
+                p[2] as f32 * a,
+                p[3] as f32,
+            ]
+        })
+        .collect();
+
+    // Horizontal pass: sw x sh -> dw x sh
+    let xw = lanczos_weights(sw, dw);
+    let mut horiz = vec![0f32; (dw * sh * 4) as usize];
+    for y in 0..sh as usize {
+        let row = &premul[y * sw as usize * 4..(y + 1) * sw as usize * 4];
+        for (x, (first, weights)) in xw.iter().enumerate() {
+            let out = &mut horiz[(y * dw as usize + x) * 4..][..4];
+            for (k, w) in weights.iter().enumerate() {
+                let s = &row[(first + k) * 4..][..4];
+                for (o, v) in out.iter_mut().zip(s) {
+                    *o += v * w;
+                }
+            }
+        }
+    }
+
+    // Vertical pass: dw x sh -> dw x dh, then un-premultiply
+    let yw = lanczos_weights(sh, dh);
+    let mut dst = vec![0u8; (dw * dh * 4) as usize];
+    for (y, (first, weights)) in yw.iter().enumerate() {
+        for x in 0..dw as usize {
+            let mut acc = [0f32; 4];
+            for (k, w) in weights.iter().enumerate() {
+                let s = &horiz[((first + k) * dw as usize + x) * 4..][..4];
+                for (o, v) in acc.iter_mut().zip(s) {
+                    *o += v * w;
+                }
+            }
+            // Lanczos lobes can overshoot; clamp before un-premultiplying.
+            let a = acc[3].clamp(0.0, 255.0);
+            let di = (y * dw as usize + x) * 4;
+            // Pixels that round to fully transparent keep black colour.
+            if a >= 0.5 {
+                let inv = 255.0 / a;
+                for (d, v) in dst[di..di + 3].iter_mut().zip(&acc[..3]) {
+                    *d = (v * inv).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            dst[di + 3] = a.round() as u8;
+        }
+    }
+    dst
+}
+
+/// Resample straight-alpha RGBA from `sw`x`sh` to `dw`x`dh` with the
+/// configured [`ScaleFilter`].
+pub(crate) fn scale_rgba(src: &[u8], sw: u32, sh: u32, dw: u32, dh: u32) -> Vec<u8> {
+    match scale_filter() {
+        ScaleFilter::Bilinear => bilinear_scale(src, sw, sh, dw, dh),
+        ScaleFilter::Lanczos => lanczos_scale(src, sw, sh, dw, dh),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference cursor with fine detail: thin rings around a dot, coloured by a
+    /// diagonal ramp and antialiased by 8x8 supersampling, so it can be drawn at
+    /// any size.
+    fn ring_cursor(width: u32, height: u32) -> Vec<u8> {
+        const SS: u32 = 8;
+        let inside = |u: f32, v: f32| {
+            let d = ((u - 0.5).powi(2) + (v - 0.5).powi(2)).sqrt();
+            d < 0.1 || (d - 0.25).abs() < 0.025 || (d - 0.4).abs() < 0.02
+        };
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut hits = 0;
+                for sy in 0..SS {
+                    for sx in 0..SS {
+                        let u = (x as f32 + (sx as f32 + 0.5) / SS as f32) / width as f32;
+                        let v = (y as f32 + (sy as f32 + 0.5) / SS as f32) / height as f32;
+                        hits += inside(u, v) as u32;
+                    }
+                }
+                let t = (x as f32 / width as f32 + y as f32 / height as f32) / 2.0;
+                let alpha = (hits * 255 / (SS * SS)) as u8;
+                let color = if alpha == 0 {
+                    [0; 3]
+                } else {
+                    [(255.0 * t) as u8, 60, (255.0 * (1.0 - t)) as u8]
+                };
+                rgba.extend(color);
+                rgba.push(alpha);
+            }
+        }
+        rgba
+    }
+
+    /// Mean absolute difference of premultiplied channels, in 0..=255 units.
+    fn mean_error(a: &[u8], b: &[u8]) -> f64 {
+        let premultiplied = |p: &[u8]| [0, 1, 2].map(|c| p[c] as f64 * p[3] as f64 / 255.0);
+        let total: f64 = a
+            .chunks(4)
+            .zip(b.chunks(4))
+            .map(|(p, q)| {
+                let (pp, qp) = (premultiplied(p), premultiplied(q));
+                (0..3).map(|c| (pp[c] - qp[c]).abs()).sum::<f64>()
+                    + (p[3] as f64 - q[3] as f64).abs()
+            })
+            .sum();
+        total / a.len() as f64
+    }
+
+    #[test]
+    fn lanczos_downscale_beats_bilinear() {
+        let (w, h) = (32, 32);
+        // A 2x (HiDPI) cursor scaled to 1x, against the same shape drawn at 1x
+        let hidpi = ring_cursor(w * 2, h * 2);
+        let reference = ring_cursor(w, h);
+        let bilinear = mean_error(&bilinear_scale(&hidpi, w * 2, h * 2, w, h), &reference);
+        let lanczos = mean_error(&lanczos_scale(&hidpi, w * 2, h * 2, w, h), &reference);
+        assert!(
+            lanczos < bilinear,
+            "lanczos error {:.2} not below bilinear {:.2}",
+            lanczos,
+            bilinear
+        );
+    }
+}
@@ -1,27 +1,40 @@
 use anyhow::{anyhow, Result};
+use std::collections::HashMap;
 use std::mem;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock};
 use tokio::sync::mpsc;
 use tokio::time::{interval, Duration};
 use tracing::{debug, info, warn};
+use windows::core::{s, w};
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
 use windows::Win32::Graphics::Gdi::{
-    CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits,
-    GetObjectW, PatBlt, ReleaseDC, SelectObject,
-    BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLACKNESS, DIB_RGB_COLORS, WHITENESS,
+    CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, EnumDisplayMonitors, GetDC,
+    GetDIBits, GetMonitorInfoW, GetObjectW, MonitorFromPoint, PatBlt, ReleaseDC, SelectObject,
+    BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLACKNESS, DIB_RGB_COLORS, HBITMAP, HDC, HGDIOBJ,
+    HMONITOR, MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONEAREST, MONITOR_DEFAULTTONULL,
+    ROP_CODE, WHITENESS,
+};
+use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+use windows::Win32::UI::HiDpi::{
+    GetDpiForMonitor, GetDpiForSystem, SetProcessDpiAwareness, MDT_EFFECTIVE_DPI,
+    PROCESS_PER_MONITOR_DPI_AWARE,
 };
-use windows::Win32::UI::HiDpi::{GetDpiForSystem, SetProcessDpiAwareness, PROCESS_PER_MONITOR_DPI_AWARE};
-use windows::Win32::Foundation::POINT;
 use windows::Win32::UI::WindowsAndMessaging::{
-    CopyIcon, DestroyIcon, DrawIconEx, GetCursorInfo, GetCursorPos, GetIconInfo,
-    GetSystemMetrics, CURSORINFO, CURSOR_SHOWING, DI_NORMAL, HCURSOR, HICON, ICONINFO,
-    SM_CXSCREEN, SM_CYSCREEN,
-    SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+    CopyIcon, CreateWindowExW, DefWindowProcW, DestroyIcon, DispatchMessageW, DrawIconEx,
+    GetCursorInfo, GetCursorPos, GetIconInfo, GetMessageW, GetSystemMetrics, RegisterClassW,
+    TranslateMessage, CURSORINFO, CURSOR_SHOWING, DI_NORMAL, HCURSOR, HICON, ICONINFO, MSG,
+    SM_CXSCREEN, SM_CXVIRTUALSCREEN, SM_CYSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+    SM_YVIRTUALSCREEN, WINDOW_EX_STYLE, WM_DISPLAYCHANGE, WM_DPICHANGED, WNDCLASSW, WS_OVERLAPPED,
 };
 
 use super::{
-    CachedCursor, CursorEvent, LAST_CURSOR_ID,
-    cache_cursor, encode_animated_webp, encode_static_webp,
-    expand_canvas, add_white_outline, init_cache,
+    add_outline, cache_cursor, check_cursor_dimensions, clear_cache, cursor_id, dpi_scale,
+    encode_animated_webp_with_delays, encode_static_webp, estimate_frame_delay,
+    evict_current_cursor, expand_canvas, init_cache, notify_display_change, outline_style,
+    poll_interval_ms, polls_for, position_event, recover_dual_render_pixel, refresh_dpi_scale,
+    take_display_change, take_recapture_request, wait_next_poll, CachedCursor, CursorEvent,
+    DEFAULT_SEAT, LAST_CURSOR_ID,
 };
 
 /// Last Windows cursor handle (HCURSOR value)
@@ -45,33 +58,74 @@ static EDGE_COOLDOWN: Mutex<u32> = Mutex::new(0);
 /// the current cursor state so the client stays in sync.
 static WAS_NEAR_TOP: Mutex<bool> = Mutex::new(false);
 
-/// Number of cooldown frames after leaving an edge before a hide is allowed.
-/// At ~16ms per poll this is ~320ms — enough to absorb edge position jitter.
-const EDGE_COOLDOWN_FRAMES: u32 = 20;
+/// Whether the cursor is currently outside the monitor selected by
+/// `CAPTURE_MONITOR` (and a hide has already been emitted for it).
+static OFF_CAPTURE_MONITOR: Mutex<bool> = Mutex::new(false);
+
+/// Cooldown after leaving an edge before a hide is allowed — enough to absorb
+/// edge position jitter.  Converted to polls with `polls_for`.
+const EDGE_COOLDOWN_MS: u64 = 320;
 
-/// Number of consecutive polls required before confirming a genuine cursor hide.
-/// At ~16ms per poll this adds ~160ms latency to real hides, which is still
-/// imperceptible but long enough for ptScreenPos to converge to the actual
-/// corner position when the cursor races to a screen edge.
-const HIDE_CONFIRM_FRAMES: u32 = 10;
+/// How long a hide must persist before it is confirmed as genuine.  This adds
+/// ~160ms latency to real hides, which is still imperceptible but long enough
+/// for ptScreenPos to converge to the actual corner position when the cursor
+/// races to a screen edge.
+const HIDE_CONFIRM_MS: u64 = 160;
 
 /// Vertical margin (in pixels) that defines the "top zone" of the screen.
 /// When the cursor leaves this zone moving downward, we force-resend the
 /// cursor state.  Larger than EDGE_MARGIN to cover taskbars and menus.
 const TOP_ZONE_MARGIN: i32 = 100;
 
-/// Default frame delay for animated cursors (ms) - Windows standard is 1 jiffy = ~60ms
+/// Frame delay for animated cursors (ms) when the cursor's own timing can't
+/// be read - the Windows default rate of ~60ms
 const ANIM_FRAME_DELAY_MS: i32 = 60;
 
 /// Maximum animation frames to probe (safety limit)
 const MAX_ANIM_FRAMES: u32 = 120;
 
+/// This many identical frames in a row while probing means DrawIconEx is
+/// clamping the step to the last frame instead of failing or wrapping.
+const ANIM_STALL_RUN: usize = 3;
+
 /// Result of capturing a cursor
 enum CaptureResult {
     /// Successfully captured cursor image
     Cursor(CachedCursor),
+    /// Animated cursor: its first frame as a static stand-in, plus the
+    /// remaining frame rendering and encoding to run off the poll loop
+    FirstFrame(CachedCursor, PendingAnimation),
+}
+
+/// Animation capture left to finish on a blocking thread once the first
+/// frame has been sent.
+struct PendingAnimation {
+    /// HCURSOR value the animation belongs to
+    handle: isize,
+    width: u32,
+    height: u32,
+    hotspot_x: i32,
+    hotspot_y: i32,
+    first_frame: Vec<u8>,
+    /// blake3 of `first_frame`, computed once during capture
+    first_hash: blake3::Hash,
+    /// Per-step delays from `GetCursorFrameInfo`; `None` probes for frames
+    delays: Option<Vec<i32>>,
+}
+
+/// What `GetCursorFrameInfo` says about a cursor's animation steps.
+enum CursorSteps {
+    Static,
+    /// Display time (ms) of each step
+    Animated(Vec<i32>),
+    /// The export is missing or the call failed; only rendering can tell
+    Unknown,
 }
 
+/// Animation queued by the last `capture_cursor` call, picked up by the
+/// capture loop and handed to `spawn_blocking`.
+static PENDING_ANIMATION: Mutex<Option<PendingAnimation>> = Mutex::new(None);
+
 /// Summary of XOR pixel statistics for logging
 struct XorShape {
     /// Number of XOR pixels
@@ -96,24 +150,233 @@ pub fn get_dpi_scale() -> f32 {
     }
 }
 
-/// Check if a single point is within `EDGE_MARGIN` pixels of the virtual screen
-/// boundary.
-fn point_near_edge(pt: &POINT) -> bool {
+/// Effective DPI per monitor (HMONITOR value → DPI), cleared on display
+/// changes so a new scaling setting is picked up.
+static MONITOR_DPI: Mutex<Option<HashMap<isize, u32>>> = Mutex::new(None);
+
+/// Last [`cursor_monitor_dpi_scale`] seen by the capture loop, as `f32`
+/// bits; 0 until the loop has run.
+static CURSOR_MONITOR_SCALE: AtomicU32 = AtomicU32::new(0);
+
+/// DPI scale factor of the monitor the cursor is on.
+///
+/// `GetDpiForSystem` is a single value, which is wrong for cursors on the
+/// other monitors of a mixed-DPI setup.  Falls back to the system DPI.
+pub fn cursor_monitor_dpi_scale() -> f32 {
+    let Some((x, y)) = cursor_position() else {
+        return dpi_scale();
+    };
+    let monitor = unsafe { MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST) };
+    let key = monitor.0 as isize;
+
+    let mut cache_guard = MONITOR_DPI.lock().unwrap();
+    let cache = cache_guard.get_or_insert_with(HashMap::new);
+    if let Some(&dpi) = cache.get(&key) {
+        return dpi as f32 / 96.0;
+    }
+
+    let (mut dpi_x, mut dpi_y) = (0u32, 0u32);
+    if unsafe { GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) }.is_err()
+        || dpi_x == 0
+    {
+        return dpi_scale();
+    }
+    debug!("Monitor {:#x} effective DPI: {}", key, dpi_x);
+    cache.insert(key, dpi_x);
+    dpi_x as f32 / 96.0
+}
+
+/// The cursor monitor's DPI scale as of the capture loop's last poll, for
+/// building cursor messages without querying the monitor again.
+pub fn cached_cursor_monitor_dpi_scale() -> f32 {
+    match CURSOR_MONITOR_SCALE.load(Ordering::Relaxed) {
+        0 => cursor_monitor_dpi_scale(),
+        bits => f32::from_bits(bits),
+    }
+}
+
+/// Monitor selected via `CAPTURE_MONITOR`, normalised to its device name
+/// (`\\.\DISPLAY2`).  Accepts the full device name or just the display
+/// number.  `None` means the cursor is followed across all monitors, which
+/// is also the fallback when the name matches no connected monitor.
+fn capture_monitor() -> Option<&'static str> {
+    static CAPTURE_MONITOR: OnceLock<Option<String>> = OnceLock::new();
+    CAPTURE_MONITOR
+        .get_or_init(|| {
+            let raw = std::env::var("CAPTURE_MONITOR").ok()?;
+            let raw = raw.trim();
+            if raw.is_empty() {
+                return None;
+            }
+            let name = if raw.chars().all(|c| c.is_ascii_digit()) {
+                format!("\\\\.\\DISPLAY{}", raw)
+            } else {
+                raw.to_string()
+            };
+            let known = monitor_names();
+            if !known.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                warn!(
+                    "CAPTURE_MONITOR {} matches no monitor (found {}), ignoring it",
+                    name,
+                    known.join(", ")
+                );
+                return None;
+            }
+            info!("CAPTURE_MONITOR set: cursor hidden while outside {}", name);
+            Some(name)
+        })
+        .as_deref()
+}
+
+/// Device name (e.g. `\\.\DISPLAY1`) and bounds of the monitor containing `pt`.
+fn monitor_at(pt: &POINT) -> Option<(String, RECT)> {
     unsafe {
-        let vx = GetSystemMetrics(SM_XVIRTUALSCREEN);
-        let vy = GetSystemMetrics(SM_YVIRTUALSCREEN);
-        let vw = GetSystemMetrics(SM_CXVIRTUALSCREEN);
-        let vh = GetSystemMetrics(SM_CYVIRTUALSCREEN);
+        let hmon = MonitorFromPoint(*pt, MONITOR_DEFAULTTONULL);
+        if hmon.is_invalid() {
+            return None;
+        }
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+        if !GetMonitorInfoW(hmon, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO).as_bool() {
+            return None;
+        }
+        let end = info
+            .szDevice
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(info.szDevice.len());
+        let name = String::from_utf16_lossy(&info.szDevice[..end]);
+        Some((name, info.monitorInfo.rcMonitor))
+    }
+}
+
+/// Device names of all monitors.
+fn monitor_names() -> Vec<String> {
+    monitor_rects()
+        .iter()
+        .filter_map(|rc| {
+            monitor_at(&POINT {
+                x: rc.left,
+                y: rc.top,
+            })
+        })
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Distance (in pixels) from a desktop edge within which a cleared
+/// `CURSOR_SHOWING` is not trusted.
+const EDGE_MARGIN: i32 = 30;
+
+/// Bounds of every monitor, cleared on display changes.
+static MONITOR_RECTS: Mutex<Option<Vec<RECT>>> = Mutex::new(None);
+
+unsafe extern "system" fn collect_monitor_rect(
+    _monitor: HMONITOR,
+    _hdc: HDC,
+    rect: *mut RECT,
+    data: LPARAM,
+) -> BOOL {
+    let rects = &mut *(data.0 as *mut Vec<RECT>);
+    rects.push(*rect);
+    BOOL(1)
+}
 
-        const EDGE_MARGIN: i32 = 30;
+/// Bounds of all monitors, enumerated once per display configuration.
+fn monitor_rects() -> Vec<RECT> {
+    let mut guard = MONITOR_RECTS.lock().unwrap();
+    guard
+        .get_or_insert_with(|| {
+            let mut rects: Vec<RECT> = Vec::new();
+            unsafe {
+                let _ = EnumDisplayMonitors(
+                    HDC::default(),
+                    None,
+                    Some(collect_monitor_rect),
+                    LPARAM(&mut rects as *mut Vec<RECT> as isize),
+                );
+            }
+            debug!("Monitor layout: {:?}", rects);
+            rects
+        })
+        .clone()
+}
+
+fn rect_contains(rc: &RECT, x: i32, y: i32) -> bool {
+    x >= rc.left && x < rc.right && y >= rc.top && y < rc.bottom
+}
 
-        pt.x <= vx + EDGE_MARGIN
-            || pt.y <= vy + EDGE_MARGIN
-            || pt.x >= vx + vw - EDGE_MARGIN
-            || pt.y >= vy + vh - EDGE_MARGIN
+/// Which side of `rc` a point is within `EDGE_MARGIN` of, counting only
+/// sides where `is_outer` says the desktop ends.
+fn near_side(pt: &POINT, rc: &RECT, is_outer: impl Fn(i32, i32) -> bool) -> Option<&'static str> {
+    let beyond = EDGE_MARGIN + 1;
+    if pt.x <= rc.left + EDGE_MARGIN && is_outer(rc.left - beyond, pt.y) {
+        Some("left")
+    } else if pt.y <= rc.top + EDGE_MARGIN && is_outer(pt.x, rc.top - beyond) {
+        Some("top")
+    } else if pt.x >= rc.right - EDGE_MARGIN && is_outer(rc.right + beyond, pt.y) {
+        Some("right")
+    } else if pt.y >= rc.bottom - EDGE_MARGIN && is_outer(pt.x, rc.bottom + beyond) {
+        Some("bottom")
+    } else {
+        None
     }
 }
 
+/// Check if a single point is within `EDGE_MARGIN` pixels of the desktop's
+/// edge, or of the captured monitor's boundary when `CAPTURE_MONITOR` is set.
+///
+/// The desktop is the union of the monitor rectangles, not the virtual
+/// screen's bounding box: with monitors of different sizes, the border of a
+/// smaller monitor facing an uncovered region is an edge too.  Monitor sides
+/// shared with a neighbour are not.  A point outside every monitor (in such
+/// a region) counts as at an edge.
+fn point_near_edge(pt: &POINT) -> bool {
+    if let Some((name, rc)) = capture_monitor().and(monitor_at(pt)) {
+        let side = near_side(pt, &rc, |_, _| true);
+        if let Some(side) = side {
+            debug!("Cursor ({}, {}) near {} edge of {}", pt.x, pt.y, side, name);
+        }
+        return side.is_some();
+    }
+
+    let rects = monitor_rects();
+    if rects.is_empty() {
+        // Enumeration failed; fall back to the virtual screen bounds.
+        let rc = unsafe {
+            let (x, y) = (
+                GetSystemMetrics(SM_XVIRTUALSCREEN),
+                GetSystemMetrics(SM_YVIRTUALSCREEN),
+            );
+            RECT {
+                left: x,
+                top: y,
+                right: x + GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                bottom: y + GetSystemMetrics(SM_CYVIRTUALSCREEN),
+            }
+        };
+        return near_side(pt, &rc, |_, _| true).is_some();
+    }
+
+    let Some((index, rc)) = rects
+        .iter()
+        .enumerate()
+        .find(|(_, rc)| rect_contains(rc, pt.x, pt.y))
+    else {
+        debug!("Cursor ({}, {}) outside every monitor", pt.x, pt.y);
+        return true;
+    };
+    let outside_desktop = |x: i32, y: i32| !rects.iter().any(|r| rect_contains(r, x, y));
+    let side = near_side(pt, rc, outside_desktop);
+    if let Some(side) = side {
+        debug!(
+            "Cursor ({}, {}) near {} edge of monitor {} ({},{})-({},{})",
+            pt.x, pt.y, side, index, rc.left, rc.top, rc.right, rc.bottom
+        );
+    }
+    side.is_some()
+}
+
 /// Check if a point is within `TOP_ZONE_MARGIN` pixels of the top of the
 /// virtual screen.
 fn point_near_top(pt: &POINT) -> bool {
@@ -149,6 +412,98 @@ fn is_cursor_at_screen_edge(info_pt: &POINT) -> bool {
     false
 }
 
+/// Window procedure for the hidden display-change listener window.
+unsafe extern "system" fn display_listener_wnd_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_DISPLAYCHANGE || msg == WM_DPICHANGED {
+        debug!("Display change message received (msg=0x{:04x})", msg);
+        notify_display_change();
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// Spawn a thread owning a hidden top-level window that receives
+/// `WM_DISPLAYCHANGE` / `WM_DPICHANGED`.  Message-only windows do not get
+/// these broadcasts, so a real (never shown) window is required.
+fn spawn_display_change_listener() {
+    let spawned = std::thread::Builder::new()
+        .name("display-change-listener".into())
+        .spawn(|| unsafe {
+            let instance = match GetModuleHandleW(None) {
+                Ok(h) => h,
+                Err(e) => {
+                    warn!(
+                        "GetModuleHandleW failed, display changes will not be detected: {}",
+                        e
+                    );
+                    return;
+                }
+            };
+
+            let class_name = w!("DeragabuDisplayListener");
+            let wc = WNDCLASSW {
+                lpfnWndProc: Some(display_listener_wnd_proc),
+                hInstance: instance.into(),
+                lpszClassName: class_name,
+                ..Default::default()
+            };
+            if RegisterClassW(&wc) == 0 {
+                warn!("RegisterClassW failed, display changes will not be detected");
+                return;
+            }
+
+            if let Err(e) = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                class_name,
+                w!(""),
+                WS_OVERLAPPED,
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                instance,
+                None,
+            ) {
+                warn!(
+                    "CreateWindowExW failed, display changes will not be detected: {}",
+                    e
+                );
+                return;
+            }
+
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        });
+
+    if let Err(e) = spawned {
+        warn!("Failed to spawn display-change listener thread: {}", e);
+    }
+}
+
+/// Invalidate cached cursors and force the current cursor to be re-captured
+/// (and re-emitted) at the new scale.
+fn handle_display_change() {
+    let dpi_scale = refresh_dpi_scale();
+    info!(
+        "Display configuration changed (DPI scale now {:.2}), re-capturing cursor",
+        dpi_scale
+    );
+    clear_cache();
+    *MONITOR_DPI.lock().unwrap() = None;
+    *MONITOR_RECTS.lock().unwrap() = None;
+    *RENDER_SURFACE.lock().unwrap() = None;
+    *LAST_CURSOR_HANDLE.lock().unwrap() = 0;
+}
+
 /// Run cursor capture loop
 pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
     // Enable DPI awareness
@@ -157,14 +512,60 @@ pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
     }
 
     init_cache();
+    spawn_display_change_listener();
+    // Resolve CAPTURE_MONITOR now so a bad name is reported at startup.
+    capture_monitor();
+
+    let dpi_scale = refresh_dpi_scale();
+    let poll_ms = poll_interval_ms();
+    info!(
+        "Starting cursor capture (DPI scale: {:.2}, poll: {} ms)",
+        dpi_scale, poll_ms
+    );
 
-    let dpi_scale = get_dpi_scale();
-    info!("Starting cursor capture (DPI scale: {:.2})", dpi_scale);
-
-    let mut poll_interval = interval(Duration::from_millis(16)); // ~60fps check
+    let mut poll_interval = interval(Duration::from_millis(poll_ms));
+    let mut monitor_scale = cursor_monitor_dpi_scale();
+    // Finished animations come back here so only this loop sends cursor
+    // events, keeping them ordered.
+    let (anim_tx, mut anim_rx) = mpsc::unbounded_channel::<(isize, Result<Option<CachedCursor>>)>();
 
     loop {
-        poll_interval.tick().await;
+        if !wait_next_poll(&mut poll_interval).await {
+            info!("Shutdown requested, stopping cursor capture");
+            break;
+        }
+
+        while let Ok((handle, result)) = anim_rx.try_recv() {
+            if let Some(event) = finish_animation(handle, result) {
+                if tx.send(event).await.is_err() {
+                    warn!("Receiver closed, stopping cursor capture");
+                    return Ok(());
+                }
+            }
+        }
+
+        if take_display_change() {
+            handle_display_change();
+        }
+        if take_recapture_request() {
+            debug!("Re-capturing live cursor on client request");
+            evict_current_cursor();
+            *LAST_CURSOR_HANDLE.lock().unwrap() = 0;
+        }
+
+        // Crossing onto a monitor with a different DPI: re-send the cursor so
+        // the client picks up the new scale (and Windows' re-rendered bitmap)
+        let scale = cursor_monitor_dpi_scale();
+        CURSOR_MONITOR_SCALE.store(scale.to_bits(), Ordering::Relaxed);
+        if (scale - monitor_scale).abs() > 0.01 {
+            debug!(
+                "Cursor moved to a monitor with DPI scale {:.2}, re-capturing",
+                scale
+            );
+            monitor_scale = scale;
+            evict_current_cursor();
+            *LAST_CURSOR_HANDLE.lock().unwrap() = 0;
+        }
 
         match capture_cursor() {
             Ok(Some(event)) => {
@@ -178,12 +579,61 @@ pub async fn run_cursor_capture(tx: mpsc::Sender<CursorEvent>) -> Result<()> {
                 warn!("Failed to capture cursor: {}", e);
             }
         }
+        if let Some(pending) = PENDING_ANIMATION.lock().unwrap().take() {
+            let done = anim_tx.clone();
+            tokio::task::spawn_blocking(move || {
+                let handle = pending.handle;
+                let _ = done.send((handle, unsafe { pending.finish() }));
+            });
+        }
+        if let Some(event) = cursor_position().and_then(|(x, y)| position_event(x, y)) {
+            if tx.send(event).await.is_err() {
+                warn!("Receiver closed, stopping cursor capture");
+                break;
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Cache a finished animation and announce it, unless the cursor has changed
+/// since its first frame was sent.
+fn finish_animation(handle: isize, result: Result<Option<CachedCursor>>) -> Option<CursorEvent> {
+    if *LAST_CURSOR_HANDLE.lock().unwrap() != handle {
+        debug!("Cursor changed while its animation was being captured, discarding it");
+        return None;
+    }
+    match result {
+        Ok(Some(cached)) => {
+            let (cursor_id, _is_new) = cache_cursor(cached);
+            Some(CursorEvent::CursorChanged {
+                seat: DEFAULT_SEAT,
+                cursor_id,
+            })
+        }
+        // No further frames after all; the first frame stays.
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Failed to capture cursor animation: {}", e);
+            None
+        }
+    }
+}
+
+/// Current cursor position in physical screen pixels.
+fn cursor_position() -> Option<(i32, i32)> {
+    let mut pt = POINT::default();
+    unsafe { GetCursorPos(&mut pt) }.ok()?;
+    Some((pt.x, pt.y))
+}
+
 /// Capture current cursor and return event if changed.
+///
+/// Image capture (`capture_full_cursor`: copy, render, hash, encode) only
+/// runs when the cursor handle differs from `LAST_CURSOR_HANDLE`.  Pure
+/// pointer movement keeps the handle, so it costs one `GetCursorInfo` call
+/// here; the position itself is reported by `cursor_position`.
 fn capture_cursor() -> Result<Option<CursorEvent>> {
     unsafe {
         let mut cursor_info = CURSORINFO {
@@ -195,6 +645,37 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             return Err(anyhow!("GetCursorInfo failed"));
         }
 
+        // CAPTURE_MONITOR: hide the cursor while it is on another monitor.
+        // This takes precedence over the edge heuristics below — crossing to
+        // another monitor is a real hide, not edge jitter.
+        if let Some(target) = capture_monitor() {
+            let on_target = monitor_at(&cursor_info.ptScreenPos)
+                .is_some_and(|(name, _)| name.eq_ignore_ascii_case(target));
+            let mut off = OFF_CAPTURE_MONITOR.lock().unwrap();
+            if !on_target {
+                if *off {
+                    return Ok(None);
+                }
+                *off = true;
+                // Reset edge state so the return isn't mistaken for an edge bounce,
+                // and clear the handle so the cursor is re-emitted on return.
+                *WAS_AT_EDGE.lock().unwrap() = false;
+                *EDGE_COOLDOWN.lock().unwrap() = 0;
+                *HIDE_COUNTER.lock().unwrap() = 0;
+                *LAST_CURSOR_HANDLE.lock().unwrap() = 0;
+                *LAST_CURSOR_ID.lock().unwrap() = None;
+                debug!(
+                    "Cursor left capture monitor {} (at {},{})",
+                    target, cursor_info.ptScreenPos.x, cursor_info.ptScreenPos.y
+                );
+                return Ok(Some(CursorEvent::CursorHidden { seat: DEFAULT_SEAT }));
+            }
+            if *off {
+                *off = false;
+                debug!("Cursor returned to capture monitor {}", target);
+            }
+        }
+
         // Cursor not showing
         if cursor_info.flags.0 & CURSOR_SHOWING.0 == 0 {
             // Check if cursor is at screen edge — if so, ignore the hide.
@@ -218,7 +699,7 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
                 // Mark/keep edge flag
                 *WAS_AT_EDGE.lock().unwrap() = true;
                 // Reset cooldown
-                *EDGE_COOLDOWN.lock().unwrap() = EDGE_COOLDOWN_FRAMES;
+                *EDGE_COOLDOWN.lock().unwrap() = polls_for(EDGE_COOLDOWN_MS);
                 return Ok(None);
             }
 
@@ -227,16 +708,16 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             // This is intentionally generous to avoid false hides when the
             // cursor races to a corner — the position fields may need
             // several frames to converge to the actual edge position.
+            let hide_confirm_frames = polls_for(HIDE_CONFIRM_MS);
             let mut counter = HIDE_COUNTER.lock().unwrap();
             *counter += 1;
 
             debug!(
                 "Cursor not-showing, not at edge (ptScreenPos={},{}) — hide counter {}/{}",
-                cursor_info.ptScreenPos.x, cursor_info.ptScreenPos.y,
-                *counter, HIDE_CONFIRM_FRAMES
+                cursor_info.ptScreenPos.x, cursor_info.ptScreenPos.y, *counter, hide_confirm_frames
             );
 
-            if *counter < HIDE_CONFIRM_FRAMES {
+            if *counter < hide_confirm_frames {
                 return Ok(None); // wait for more confirmations
             }
 
@@ -246,8 +727,11 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
                 *last = 0;
                 *LAST_CURSOR_ID.lock().unwrap() = None;
                 *counter = 0;
-                debug!("Cursor hidden (confirmed after {} frames, not at edge)", HIDE_CONFIRM_FRAMES);
-                return Ok(Some(CursorEvent::CursorHidden));
+                debug!(
+                    "Cursor hidden (confirmed after {} frames, not at edge)",
+                    hide_confirm_frames
+                );
+                return Ok(Some(CursorEvent::CursorHidden { seat: DEFAULT_SEAT }));
             }
             *counter = 0;
             return Ok(None);
@@ -265,7 +749,7 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
         let currently_at_edge = is_cursor_at_screen_edge(&cursor_info.ptScreenPos);
         if currently_at_edge {
             *WAS_AT_EDGE.lock().unwrap() = true;
-            *EDGE_COOLDOWN.lock().unwrap() = EDGE_COOLDOWN_FRAMES;
+            *EDGE_COOLDOWN.lock().unwrap() = polls_for(EDGE_COOLDOWN_MS);
         }
 
         // If the cursor was previously at a screen edge and has now moved away,
@@ -274,7 +758,9 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             // Tick down the cooldown while cursor is visible and away from edge
             {
                 let mut cd = EDGE_COOLDOWN.lock().unwrap();
-                if *cd > 0 { *cd -= 1; }
+                if *cd > 0 {
+                    *cd -= 1;
+                }
             }
             let mut flag = WAS_AT_EDGE.lock().unwrap();
             let was = *flag;
@@ -320,7 +806,10 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             }
             let last_id = LAST_CURSOR_ID.lock().unwrap().clone();
             if let Some(cursor_id) = last_id {
-                return Ok(Some(CursorEvent::CursorChanged(cursor_id)));
+                return Ok(Some(CursorEvent::CursorChanged {
+                    seat: DEFAULT_SEAT,
+                    cursor_id,
+                }));
             }
             // If no cached id, fall through to full capture below
         }
@@ -331,22 +820,37 @@ fn capture_cursor() -> Result<Option<CursorEvent>> {
             *last = cursor_handle;
         }
 
-        // Capture the cursor (with all animation frames if animated)
-        let result = capture_full_cursor(hcursor)?;
-
-        let CaptureResult::Cursor(cached) = result;
+        // Capture the cursor.  Animated cursors are sent as their first frame
+        // now; the full animation follows from a blocking task.
+        let cached = match capture_full_cursor(hcursor)? {
+            CaptureResult::Cursor(cached) => cached,
+            CaptureResult::FirstFrame(cached, pending) => {
+                *PENDING_ANIMATION.lock().unwrap() = Some(pending);
+                cached
+            }
+        };
 
         let (cursor_id, _is_new) = cache_cursor(cached);
-        Ok(Some(CursorEvent::CursorChanged(cursor_id)))
+        Ok(Some(CursorEvent::CursorChanged {
+            seat: DEFAULT_SEAT,
+            cursor_id,
+        }))
     }
 }
 
-/// Capture a cursor with all its animation frames and encode as WebP.
+/// Number of `capture_full_cursor` calls, for tests.
+#[cfg(test)]
+static FULL_CAPTURES: AtomicU32 = AtomicU32::new(0);
+
+/// Capture a cursor and encode it as WebP.
 /// For static cursors: returns a single-frame lossless WebP.
-/// For animated cursors: probes all frames via DrawIconEx step parameter,
-/// then encodes them as an animated WebP.
+/// For animated cursors: returns the first frame plus a [`PendingAnimation`]
+/// that renders all frames via the DrawIconEx step parameter and encodes
+/// them as an animated WebP.
 /// XOR/inversion cursors are also rendered as images.
 unsafe fn capture_full_cursor(hcursor: HCURSOR) -> Result<CaptureResult> {
+    #[cfg(test)]
+    FULL_CAPTURES.fetch_add(1, Ordering::Relaxed);
     let hicon = CopyIcon(hcursor)?;
     let mut icon_info = ICONINFO::default();
 
@@ -381,16 +885,24 @@ unsafe fn capture_full_cursor(hcursor: HCURSOR) -> Result<CaptureResult> {
         return Err(anyhow!("No bitmap data in cursor"));
     };
 
-    if width == 0 || height == 0 {
-        if !icon_info.hbmColor.is_invalid() { let _ = DeleteObject(icon_info.hbmColor); }
-        if !icon_info.hbmMask.is_invalid() { let _ = DeleteObject(icon_info.hbmMask); }
+    if let Err(e) = check_cursor_dimensions(width, height) {
+        if !icon_info.hbmColor.is_invalid() {
+            let _ = DeleteObject(icon_info.hbmColor);
+        }
+        if !icon_info.hbmMask.is_invalid() {
+            let _ = DeleteObject(icon_info.hbmMask);
+        }
         DestroyIcon(hicon)?;
-        return Err(anyhow!("Cursor has zero dimensions"));
+        return Err(e);
     }
 
     // Clean up bitmaps from GetIconInfo
-    if !icon_info.hbmColor.is_invalid() { let _ = DeleteObject(icon_info.hbmColor); }
-    if !icon_info.hbmMask.is_invalid() { let _ = DeleteObject(icon_info.hbmMask); }
+    if !icon_info.hbmColor.is_invalid() {
+        let _ = DeleteObject(icon_info.hbmColor);
+    }
+    if !icon_info.hbmMask.is_invalid() {
+        let _ = DeleteObject(icon_info.hbmMask);
+    }
     DestroyIcon(hicon)?;
 
     if is_monochrome {
@@ -401,36 +913,46 @@ unsafe fn capture_full_cursor(hcursor: HCURSOR) -> Result<CaptureResult> {
             DestroyIcon(hicon_copy)?;
             return Err(anyhow!("GetIconInfo failed (monochrome)"));
         }
-        if !icon_info2.hbmColor.is_invalid() { let _ = DeleteObject(icon_info2.hbmColor); }
+        if !icon_info2.hbmColor.is_invalid() {
+            let _ = DeleteObject(icon_info2.hbmColor);
+        }
         let result = get_monochrome_cursor_rgba(icon_info2.hbmMask);
         let _ = DeleteObject(icon_info2.hbmMask);
         DestroyIcon(hicon_copy)?;
         let (rgba, w, h, has_xor, xor_shape) = result?;
 
         if has_xor {
-            const XOR_PAD: u32 = 4;
-            info!("Monochrome XOR cursor detected, rendering image directly ({}x{}, {} XOR pixels)", w, h, xor_shape.count);
-            let (mut expanded, ew, eh) = expand_canvas(&rgba, w, h, XOR_PAD);
-            add_white_outline(&mut expanded, ew, eh, XOR_PAD as i32);
+            let outline = outline_style();
+            info!(
+                "Monochrome XOR cursor detected, rendering image directly ({}x{}, {} XOR pixels)",
+                w, h, xor_shape.count
+            );
+            let (mut expanded, ew, eh) = expand_canvas(&rgba, w, h, outline.radius)?;
+            add_outline(&mut expanded, ew, eh, outline);
 
             let webp_data = encode_static_webp(&expanded, ew, eh)?;
-            let cursor_id = format!("cur_{}", &blake3::hash(&expanded).to_hex()[..12]);
+            let (hotspot_x, hotspot_y) = (
+                hotspot_x + outline.radius as i32,
+                hotspot_y + outline.radius as i32,
+            );
+            let cursor_id = cursor_id("cur", &expanded, ew, eh, hotspot_x, hotspot_y);
 
             return Ok(CaptureResult::Cursor(CachedCursor {
                 id: cursor_id,
                 webp_data,
                 width: ew,
                 height: eh,
-                hotspot_x: hotspot_x + XOR_PAD as i32,
-                hotspot_y: hotspot_y + XOR_PAD as i32,
+                hotspot_x,
+                hotspot_y,
                 is_animated: false,
                 frame_count: 1,
                 frame_delay_ms: 0,
+                name: String::new(),
             }));
         }
 
         let webp_data = encode_static_webp(&rgba, w, h)?;
-        let cursor_id = format!("cur_{}", &blake3::hash(&rgba).to_hex()[..12]);
+        let cursor_id = cursor_id("cur", &rgba, w, h, hotspot_x, hotspot_y);
 
         return Ok(CaptureResult::Cursor(CachedCursor {
             id: cursor_id,
@@ -442,78 +964,209 @@ unsafe fn capture_full_cursor(hcursor: HCURSOR) -> Result<CaptureResult> {
             is_animated: false,
             frame_count: 1,
             frame_delay_ms: 0,
+            name: String::new(),
         }));
     }
 
-    // Color cursor - render first frame to check for XOR pixels
+    // Color cursor - render first frame to check for XOR pixels.
+    // Everything below (render, probe, hash, encode) only runs when the
+    // cursor handle changed; pure pointer movement never reaches this point.
     let hicon_raw: HICON = mem::transmute(hcursor);
-    let (first_frame, has_xor, xor_shape) = render_cursor_frame_with_xor_detection(hicon_raw, width, height, 0)?;
+    let (first_frame, has_xor, xor_shape) =
+        render_cursor_frame_with_xor_detection(hicon_raw, width, height, 0)?;
 
     if has_xor {
-        info!("Color XOR cursor detected, rendering image directly ({}x{}, {} XOR pixels)", width, height, xor_shape.count);
+        info!(
+            "Color XOR cursor detected, rendering image directly ({}x{}, {} XOR pixels)",
+            width, height, xor_shape.count
+        );
     }
 
-    // Probe animation frames using the original HCURSOR handle.
-    let frames = probe_animation_frames_with_first(hicon_raw, width, height, first_frame)?;
-
-    if frames.len() <= 1 {
-        // Static cursor
-        let rgba = frames[0].clone();
-
-        if has_xor {
-            const XOR_PAD: u32 = 4;
-            let (mut expanded, ew, eh) = expand_canvas(&rgba, width, height, XOR_PAD);
-            add_white_outline(&mut expanded, ew, eh, XOR_PAD as i32);
-
-            let webp_data = encode_static_webp(&expanded, ew, eh)?;
-            let cursor_id = format!("cur_{}", &blake3::hash(&expanded).to_hex()[..12]);
+    // Only a cheap check here: the cursor reports animation steps, or, when
+    // GetCursorFrameInfo can't tell, step 1 renders differently from step 0.
+    // The full render/encode is deferred.
+    let (animated, delays) = match cursor_steps(hcursor) {
+        CursorSteps::Static => (false, None),
+        CursorSteps::Animated(delays) => (true, Some(delays)),
+        CursorSteps::Unknown => (
+            render_cursor_frame(hicon_raw, width, height, 1)
+                .is_ok_and(|step1| step1 != first_frame),
+            None,
+        ),
+    };
 
-            return Ok(CaptureResult::Cursor(CachedCursor {
-                id: cursor_id,
-                webp_data,
-                width: ew,
-                height: eh,
-                hotspot_x: hotspot_x + XOR_PAD as i32,
-                hotspot_y: hotspot_y + XOR_PAD as i32,
-                is_animated: false,
-                frame_count: 1,
-                frame_delay_ms: 0,
-            }));
-        }
+    // Hashed once here; the static id and the animation probe both reuse it.
+    let first_hash = blake3::hash(&first_frame);
+    let cached = static_color_cursor(
+        &first_frame,
+        &first_hash,
+        width,
+        height,
+        hotspot_x,
+        hotspot_y,
+        has_xor,
+    )?;
+    if !animated {
+        return Ok(CaptureResult::Cursor(cached));
+    }
+    Ok(CaptureResult::FirstFrame(
+        cached,
+        PendingAnimation {
+            handle: hcursor.0 as isize,
+            width,
+            height,
+            hotspot_x,
+            hotspot_y,
+            first_frame,
+            first_hash,
+            delays,
+        },
+    ))
+}
 
-        let webp_data = encode_static_webp(&rgba, width, height)?;
-        let cursor_id = format!("cur_{}", &blake3::hash(&rgba).to_hex()[..12]);
+/// Encode a single color-cursor frame, outlined when it contains XOR pixels.
+/// The id is derived from `frame_hash` (blake3 of `rgba`) rather than by
+/// hashing the pixels again.
+fn static_color_cursor(
+    rgba: &[u8],
+    frame_hash: &blake3::Hash,
+    width: u32,
+    height: u32,
+    hotspot_x: i32,
+    hotspot_y: i32,
+    has_xor: bool,
+) -> Result<CachedCursor> {
+    if has_xor {
+        let outline = outline_style();
+        let (mut expanded, ew, eh) = expand_canvas(rgba, width, height, outline.radius)?;
+        add_outline(&mut expanded, ew, eh, outline);
+
+        let webp_data = encode_static_webp(&expanded, ew, eh)?;
+        let (hotspot_x, hotspot_y) = (
+            hotspot_x + outline.radius as i32,
+            hotspot_y + outline.radius as i32,
+        );
+        // The outline is a function of the frame and the style, so the frame
+        // hash plus the outline color identifies the expanded image.
+        let mut content = frame_hash.as_bytes().to_vec();
+        content.extend_from_slice(&outline.color);
+        let cursor_id = cursor_id("cur", &content, ew, eh, hotspot_x, hotspot_y);
 
-        Ok(CaptureResult::Cursor(CachedCursor {
+        return Ok(CachedCursor {
             id: cursor_id,
             webp_data,
-            width,
-            height,
+            width: ew,
+            height: eh,
             hotspot_x,
             hotspot_y,
             is_animated: false,
             frame_count: 1,
             frame_delay_ms: 0,
-        }))
-    } else {
-        // Animated cursor - encode as animated WebP
+            name: String::new(),
+        });
+    }
+
+    let webp_data = encode_static_webp(rgba, width, height)?;
+    let cursor_id = cursor_id(
+        "cur",
+        frame_hash.as_bytes(),
+        width,
+        height,
+        hotspot_x,
+        hotspot_y,
+    );
+
+    Ok(CachedCursor {
+        id: cursor_id,
+        webp_data,
+        width,
+        height,
+        hotspot_x,
+        hotspot_y,
+        is_animated: false,
+        frame_count: 1,
+        frame_delay_ms: 0,
+        name: String::new(),
+    })
+}
+
+impl PendingAnimation {
+    /// Render the remaining animation frames and encode them as an animated
+    /// WebP.  Blocking; returns `None` if only one frame turned up or the
+    /// cursor changed while rendering.
+    unsafe fn finish(self) -> Result<Option<CachedCursor>> {
+        let PendingAnimation {
+            handle,
+            width,
+            height,
+            hotspot_x,
+            hotspot_y,
+            first_frame,
+            first_hash,
+            delays,
+        } = self;
+        let hicon = HICON(handle as *mut std::ffi::c_void);
+        // The HCURSOR isn't owned here and its owner may destroy it once the
+        // cursor changes, so stop rendering as soon as it is no longer current.
+        let still_current = || *LAST_CURSOR_HANDLE.lock().unwrap() == handle;
+
+        // Render animation frames using the original HCURSOR handle: every step
+        // with its authored display time when the cursor reports them, otherwise
+        // by probing for unique frames at the default rate.
+        let first = (first_frame, first_hash);
+        let (frames, frame_hashes, delays) = match delays {
+            Some(mut delays) => {
+                let Some((frames, hashes)) = render_animation_steps(
+                    hicon,
+                    width,
+                    height,
+                    first,
+                    delays.len() as u32,
+                    still_current,
+                ) else {
+                    return Ok(None);
+                };
+                delays.truncate(frames.len());
+                (frames, hashes, delays)
+            }
+            None => {
+                let Some((frames, hashes)) =
+                    probe_animation_frames_with_first(hicon, width, height, first, still_current)?
+                else {
+                    return Ok(None);
+                };
+                let delays = vec![ANIM_FRAME_DELAY_MS; frames.len()];
+                (frames, hashes, delays)
+            }
+        };
+        if frames.len() <= 1 {
+            return Ok(None);
+        }
+
         let frame_count = frames.len() as u32;
-        let frame_delay = ANIM_FRAME_DELAY_MS as u32;
+        // CursorData carries a single delay; the WebP has the exact timing.
+        let mean_delay = delays.iter().sum::<i32>() / delays.len() as i32;
+        let frame_delay = estimate_frame_delay(0, mean_delay.max(0) as u32);
 
         let mut hasher_input = Vec::new();
-        for frame in &frames {
-            hasher_input.extend_from_slice(blake3::hash(frame).as_bytes());
+        for (hash, delay) in frame_hashes.iter().zip(&delays) {
+            hasher_input.extend_from_slice(hash.as_bytes());
+            hasher_input.extend_from_slice(&delay.to_le_bytes());
         }
-        let cursor_id = format!("ani_{}", &blake3::hash(&hasher_input).to_hex()[..12]);
+        let cursor_id = cursor_id("ani", &hasher_input, width, height, hotspot_x, hotspot_y);
 
-        let webp_data = encode_animated_webp(&frames, width, height, ANIM_FRAME_DELAY_MS)?;
+        let webp_data = encode_animated_webp_with_delays(&frames, width, height, &delays)?;
 
         info!(
-            "Animated cursor encoded: {} frames, {}x{}, delay={}ms, webp={} bytes",
-            frame_count, width, height, frame_delay, webp_data.len()
+            "Animated cursor encoded: {} frames, {}x{}, delays={:?}ms, webp={} bytes",
+            frame_count,
+            width,
+            height,
+            delays,
+            webp_data.len()
         );
 
-        Ok(CaptureResult::Cursor(CachedCursor {
+        Ok(Some(CachedCursor {
             id: cursor_id,
             webp_data,
             width,
@@ -523,129 +1176,327 @@ unsafe fn capture_full_cursor(hcursor: HCURSOR) -> Result<CaptureResult> {
             is_animated: true,
             frame_count,
             frame_delay_ms: frame_delay,
+            name: String::new(),
         }))
     }
 }
 
-/// Probe all unique animation frames for a cursor, reusing an already-rendered first frame.
+/// `GetCursorFrameInfo(hcursor, 0, step, &rate, &steps)`: undocumented but
+/// long-stable user32 export returning the display rate of one animation
+/// step (in jiffies, 1/60 s) and the number of steps, from the cursor's
+/// `.ani` `rate`/`seq` chunks.  Returns a null cursor on failure.
+type GetCursorFrameInfoFn =
+    unsafe extern "system" fn(HCURSOR, u32, u32, *mut u32, *mut u32) -> HCURSOR;
+
+fn get_cursor_frame_info() -> Option<GetCursorFrameInfoFn> {
+    static FRAME_INFO: OnceLock<Option<GetCursorFrameInfoFn>> = OnceLock::new();
+    *FRAME_INFO.get_or_init(|| unsafe {
+        let user32 = GetModuleHandleW(w!("user32.dll")).ok()?;
+        let proc = GetProcAddress(user32, s!("GetCursorFrameInfo"));
+        if proc.is_none() {
+            warn!("GetCursorFrameInfo not available, animated cursors use a fixed frame delay");
+        }
+        proc.map(|p| {
+            mem::transmute::<unsafe extern "system" fn() -> isize, GetCursorFrameInfoFn>(p)
+        })
+    })
+}
+
+/// Animation steps of `hcursor` with the display time (ms) of each.
+unsafe fn cursor_steps(hcursor: HCURSOR) -> CursorSteps {
+    let Some(frame_info) = get_cursor_frame_info() else {
+        return CursorSteps::Unknown;
+    };
+    let (mut rate, mut steps) = (0u32, 0u32);
+    if frame_info(hcursor, 0, 0, &mut rate, &mut steps).is_invalid() {
+        return CursorSteps::Unknown;
+    }
+    if steps <= 1 {
+        return CursorSteps::Static;
+    }
+
+    let steps = steps.min(MAX_ANIM_FRAMES);
+    let mut delays = Vec::with_capacity(steps as usize);
+    for step in 0..steps {
+        let mut step_count = 0u32;
+        if frame_info(hcursor, 0, step, &mut rate, &mut step_count).is_invalid() {
+            return CursorSteps::Unknown;
+        }
+        delays.push(if rate == 0 {
+            ANIM_FRAME_DELAY_MS
+        } else {
+            (rate * 1000 / 60) as i32
+        });
+    }
+    debug!(
+        "Cursor animation timing: {} steps, delays {:?}ms",
+        steps, delays
+    );
+    CursorSteps::Animated(delays)
+}
+
+/// Rendered RGBA frames with the blake3 hash of each.
+type RenderedFrames = (Vec<Vec<u8>>, Vec<blake3::Hash>);
+
+/// Render animation steps `0..steps` (step 0 already rendered, with its
+/// hash, as `first`), keeping repeated frames since `.ani` sequences may show
+/// a frame more than once.  Stops early if a step fails to render; returns
+/// `None` once `still_current` says the cursor is gone.
+unsafe fn render_animation_steps(
+    hicon: HICON,
+    width: u32,
+    height: u32,
+    (first_frame, first_hash): (Vec<u8>, blake3::Hash),
+    steps: u32,
+    still_current: impl Fn() -> bool,
+) -> Option<RenderedFrames> {
+    let mut frame_hashes = vec![first_hash];
+    let mut frames = vec![first_frame];
+
+    for step in 1..steps {
+        if !still_current() {
+            debug!(
+                "Cursor changed at animation step {}, abandoning render",
+                step
+            );
+            return None;
+        }
+        match render_cursor_frame(hicon, width, height, step) {
+            Ok(rgba) => {
+                frame_hashes.push(blake3::hash(&rgba));
+                frames.push(rgba);
+            }
+            Err(e) => {
+                debug!(
+                    "Rendering animation step {} failed ({}), keeping {} frames",
+                    step,
+                    e,
+                    frames.len()
+                );
+                break;
+            }
+        }
+    }
+    Some((frames, frame_hashes))
+}
+
+/// Probe all unique animation frames for a cursor, reusing an already-rendered
+/// first frame and its hash.
+///
+/// Returns the frames together with their blake3 hashes so callers can derive
+/// the cursor id without hashing the pixel data a second time, or `None` once
+/// `still_current` says the cursor is gone.
 unsafe fn probe_animation_frames_with_first(
     hicon: HICON,
     width: u32,
     height: u32,
-    first_frame: Vec<u8>,
-) -> Result<Vec<Vec<u8>>> {
+    (first_frame, first_hash): (Vec<u8>, blake3::Hash),
+    still_current: impl Fn() -> bool,
+) -> Result<Option<RenderedFrames>> {
     let mut frames: Vec<Vec<u8>> = Vec::new();
-    let mut frame_hashes: Vec<String> = Vec::new();
+    let mut frame_hashes: Vec<blake3::Hash> = Vec::new();
 
-    let first_hash = blake3::hash(&first_frame).to_hex()[..16].to_string();
     frames.push(first_frame);
     frame_hashes.push(first_hash);
 
+    // Copies of the most recent frame at the end of `frames`
+    let mut run = 1;
+    let mut stop_reason = format!("reached the {}-frame limit", MAX_ANIM_FRAMES);
     for step in 1..MAX_ANIM_FRAMES {
+        if !still_current() {
+            debug!("Cursor changed at probe step {}, abandoning render", step);
+            return Ok(None);
+        }
         let rgba = match render_cursor_frame(hicon, width, height, step) {
             Ok(data) => data,
             Err(e) => {
-                debug!("Frame probe ended at step {} ({}): {} frames captured", step, e, frames.len());
+                stop_reason = format!("step {} failed to render ({})", step, e);
                 break;
             }
         };
-        let hash = blake3::hash(&rgba).to_hex()[..16].to_string();
+        let hash = blake3::hash(&rgba);
 
         if hash == frame_hashes[0] {
-            debug!("Animation cycle detected at step {}: {} unique frames", step, frames.len());
-            break;
-        } else if frame_hashes.contains(&hash) {
-            debug!("Duplicate frame at step {} (not first frame): stopping", step);
+            stop_reason = format!("cycled back to the first frame at step {}", step);
             break;
+        }
+        // Short runs are frames the animation holds; a long one is a clamped
+        // step, so keep a single copy and stop.
+        if frame_hashes.last() == Some(&hash) {
+            run += 1;
+            if run >= ANIM_STALL_RUN {
+                let keep = frames.len() + 2 - run;
+                frames.truncate(keep);
+                frame_hashes.truncate(keep);
+                stop_reason = format!("step {} repeated the last frame {} times", step, run);
+                break;
+            }
         } else {
-            frames.push(rgba);
-            frame_hashes.push(hash);
+            run = 1;
         }
+        frames.push(rgba);
+        frame_hashes.push(hash);
     }
+    debug!(
+        "Frame probe stopped: {}; {} frames captured",
+        stop_reason,
+        frames.len()
+    );
 
-    Ok(frames)
+    Ok(Some((frames, frame_hashes)))
 }
 
-/// Render a single cursor frame using DrawIconEx with dual-background technique
-/// for correct per-pixel alpha recovery.
-unsafe fn render_cursor_frame(
-    hicon: HICON,
+/// Memory DC and bitmap reused across frame renders, grown to the largest
+/// cursor seen so far.  Animation probing renders dozens of frames per
+/// capture; creating the GDI objects per frame was measurable overhead.
+struct RenderSurface {
+    hdc: HDC,
+    hbmp: HBITMAP,
+    old_obj: HGDIOBJ,
     width: u32,
     height: u32,
-    step: u32,
-) -> Result<Vec<u8>> {
-    let hdc_screen = GetDC(None);
-    if hdc_screen.is_invalid() {
-        return Err(anyhow!("GetDC failed"));
-    }
+}
 
-    let hdc_mem = CreateCompatibleDC(hdc_screen);
-    if hdc_mem.is_invalid() {
-        ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("CreateCompatibleDC failed"));
-    }
+// Memory DCs and bitmaps are not bound to the creating thread; access is
+// serialised by `RENDER_SURFACE`.
+unsafe impl Send for RenderSurface {}
 
-    let hbmp = CreateCompatibleBitmap(hdc_screen, width as i32, height as i32);
-    if hbmp.is_invalid() {
-        let _ = DeleteDC(hdc_mem);
+impl RenderSurface {
+    unsafe fn new(width: u32, height: u32) -> Result<Self> {
+        let hdc_screen = GetDC(None);
+        if hdc_screen.is_invalid() {
+            return Err(anyhow!("GetDC failed"));
+        }
+
+        let hdc = CreateCompatibleDC(hdc_screen);
+        if hdc.is_invalid() {
+            ReleaseDC(None, hdc_screen);
+            return Err(anyhow!("CreateCompatibleDC failed"));
+        }
+
+        let hbmp = CreateCompatibleBitmap(hdc_screen, width as i32, height as i32);
         ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("CreateCompatibleBitmap failed"));
-    }
+        if hbmp.is_invalid() {
+            let _ = DeleteDC(hdc);
+            return Err(anyhow!("CreateCompatibleBitmap failed"));
+        }
 
-    let old_obj = SelectObject(hdc_mem, hbmp);
+        let old_obj = SelectObject(hdc, hbmp);
+        Ok(RenderSurface {
+            hdc,
+            hbmp,
+            old_obj,
+            width,
+            height,
+        })
+    }
 
-    let mut bmp_info = BITMAPINFO {
-        bmiHeader: BITMAPINFOHEADER {
-            biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
-            biWidth: width as i32,
-            biHeight: -(height as i32), // top-down
-            biPlanes: 1,
-            biBitCount: 32,
-            biCompression: BI_RGB.0 as u32,
+    /// Fill the top-left `width`×`height` area with `rop`, draw `hicon` step
+    /// `step` on it and return that area as top-down BGRA.
+    unsafe fn draw(
+        &self,
+        hicon: HICON,
+        width: u32,
+        height: u32,
+        step: u32,
+        rop: ROP_CODE,
+    ) -> Result<Vec<u8>> {
+        let _ = PatBlt(self.hdc, 0, 0, width as i32, height as i32, rop);
+        DrawIconEx(
+            self.hdc,
+            0,
+            0,
+            hicon,
+            width as i32,
+            height as i32,
+            step,
+            None,
+            DI_NORMAL,
+        )
+        .map_err(|e| anyhow!("DrawIconEx failed: {}", e))?;
+
+        let mut bmp_info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: self.width as i32,
+                biHeight: -(self.height as i32), // top-down
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
             ..Default::default()
-        },
-        ..Default::default()
-    };
+        };
+        let stride = self.width as usize * 4;
+        let mut pixels = vec![0u8; stride * self.height as usize];
+        GetDIBits(
+            self.hdc,
+            self.hbmp,
+            0,
+            self.height,
+            Some(pixels.as_mut_ptr() as *mut _),
+            &mut bmp_info,
+            DIB_RGB_COLORS,
+        );
+        if self.width == width && self.height == height {
+            return Ok(pixels);
+        }
 
-    // Pass 1: Draw cursor on BLACK background
-    let _ = PatBlt(hdc_mem, 0, 0, width as i32, height as i32, BLACKNESS);
-    if DrawIconEx(hdc_mem, 0, 0, hicon, width as i32, height as i32, step, None, DI_NORMAL).is_err() {
-        SelectObject(hdc_mem, old_obj);
-        let _ = DeleteObject(hbmp);
-        let _ = DeleteDC(hdc_mem);
-        ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("DrawIconEx failed (black pass, step={})", step));
+        let row = width as usize * 4;
+        let mut cropped = Vec::with_capacity(row * height as usize);
+        for y in 0..height as usize {
+            cropped.extend_from_slice(&pixels[y * stride..y * stride + row]);
+        }
+        Ok(cropped)
     }
+}
 
-    let mut black_pixels = vec![0u8; (width * height * 4) as usize];
-    GetDIBits(
-        hdc_mem, hbmp, 0, height,
-        Some(black_pixels.as_mut_ptr() as *mut _),
-        &mut bmp_info, DIB_RGB_COLORS,
-    );
-
-    // Pass 2: Draw cursor on WHITE background
-    let _ = PatBlt(hdc_mem, 0, 0, width as i32, height as i32, WHITENESS);
-    if DrawIconEx(hdc_mem, 0, 0, hicon, width as i32, height as i32, step, None, DI_NORMAL).is_err() {
-        SelectObject(hdc_mem, old_obj);
-        let _ = DeleteObject(hbmp);
-        let _ = DeleteDC(hdc_mem);
-        ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("DrawIconEx failed (white pass, step={})", step));
+impl Drop for RenderSurface {
+    fn drop(&mut self) {
+        unsafe {
+            SelectObject(self.hdc, self.old_obj);
+            let _ = DeleteObject(self.hbmp);
+            let _ = DeleteDC(self.hdc);
+        }
     }
+}
 
-    let mut white_pixels = vec![0u8; (width * height * 4) as usize];
-    GetDIBits(
-        hdc_mem, hbmp, 0, height,
-        Some(white_pixels.as_mut_ptr() as *mut _),
-        &mut bmp_info, DIB_RGB_COLORS,
-    );
+/// Shared render surface; dropped on display changes, since the compatible
+/// bitmap format follows the screen's.
+static RENDER_SURFACE: Mutex<Option<RenderSurface>> = Mutex::new(None);
 
-    // Cleanup GDI resources
-    SelectObject(hdc_mem, old_obj);
-    let _ = DeleteObject(hbmp);
-    let _ = DeleteDC(hdc_mem);
-    ReleaseDC(None, hdc_screen);
+/// Draw one cursor frame on black and on white, returning both BGRA renders
+/// for alpha recovery.
+unsafe fn render_on_black_and_white(
+    hicon: HICON,
+    width: u32,
+    height: u32,
+    step: u32,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut surface = RENDER_SURFACE.lock().unwrap();
+    let (cap_w, cap_h) = surface.as_ref().map_or((0, 0), |s| (s.width, s.height));
+    if width > cap_w || height > cap_h {
+        let (new_w, new_h) = (width.max(cap_w), height.max(cap_h));
+        // Free the old objects before allocating the larger ones.
+        *surface = None;
+        *surface = Some(RenderSurface::new(new_w, new_h)?);
+        debug!("Cursor render surface grown to {}x{}", new_w, new_h);
+    }
+    let surface = surface.as_ref().expect("render surface just ensured");
+
+    let black = surface
+        .draw(hicon, width, height, step, BLACKNESS)
+        .map_err(|e| anyhow!("{} (black pass, step={})", e, step))?;
+    let white = surface
+        .draw(hicon, width, height, step, WHITENESS)
+        .map_err(|e| anyhow!("{} (white pass, step={})", e, step))?;
+    Ok((black, white))
+}
+
+/// Render a single cursor frame using DrawIconEx with dual-background technique
+/// for correct per-pixel alpha recovery.
+unsafe fn render_cursor_frame(hicon: HICON, width: u32, height: u32, step: u32) -> Result<Vec<u8>> {
+    let (black_pixels, white_pixels) = render_on_black_and_white(hicon, width, height, step)?;
 
     // Compute RGBA with correct alpha from dual-render results.
     // GetDIBits returns pixels in BGRA order.
@@ -671,26 +1522,9 @@ unsafe fn render_cursor_frame(
             rgba[i * 4 + 2] = (255 - b_black) as u8;
             rgba[i * 4 + 3] = 255;
         } else {
-            let alpha = (255 - (r_white - r_black))
-                .min(255 - (g_white - g_black))
-                .min(255 - (b_white - b_black))
-                .clamp(0, 255) as u8;
-
-            let (r, g, b) = if alpha > 0 {
-                let a = alpha as f32;
-                (
-                    (r_black as f32 * 255.0 / a).round().clamp(0.0, 255.0) as u8,
-                    (g_black as f32 * 255.0 / a).round().clamp(0.0, 255.0) as u8,
-                    (b_black as f32 * 255.0 / a).round().clamp(0.0, 255.0) as u8,
-                )
-            } else {
-                (0, 0, 0)
-            };
-
-            rgba[i * 4] = r;
-            rgba[i * 4 + 1] = g;
-            rgba[i * 4 + 2] = b;
-            rgba[i * 4 + 3] = alpha;
+            let on_black = [r_black as u8, g_black as u8, b_black as u8];
+            let on_white = [r_white as u8, g_white as u8, b_white as u8];
+            rgba[i * 4..i * 4 + 4].copy_from_slice(&recover_dual_render_pixel(on_black, on_white));
         }
     }
 
@@ -704,78 +1538,7 @@ unsafe fn render_cursor_frame_with_xor_detection(
     height: u32,
     step: u32,
 ) -> Result<(Vec<u8>, bool, XorShape)> {
-    let hdc_screen = GetDC(None);
-    if hdc_screen.is_invalid() {
-        return Err(anyhow!("GetDC failed"));
-    }
-
-    let hdc_mem = CreateCompatibleDC(hdc_screen);
-    if hdc_mem.is_invalid() {
-        ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("CreateCompatibleDC failed"));
-    }
-
-    let hbmp = CreateCompatibleBitmap(hdc_screen, width as i32, height as i32);
-    if hbmp.is_invalid() {
-        let _ = DeleteDC(hdc_mem);
-        ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("CreateCompatibleBitmap failed"));
-    }
-
-    let old_obj = SelectObject(hdc_mem, hbmp);
-
-    let mut bmp_info = BITMAPINFO {
-        bmiHeader: BITMAPINFOHEADER {
-            biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
-            biWidth: width as i32,
-            biHeight: -(height as i32),
-            biPlanes: 1,
-            biBitCount: 32,
-            biCompression: BI_RGB.0 as u32,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    // Pass 1: Draw cursor on BLACK background
-    let _ = PatBlt(hdc_mem, 0, 0, width as i32, height as i32, BLACKNESS);
-    if DrawIconEx(hdc_mem, 0, 0, hicon, width as i32, height as i32, step, None, DI_NORMAL).is_err() {
-        SelectObject(hdc_mem, old_obj);
-        let _ = DeleteObject(hbmp);
-        let _ = DeleteDC(hdc_mem);
-        ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("DrawIconEx failed (black pass, step={})", step));
-    }
-
-    let mut black_pixels = vec![0u8; (width * height * 4) as usize];
-    GetDIBits(
-        hdc_mem, hbmp, 0, height,
-        Some(black_pixels.as_mut_ptr() as *mut _),
-        &mut bmp_info, DIB_RGB_COLORS,
-    );
-
-    // Pass 2: Draw cursor on WHITE background
-    let _ = PatBlt(hdc_mem, 0, 0, width as i32, height as i32, WHITENESS);
-    if DrawIconEx(hdc_mem, 0, 0, hicon, width as i32, height as i32, step, None, DI_NORMAL).is_err() {
-        SelectObject(hdc_mem, old_obj);
-        let _ = DeleteObject(hbmp);
-        let _ = DeleteDC(hdc_mem);
-        ReleaseDC(None, hdc_screen);
-        return Err(anyhow!("DrawIconEx failed (white pass, step={})", step));
-    }
-
-    let mut white_pixels = vec![0u8; (width * height * 4) as usize];
-    GetDIBits(
-        hdc_mem, hbmp, 0, height,
-        Some(white_pixels.as_mut_ptr() as *mut _),
-        &mut bmp_info, DIB_RGB_COLORS,
-    );
-
-    // Cleanup GDI resources
-    SelectObject(hdc_mem, old_obj);
-    let _ = DeleteObject(hbmp);
-    let _ = DeleteDC(hdc_mem);
-    ReleaseDC(None, hdc_screen);
+    let (black_pixels, white_pixels) = render_on_black_and_white(hicon, width, height, step)?;
 
     // Compute RGBA with XOR detection and shape tracking
     let pixel_count = (width * height) as usize;
@@ -805,26 +1568,9 @@ unsafe fn render_cursor_frame_with_xor_detection(
             rgba[i * 4 + 2] = (255 - b_black) as u8;
             rgba[i * 4 + 3] = 255;
         } else {
-            let alpha = (255 - (r_white - r_black))
-                .min(255 - (g_white - g_black))
-                .min(255 - (b_white - b_black))
-                .clamp(0, 255) as u8;
-
-            let (r, g, b) = if alpha > 0 {
-                let a = alpha as f32;
-                (
-                    (r_black as f32 * 255.0 / a).round().clamp(0.0, 255.0) as u8,
-                    (g_black as f32 * 255.0 / a).round().clamp(0.0, 255.0) as u8,
-                    (b_black as f32 * 255.0 / a).round().clamp(0.0, 255.0) as u8,
-                )
-            } else {
-                (0, 0, 0)
-            };
-
-            rgba[i * 4] = r;
-            rgba[i * 4 + 1] = g;
-            rgba[i * 4 + 2] = b;
-            rgba[i * 4 + 3] = alpha;
+            let on_black = [r_black as u8, g_black as u8, b_black as u8];
+            let on_white = [r_white as u8, g_white as u8, b_white as u8];
+            rgba[i * 4..i * 4 + 4].copy_from_slice(&recover_dual_render_pixel(on_black, on_white));
         }
     }
 
@@ -938,3 +1684,33 @@ unsafe fn get_monochrome_cursor_rgba(
 
     Ok((rgba, width, height, has_xor, xor_shape))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::Win32::UI::WindowsAndMessaging::SetCursorPos;
+
+    #[test]
+    fn pointer_movement_does_not_recapture() {
+        init_cache();
+        // Without an interactive desktop there is no cursor to move.
+        let Some((x, y)) = cursor_position() else {
+            return;
+        };
+        let _ = capture_cursor();
+        let captures = FULL_CAPTURES.load(Ordering::Relaxed);
+
+        // Small moves keep the pointer over the same window, so the cursor
+        // shape (and handle) stays the same.
+        for i in 1..=20 {
+            unsafe {
+                let _ = SetCursorPos(x + i % 4, y + i % 3);
+            }
+            let _ = capture_cursor();
+        }
+        unsafe {
+            let _ = SetCursorPos(x, y);
+        }
+        assert_eq!(FULL_CAPTURES.load(Ordering::Relaxed), captures);
+    }
+}
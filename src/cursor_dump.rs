@@ -0,0 +1,86 @@
+//! Cursor dump mode (`--dump-cursors DIR`): runs cursor capture without the
+//! WebRTC server and writes every newly captured cursor to disk, for checking
+//! capture quality (XOR outlines, alpha, scaling) without a browser.
+//!
+//! Each cursor produces `<id>.webp` (exactly what clients receive),
+//! `<id>.png` (first frame, for viewers without WebP support) and
+//! `<id>.json` (size, hotspot, animation details).
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::clipboard_sync::encode_rgba_to_png;
+use crate::cursor_capture::{decode_cursor_rgba, CachedCursor, CursorCapturer, CursorEvent};
+use crate::shutdown;
+
+/// Capture cursors into `dir` until shutdown is requested.
+pub async fn run_cursor_dump(dir: PathBuf) -> Result<()> {
+    std::fs::create_dir_all(&dir).with_context(|| format!("create {:?}", dir))?;
+    info!("Dumping captured cursors to {:?} (Ctrl+C to stop)", dir);
+
+    let mut capturer = CursorCapturer::start();
+    let mut dumped = HashSet::new();
+    loop {
+        let event = tokio::select! {
+            _ = shutdown::requested() => break,
+            event = capturer.next_event() => event,
+        };
+        let Some(event) = event else { break };
+        let CursorEvent::CursorChanged { cursor_id: id, .. } = event else {
+            continue;
+        };
+        if dumped.contains(&id) {
+            continue;
+        }
+        let Some(cursor) = capturer.get_cursor(&id) else {
+            warn!("Cursor {} left the cache before it could be dumped", id);
+            continue;
+        };
+        match dump_cursor(&dir, &cursor) {
+            Ok(()) => {
+                info!(
+                    "Dumped {} ({}x{}, hotspot {},{}{})",
+                    id,
+                    cursor.width,
+                    cursor.height,
+                    cursor.hotspot_x,
+                    cursor.hotspot_y,
+                    if cursor.is_animated { ", animated" } else { "" }
+                );
+                dumped.insert(id);
+            }
+            Err(e) => warn!("Failed to dump cursor {}: {:#}", id, e),
+        }
+    }
+
+    info!("Dumped {} cursors to {:?}", dumped.len(), dir);
+    capturer.stop().await
+}
+
+fn dump_cursor(dir: &Path, cursor: &CachedCursor) -> Result<()> {
+    let path = |ext: &str| dir.join(format!("{}.{}", cursor.id, ext));
+
+    std::fs::write(path("webp"), &cursor.webp_data).context("write WebP")?;
+
+    let rgba = decode_cursor_rgba(cursor)?;
+    let png = encode_rgba_to_png(&rgba, cursor.width, cursor.height)?;
+    std::fs::write(path("png"), png).context("write PNG")?;
+
+    let metadata = serde_json::json!({
+        "id": cursor.id,
+        "width": cursor.width,
+        "height": cursor.height,
+        "hotspot_x": cursor.hotspot_x,
+        "hotspot_y": cursor.hotspot_y,
+        "is_animated": cursor.is_animated,
+        "frame_count": cursor.frame_count,
+        "frame_delay_ms": cursor.frame_delay_ms,
+        "name": cursor.name,
+        "webp_bytes": cursor.webp_data.len(),
+    });
+    std::fs::write(path("json"), serde_json::to_vec_pretty(&metadata)?)
+        .context("write metadata")?;
+    Ok(())
+}
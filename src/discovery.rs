@@ -0,0 +1,125 @@
+//! LAN discovery: advertises the signaling server over mDNS as
+//! `_deragabu._tcp.local.` when `ADVERTISE=1`.
+//!
+//! The instance name comes from `ADVERTISE_NAME`, defaulting to the host
+//! name.  TXT records carry the agent `version` and the signaling `scheme`
+//! (`http`, or `https` when `TLS_CERT` is set).
+
+use anyhow::{Context, Result};
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::net::SocketAddr;
+use tracing::{debug, info, warn};
+
+use crate::shutdown;
+
+/// DNS-SD service type clients browse for.
+const SERVICE_TYPE: &str = "_deragabu._tcp.local.";
+
+/// Whether `ADVERTISE` asks for mDNS advertisement.
+pub fn advertise_enabled() -> bool {
+    crate::env_flag("ADVERTISE", false)
+}
+
+/// Advertise the signaling server at `bind_addr` until shutdown, then
+/// unregister it.
+pub async fn run_advertiser(bind_addr: String) -> Result<()> {
+    let addr: SocketAddr = bind_addr
+        .parse()
+        .with_context(|| format!("parse {:?}", bind_addr))?;
+    if addr.ip().is_loopback() {
+        warn!(
+            "ADVERTISE set but the server only listens on {}, not advertising",
+            addr
+        );
+        return Ok(());
+    }
+
+    let name = instance_name();
+    let host_name = format!("{}.local.", dns_label(&name));
+    let scheme = if std::env::var_os("TLS_CERT").is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    let properties = [("version", env!("CARGO_PKG_VERSION")), ("scheme", scheme)];
+
+    // A wildcard bind is reachable on every interface; let the daemon
+    // publish and track all of them.
+    let info = if addr.ip().is_unspecified() {
+        ServiceInfo::new(
+            SERVICE_TYPE,
+            &name,
+            &host_name,
+            (),
+            addr.port(),
+            &properties[..],
+        )?
+        .enable_addr_auto()
+    } else {
+        ServiceInfo::new(
+            SERVICE_TYPE,
+            &name,
+            &host_name,
+            addr.ip(),
+            addr.port(),
+            &properties[..],
+        )?
+    };
+    let fullname = info.get_fullname().to_string();
+
+    let daemon = ServiceDaemon::new().context("start mDNS daemon")?;
+    daemon.register(info).context("register mDNS service")?;
+    info!("Advertising {} on port {} via mDNS", fullname, addr.port());
+
+    shutdown::requested().await;
+
+    match daemon.unregister(&fullname) {
+        Ok(status) => {
+            // Wait (briefly) for the goodbye packets so browsers drop us now
+            // rather than when the record expires.
+            let _ = tokio::task::spawn_blocking(move || {
+                status.recv_timeout(std::time::Duration::from_secs(1))
+            })
+            .await;
+            debug!("Unregistered {}", fullname);
+        }
+        Err(e) => warn!("Cannot unregister mDNS service {}: {}", fullname, e),
+    }
+    let _ = daemon.shutdown();
+    Ok(())
+}
+
+/// `ADVERTISE_NAME`, else the host name, else a fixed fallback.
+fn instance_name() -> String {
+    let from_env = |var: &str| {
+        std::env::var(var)
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    from_env("ADVERTISE_NAME")
+        .or_else(|| from_env("COMPUTERNAME"))
+        .or_else(|| from_env("HOSTNAME"))
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        })
+        .unwrap_or_else(|| "deragabu-agent".to_string())
+}
+
+/// Reduce `name` to a valid DNS label for the advertised host name.
+fn dns_label(name: &str) -> String {
+    let label: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .take(63)
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() {
+        "deragabu-agent".to_string()
+    } else {
+        label.to_string()
+    }
+}
@@ -1,16 +1,23 @@
 //! C FFI interface for embedding deragabu-agent into Sunshine (or any C/C++ host).
 //!
-//! Exposes four `extern "C"` functions:
+//! Exposes these `extern "C"` functions:
 //! - `deragabu_agent_init`  — start the agent (tokio runtime + all subsystems)
 //! - `deragabu_agent_shutdown` — stop the agent and tear down the runtime
 //! - `deragabu_agent_set_display_cursor` — push Sunshine's `display_cursor` state
 //! - `deragabu_agent_is_running` — health check
+//! - `deragabu_agent_start_capture` / `deragabu_agent_stop_capture` — run
+//!   cursor capture alone, reporting changes to a C callback
+//! - `deragabu_agent_get_cursor` — copy a cached cursor's WebP bytes
+//!
+//! The declarations live in `include/deragabu_agent.h`.
 
-use std::ffi::CStr;
+use std::ffi::{c_void, CStr, CString};
 use std::os::raw::c_char;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
+
+use crate::cursor_capture::{get_cached_cursor, CachedCursor, CursorCapturer, CursorEvent};
 
 /// Whether the agent is currently running.
 static RUNNING: AtomicBool = AtomicBool::new(false);
@@ -29,11 +36,21 @@ static RUNTIME: Mutex<Option<tokio::runtime::Runtime>> = Mutex::new(None);
 /// # Safety
 /// `bind_addr` must be a valid null-terminated C string or `NULL`.
 #[no_mangle]
-pub extern "C" fn deragabu_agent_init(bind_addr: *const c_char) -> i32 {
+pub unsafe extern "C" fn deragabu_agent_init(bind_addr: *const c_char) -> i32 {
+    // Held until RUNNING is set, so this and deragabu_agent_start_capture
+    // see each other's state under one lock.
+    let Ok(capture) = CAPTURE_RUNTIME.lock() else {
+        return -1;
+    };
     // Prevent double-init
     if RUNNING.load(Ordering::SeqCst) {
         return 0;
     }
+    // Standalone capture shares the cursor cache with the agent
+    if capture.is_some() {
+        eprintln!("deragabu_agent_init: stop the standalone cursor capture first");
+        return -1;
+    }
 
     let addr = if bind_addr.is_null() {
         "0.0.0.0:9000".to_string()
@@ -60,6 +77,7 @@ pub extern "C" fn deragabu_agent_init(bind_addr: *const c_char) -> i32 {
     };
 
     // Spawn the main subsystem future on the runtime
+    crate::shutdown::reset();
     rt.spawn(crate::start_all_subsystems(addr));
 
     // Store the runtime so it stays alive (and can be dropped on shutdown)
@@ -68,6 +86,7 @@ pub extern "C" fn deragabu_agent_init(bind_addr: *const c_char) -> i32 {
     }
 
     RUNNING.store(true, Ordering::SeqCst);
+    drop(capture);
     info!("deragabu_agent_init: agent started");
     0
 }
@@ -83,6 +102,9 @@ pub extern "C" fn deragabu_agent_shutdown() {
 
     RUNNING.store(false, Ordering::SeqCst);
 
+    // Let the subsystems close peer connections before the runtime goes away.
+    crate::shutdown::trigger();
+
     // Take the runtime out and drop it — this cancels all spawned tasks and
     // waits for blocking threads to finish.
     if let Ok(mut guard) = RUNTIME.lock() {
@@ -112,3 +134,215 @@ pub extern "C" fn deragabu_agent_set_display_cursor(display: bool) {
 pub extern "C" fn deragabu_agent_is_running() -> bool {
     RUNNING.load(Ordering::SeqCst)
 }
+
+// ─── Standalone cursor capture ──────────────────────────────────────────────
+
+/// Runtime driving the capture started by [`deragabu_agent_start_capture`].
+static CAPTURE_RUNTIME: Mutex<Option<tokio::runtime::Runtime>> = Mutex::new(None);
+
+/// A captured cursor as handed to a [`CursorCallback`].  Every pointer is
+/// borrowed for the duration of the callback only.
+#[repr(C)]
+pub struct CursorInfo {
+    /// NUL-terminated cursor id, usable with [`deragabu_agent_get_cursor`]
+    pub cursor_id: *const c_char,
+    /// WebP image (static or animated)
+    pub webp_data: *const u8,
+    pub webp_len: usize,
+    pub width: u32,
+    pub height: u32,
+    pub hotspot_x: i32,
+    pub hotspot_y: i32,
+    pub is_animated: bool,
+    pub frame_count: u32,
+    pub frame_delay_ms: u32,
+}
+
+/// Called on every cursor change with the new cursor, or with `NULL` when
+/// the cursor is hidden.
+pub type CursorCallback = extern "C" fn(cursor: *const CursorInfo, userdata: *mut c_void);
+
+/// The host's `userdata`, handed back untouched on the capture thread.
+struct UserData(*mut c_void);
+
+// SAFETY: the pointer is never dereferenced here; the header documents that
+// the callback receives it on a capture thread.
+unsafe impl Send for UserData {}
+
+/// Start cursor capture without the WebRTC server, calling `callback` on
+/// each cursor change.  Cursor moves are not reported.
+///
+/// The callback runs on a capture thread and should return quickly; capture
+/// waits for it.
+///
+/// Returns `0` on success, `-1` if capture is already running, the full
+/// agent is running (they share the cursor cache), or the runtime could not
+/// be created.
+#[no_mangle]
+pub extern "C" fn deragabu_agent_start_capture(
+    callback: CursorCallback,
+    userdata: *mut c_void,
+) -> i32 {
+    // deragabu_agent_init sets RUNNING while holding this lock.
+    let Ok(mut guard) = CAPTURE_RUNTIME.lock() else {
+        return -1;
+    };
+    if RUNNING.load(Ordering::SeqCst) {
+        warn!("deragabu_agent_start_capture: the agent is running, capture is already part of it");
+        return -1;
+    }
+    if guard.is_some() {
+        return -1;
+    }
+
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let rt = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("deragabu_agent_start_capture: failed to create tokio runtime: {e}");
+            return -1;
+        }
+    };
+
+    let userdata = UserData(userdata);
+    crate::shutdown::reset();
+    rt.spawn(async move {
+        let userdata = userdata;
+        let mut capturer = CursorCapturer::start();
+        while let Some(event) = capturer.next_event().await {
+            match event {
+                CursorEvent::CursorChanged { cursor_id, .. } => {
+                    if let Some(cursor) = capturer.get_cursor(&cursor_id) {
+                        report_cursor(callback, &cursor, userdata.0);
+                    }
+                }
+                CursorEvent::CursorHidden { .. } => callback(std::ptr::null(), userdata.0),
+                CursorEvent::Moved { .. } => {}
+            }
+        }
+        if let Err(e) = capturer.stop().await {
+            warn!(
+                "deragabu_agent_start_capture: cursor capture stopped: {:#}",
+                e
+            );
+        }
+    });
+
+    *guard = Some(rt);
+    info!("deragabu_agent_start_capture: cursor capture started");
+    0
+}
+
+/// Stop the capture started by [`deragabu_agent_start_capture`], waiting up
+/// to five seconds for a callback in progress to return.  A no-op when
+/// capture is not running.
+///
+/// Must not be called from the callback: shutting the runtime down from one
+/// of its own threads would wait on itself (tokio panics), so such a call is
+/// logged and ignored.
+#[no_mangle]
+pub extern "C" fn deragabu_agent_stop_capture() {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        warn!("deragabu_agent_stop_capture: called from an agent thread (the cursor callback?), ignoring");
+        return;
+    }
+    let rt = CAPTURE_RUNTIME
+        .lock()
+        .ok()
+        .and_then(|mut guard| guard.take());
+    if let Some(rt) = rt {
+        crate::shutdown::trigger();
+        rt.shutdown_timeout(std::time::Duration::from_secs(5));
+        info!("deragabu_agent_stop_capture: cursor capture stopped");
+    }
+}
+
+/// Copy the WebP bytes of cached cursor `cursor_id` into `out_buf`.
+///
+/// Returns the image size in bytes.  Nothing is copied when `out_buf` is
+/// `NULL` or `out_len` is smaller than that size, so a caller can query the
+/// size first.  Returns `-1` when `cursor_id` is `NULL`, not UTF-8 or not
+/// in the cache.
+///
+/// # Safety
+/// `cursor_id` must be a valid NUL-terminated C string or `NULL`, and
+/// `out_buf` must be writable for `out_len` bytes or `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn deragabu_agent_get_cursor(
+    cursor_id: *const c_char,
+    out_buf: *mut u8,
+    out_len: usize,
+) -> i64 {
+    if cursor_id.is_null() {
+        return -1;
+    }
+    let Ok(id) = unsafe { CStr::from_ptr(cursor_id) }.to_str() else {
+        return -1;
+    };
+    let Some(cursor) = get_cached_cursor(id) else {
+        return -1;
+    };
+    let data = &cursor.webp_data;
+    if !out_buf.is_null() && out_len >= data.len() {
+        // SAFETY: the caller guarantees `out_len` writable bytes at `out_buf`.
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), out_buf, data.len()) };
+    }
+    data.len() as i64
+}
+
+/// Hand `cursor` to the host callback.
+fn report_cursor(callback: CursorCallback, cursor: &CachedCursor, userdata: *mut c_void) {
+    // Cursor ids never contain NUL.
+    let Ok(id) = CString::new(cursor.id.as_str()) else {
+        return;
+    };
+    let info = CursorInfo {
+        cursor_id: id.as_ptr(),
+        webp_data: cursor.webp_data.as_ptr(),
+        webp_len: cursor.webp_data.len(),
+        width: cursor.width,
+        height: cursor.height,
+        hotspot_x: cursor.hotspot_x,
+        hotspot_y: cursor.hotspot_y,
+        is_animated: cursor.is_animated,
+        frame_count: cursor.frame_count,
+        frame_delay_ms: cursor.frame_delay_ms,
+    };
+    callback(&info, userdata);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn ignore_cursor(_cursor: *const CursorInfo, _userdata: *mut c_void) {}
+
+    #[test]
+    fn stop_capture_from_an_agent_thread_is_ignored() {
+        assert_eq!(
+            deragabu_agent_start_capture(ignore_cursor, std::ptr::null_mut()),
+            0
+        );
+        assert_eq!(
+            deragabu_agent_start_capture(ignore_cursor, std::ptr::null_mut()),
+            -1
+        );
+
+        // Where the callback runs: inside a tokio runtime
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async { deragabu_agent_stop_capture() });
+        assert!(
+            CAPTURE_RUNTIME.lock().unwrap().is_some(),
+            "stopped from a runtime thread"
+        );
+
+        deragabu_agent_stop_capture();
+        assert!(CAPTURE_RUNTIME.lock().unwrap().is_none());
+    }
+}
@@ -1,12 +1,38 @@
+//! Deragabu Agent: captures the host cursor and clipboard and streams them
+//! to WebRTC clients.
+//!
+//! Entry points:
+//!
+//! - [`start_all_subsystems`] runs the full agent (capture, clipboard sync,
+//!   Sunshine monitor and the WebRTC server); the `deragabu-agent` binary is
+//!   a thin wrapper around it.
+//! - [`cursor_capture::CursorCapturer`] runs cursor capture alone, for
+//!   embedding in another program with its own transport.
+//! - [`cursor_capture`] also exposes the WebP encoders and
+//!   [`cursor_capture::build_external_cursor`] for working with cursor
+//!   images directly.
+
+pub mod client_config;
 pub mod clipboard_sync;
 pub mod cursor_capture;
-#[cfg(target_os = "macos")]
+pub mod cursor_dump;
+pub mod discovery;
 pub mod ffi;
+pub mod metrics;
+pub mod shutdown;
 pub mod sunshine_monitor;
 pub mod webrtc_server;
 
+#[cfg(test)]
+mod test_support;
+
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::{timeout, Duration};
+use tracing::{error, info, warn};
+
+/// How long subsystems get to wind down after shutdown is requested.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
 
 // Include generated Protobuf code
 pub mod cursor {
@@ -21,12 +47,62 @@ pub enum AgentEvent {
     Settings(sunshine_monitor::SunshineSettingsEvent),
 }
 
+/// Read an on/off switch from the environment: `1`, `true`, `yes` or `on`
+/// turn it on, `0`, `false`, `no` or `off` turn it off (case-insensitive).
+/// Unset or empty gives `default`, as does anything else, with a warning.
+pub(crate) fn env_flag(var: &str, default: bool) -> bool {
+    let Ok(raw) = std::env::var(var) else {
+        return default;
+    };
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => true,
+        "0" | "false" | "no" | "off" => false,
+        "" => default,
+        _ => {
+            warn!(
+                "Invalid {}={:?}, using {}",
+                var,
+                raw,
+                if default { "on" } else { "off" }
+            );
+            default
+        }
+    }
+}
+
+/// Whether the subsystem switch `var` (`ENABLE_CURSOR`, `ENABLE_CLIPBOARD`,
+/// `ENABLE_SUNSHINE`) is on; see [`env_flag`].  Unset means on.
+pub(crate) fn subsystem_enabled(var: &str) -> bool {
+    env_flag(var, true)
+}
+
 /// Start all agent subsystems (cursor capture, clipboard sync, sunshine monitor,
 /// WebRTC server).  This is the shared entry point used by both `main.rs` (standalone
 /// binary) and `ffi.rs` (when linked as a static library into Sunshine).
+///
+/// `ENABLE_CURSOR`, `ENABLE_CLIPBOARD` and `ENABLE_SUNSHINE` (see
+/// `subsystem_enabled`) skip cursor capture, clipboard sync (host capture
+/// and client writes alike) and the Sunshine monitor; the WebRTC server
+/// always runs.
+///
+/// Returns when any subsystem exits or once [`shutdown::trigger`] has been
+/// called and the subsystems have wound down.
 pub async fn start_all_subsystems(bind_addr: String) {
     info!("Deragabu Agent starting...");
 
+    let cursor_enabled = subsystem_enabled("ENABLE_CURSOR");
+    let clipboard_enabled = subsystem_enabled("ENABLE_CLIPBOARD");
+    let sunshine_enabled = subsystem_enabled("ENABLE_SUNSHINE");
+    for (name, enabled) in [
+        ("Cursor capture", cursor_enabled),
+        ("Clipboard sync", clipboard_enabled),
+        ("Sunshine monitor", sunshine_enabled),
+    ] {
+        if !enabled {
+            info!("{} disabled", name);
+        }
+    }
+
     // Channels feeding into the unified broadcast
     let (cursor_tx, mut cursor_rx) = mpsc::channel::<cursor_capture::CursorEvent>(32);
     let (clipboard_tx, mut clipboard_rx) = mpsc::channel::<clipboard_sync::ClipboardEvent>(32);
@@ -62,6 +138,11 @@ pub async fn start_all_subsystems(bind_addr: String) {
     let agent_tx_settings = agent_tx.clone();
     tokio::spawn(async move {
         while let Some(ev) = settings_rx.recv().await {
+            if ev.color_scheme != sunshine_monitor::ColorScheme::Unknown {
+                cursor_capture::set_light_desktop(
+                    ev.color_scheme == sunshine_monitor::ColorScheme::Light,
+                );
+            }
             if agent_tx_settings
                 .send(AgentEvent::Settings(ev))
                 .await
@@ -72,51 +153,139 @@ pub async fn start_all_subsystems(bind_addr: String) {
         }
     });
 
+    // Advertise the signaling server on the LAN (ADVERTISE=1).  Not fatal:
+    // the agent works without it.
+    let advertise_handle = discovery::advertise_enabled().then(|| {
+        let bind_addr = bind_addr.clone();
+        tokio::spawn(async move {
+            if let Err(e) = discovery::run_advertiser(bind_addr).await {
+                warn!("mDNS advertisement failed: {}", e);
+            }
+        })
+    });
+
     // Start WebRTC signaling + data channel server
-    let rtc_handle = tokio::spawn(webrtc_server::run_webrtc_server(bind_addr, agent_rx));
+    let mut rtc_handle = tokio::spawn(webrtc_server::run_webrtc_server(bind_addr, agent_rx));
 
-    // Start cursor capture task
-    let capture_handle = tokio::spawn(async move {
-        if let Err(e) = cursor_capture::run_cursor_capture(cursor_tx).await {
-            error!("Cursor capture error: {}", e);
-        }
+    // Start cursor capture task.  A disabled subsystem drops its sender, so
+    // its forwarder ends and the server simply never sees its events.
+    let mut capture_handle = cursor_enabled.then(|| {
+        tokio::spawn(async move {
+            if let Err(e) = cursor_capture::run_cursor_capture(cursor_tx).await {
+                error!("Cursor capture error: {}", e);
+            }
+        })
     });
 
     // Start clipboard capture task
-    let clipboard_handle = tokio::spawn(async move {
-        if let Err(e) = clipboard_sync::run_clipboard_capture(clipboard_tx).await {
-            error!("Clipboard capture error: {}", e);
-        }
+    let mut clipboard_handle = clipboard_enabled.then(|| {
+        tokio::spawn(async move {
+            if let Err(e) = clipboard_sync::run_clipboard_capture(clipboard_tx).await {
+                error!("Clipboard capture error: {}", e);
+            }
+        })
     });
 
     // Start Sunshine monitor (detects draw_cursor state from running Sunshine process)
-    let sunshine_handle = tokio::spawn(async move {
-        if let Err(e) = sunshine_monitor::run_sunshine_monitor(settings_tx).await {
-            error!("Sunshine monitor error: {}", e);
-        }
+    let mut sunshine_handle = sunshine_enabled.then(|| {
+        tokio::spawn(async move {
+            if let Err(e) = sunshine_monitor::run_sunshine_monitor(settings_tx).await {
+                error!("Sunshine monitor error: {}", e);
+            }
+        })
     });
 
-    // Wait for any task to complete (any exit is treated as fatal)
+    // Wait for shutdown, or for any task to complete (any exit is treated as fatal)
     tokio::select! {
-        result = rtc_handle => {
-            if let Err(e) = result {
-                error!("WebRTC server task error: {}", e);
+        _ = shutdown::requested() => {
+            info!("Shutting down…");
+            // The sunshine monitor holds no client-visible state; just stop it.
+            if let Some(handle) = &sunshine_handle {
+                handle.abort();
+            }
+            let wind_down = async {
+                let _ = (&mut rtc_handle).await;
+                for handle in [&mut capture_handle, &mut clipboard_handle].into_iter().flatten() {
+                    let _ = handle.await;
+                }
+                // Unregisters the mDNS service on shutdown.
+                if let Some(handle) = advertise_handle {
+                    let _ = handle.await;
+                }
+            };
+            if timeout(SHUTDOWN_GRACE, wind_down).await.is_err() {
+                warn!("Subsystems did not stop within {:?}, abandoning them", SHUTDOWN_GRACE);
+                rtc_handle.abort();
+                for handle in [&capture_handle, &clipboard_handle].into_iter().flatten() {
+                    handle.abort();
+                }
             }
+            info!("Deragabu Agent stopped");
         }
-        result = capture_handle => {
+        result = &mut rtc_handle => match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("WebRTC server error: {:#}", e),
+            Err(e) => error!("WebRTC server task error: {}", e),
+        },
+        result = join_started(&mut capture_handle) => {
             if let Err(e) = result {
                 error!("Cursor capture task error: {}", e);
             }
         }
-        result = clipboard_handle => {
+        result = join_started(&mut clipboard_handle) => {
             if let Err(e) = result {
                 error!("Clipboard capture task error: {}", e);
             }
         }
-        result = sunshine_handle => {
+        result = join_started(&mut sunshine_handle) => {
             if let Err(e) = result {
                 error!("Sunshine monitor task error: {}", e);
             }
         }
     }
 }
+
+/// Wait for a subsystem task; one that was never started never finishes.
+async fn join_started(handle: &mut Option<JoinHandle<()>>) -> Result<(), JoinError> {
+    match handle {
+        Some(handle) => handle.await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_flag_parses_switches() {
+        // A variable of its own, so parallel tests can't see it change
+        const VAR: &str = "DERAGABU_TEST_ENV_FLAG";
+        std::env::remove_var(VAR);
+        assert!(env_flag(VAR, true));
+        assert!(!env_flag(VAR, false));
+
+        for (raw, expected) in [
+            ("1", true),
+            ("Yes", true),
+            (" on ", true),
+            ("TRUE", true),
+            ("0", false),
+            ("off", false),
+            ("No", false),
+            ("false", false),
+        ] {
+            std::env::set_var(VAR, raw);
+            assert_eq!(env_flag(VAR, !expected), expected, "{:?}", raw);
+        }
+        for raw in ["", "maybe", "2"] {
+            std::env::set_var(VAR, raw);
+            assert!(
+                env_flag(VAR, true) && !env_flag(VAR, false),
+                "{:?} should fall back",
+                raw
+            );
+        }
+        std::env::remove_var(VAR);
+    }
+}
@@ -1,16 +1,91 @@
 use anyhow::Result;
-use deragabu_agent::start_all_subsystems;
+use clap::{Parser, ValueEnum};
+use deragabu_agent::{cursor_dump, shutdown, start_all_subsystems};
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+/// Deragabu Agent: streams the host cursor and clipboard to WebRTC clients.
+///
+/// Every flag falls back to its environment variable, so existing service
+/// configurations keep working.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Address of the signaling server
+    #[arg(long, env = "BIND_ADDR", default_value = "127.0.0.1:9000")]
+    bind: String,
+
+    /// Log filter: a level (`debug`) or per-target directives
+    /// (`info,webrtc=warn`)
+    #[arg(long, env = "RUST_LOG", default_value = "info")]
+    log_level: String,
 
-    // Server bind address
-    let bind_addr = std::env::var("BIND_ADDR").unwrap_or_else(|_| "127.0.0.1:9000".to_string());
+    /// Log output format
+    #[arg(long, env = "LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
 
-    start_all_subsystems(bind_addr).await;
+    /// STUN/TURN servers, comma-separated (`stun:host:port`,
+    /// `turn:user:pass@host:port`)
+    #[arg(long, env = "ICE_SERVERS")]
+    ice_servers: Option<String>,
 
-    Ok(())
+    /// Instead of serving clients, write each newly captured cursor to DIR
+    /// as WebP, PNG and JSON metadata, until Ctrl+C
+    #[arg(long, env = "DUMP_CURSORS", value_name = "DIR")]
+    dump_cursors: Option<PathBuf>,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line, with target and span fields, for log pipelines
+    Json,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Initialize logging
+    let filter = Targets::from_str(&cli.log_level).unwrap_or_else(|e| {
+        eprintln!("Ignoring --log-level {:?}: {}", cli.log_level, e);
+        Targets::new().with_default(tracing::Level::INFO)
+    });
+    let json = cli.log_format == LogFormat::Json;
+    tracing_subscriber::registry()
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        .with(json.then(|| {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+        }))
+        .with(filter)
+        .init();
+
+    // The WebRTC server reads ICE_SERVERS itself.  Set it before the runtime
+    // starts any other thread.
+    if let Some(ice_servers) = &cli.ice_servers {
+        std::env::set_var("ICE_SERVERS", ice_servers);
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            tokio::spawn(async {
+                shutdown::wait_for_signal().await;
+                shutdown::trigger();
+            });
+
+            match cli.dump_cursors {
+                Some(dir) => cursor_dump::run_cursor_dump(dir).await,
+                None => {
+                    start_all_subsystems(cli.bind).await;
+                    Ok(())
+                }
+            }
+        })
+}
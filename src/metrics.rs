@@ -0,0 +1,35 @@
+//! Process-wide counters shared between the WebRTC server and the capture
+//! loops.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Notify;
+
+/// Number of data channels with an active sender task.
+static CONNECTED_CLIENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Signalled when the client count goes from zero to one, so idle capture
+/// loops can resume full-rate polling immediately.
+static CLIENT_ARRIVED: Notify = Notify::const_new();
+
+/// Record a newly connected client.
+pub fn client_connected() {
+    if CONNECTED_CLIENTS.fetch_add(1, Ordering::SeqCst) == 0 {
+        CLIENT_ARRIVED.notify_one();
+    }
+}
+
+/// Record a disconnected client.
+pub fn client_disconnected() {
+    CONNECTED_CLIENTS.fetch_sub(1, Ordering::SeqCst);
+}
+
+/// Current number of connected clients.
+pub fn connected_clients() -> usize {
+    CONNECTED_CLIENTS.load(Ordering::SeqCst)
+}
+
+/// Wait until the first client connects (returns immediately if one arrived
+/// since the last call).
+pub async fn client_arrived() {
+    CLIENT_ARRIVED.notified().await;
+}
@@ -0,0 +1,66 @@
+//! Process-wide shutdown signal.  Triggered by SIGINT/SIGTERM in the
+//! standalone binary or by `deragabu_agent_shutdown` over FFI, and observed
+//! by the signaling server and the capture loops.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+use tracing::info;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+static SHUTDOWN: Notify = Notify::const_new();
+
+/// Request shutdown.  Idempotent; safe to call from any thread.
+pub fn trigger() {
+    if !REQUESTED.swap(true, Ordering::SeqCst) {
+        SHUTDOWN.notify_waiters();
+    }
+}
+
+/// Clear a previous request so the agent can be started again (FFI re-init).
+pub(crate) fn reset() {
+    REQUESTED.store(false, Ordering::SeqCst);
+}
+
+/// Whether shutdown has been requested.
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Wait until shutdown is requested (returns immediately if it already was).
+pub async fn requested() {
+    let notified = SHUTDOWN.notified();
+    tokio::pin!(notified);
+    // Register before checking the flag so a concurrent trigger isn't missed.
+    notified.as_mut().enable();
+    if is_requested() {
+        return;
+    }
+    notified.await;
+}
+
+/// Wait for Ctrl+C (SIGINT / CTRL_C_EVENT) or, on Unix, SIGTERM.
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+                    _ = term.recv() => info!("Received SIGTERM"),
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Cannot install SIGTERM handler: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                info!("Received SIGINT");
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        info!("Received Ctrl+C");
+    }
+}
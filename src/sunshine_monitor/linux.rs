@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use notify::{RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use tracing::{debug, info, warn};
+
+use super::SunshineSettingsEvent;
+
+// ─── Constants ──────────────────────────────────────────────────────────────
+
+/// Sunshine's config file name inside its config directory.
+const CONFIG_FILE_NAME: &str = "sunshine.conf";
+
+/// Editors often write a file in several steps; wait this long after the
+/// first change notification before re-reading.
+const RELOAD_DEBOUNCE_MS: u64 = 200;
+
+// ─── Config parsing ─────────────────────────────────────────────────────────
+
+/// Path of Sunshine's config file: `$XDG_CONFIG_HOME/sunshine/sunshine.conf`,
+/// falling back to `~/.config/sunshine/sunshine.conf`.
+pub fn config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("sunshine").join(CONFIG_FILE_NAME))
+}
+
+/// The subset of `sunshine.conf` the agent cares about.
+#[derive(Debug, Default)]
+struct SunshineConfig {
+    /// Display to capture (`output_name`)
+    output_name: Option<String>,
+    /// Capture backend (`capture`, e.g. `kms`, `x11`, `wlr`)
+    capture: Option<String>,
+    /// Whether Sunshine draws the cursor into the video (`display_cursor`)
+    display_cursor: Option<bool>,
+}
+
+/// Parse Sunshine's `key = value` config format.  Lines starting with `#`
+/// are comments; unknown keys are ignored.
+fn parse_config(text: &str) -> SunshineConfig {
+    let mut config = SunshineConfig::default();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "output_name" => config.output_name = Some(value.to_string()),
+            "capture" => config.capture = Some(value.to_string()),
+            "display_cursor" => {
+                config.display_cursor = match value.to_ascii_lowercase().as_str() {
+                    "true" | "enabled" | "on" | "yes" | "1" => Some(true),
+                    "false" | "disabled" | "off" | "no" | "0" => Some(false),
+                    _ => {
+                        warn!(
+                            "Unrecognised display_cursor value {:?} in sunshine.conf",
+                            value
+                        );
+                        None
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    config
+}
+
+fn read_config(path: &Path) -> Result<SunshineConfig> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {:?}", path))?;
+    Ok(parse_config(&text))
+}
+
+// ─── Main monitor loop ──────────────────────────────────────────────────────
+
+/// Watch `sunshine.conf` and emit [`SunshineSettingsEvent`]s when its
+/// `display_cursor` setting changes.
+///
+/// 1. Read the config and send the initial state.
+/// 2. Watch the config directory (editors replace the file by rename, which a
+///    watch on the file itself would miss).
+/// 3. On each change to the file, re-read it and send an event if the
+///    resulting overlay state differs.
+///
+/// Without a `display_cursor` key the overlay stays shown, matching the
+/// non-Windows default.
+pub async fn run_monitor(tx: mpsc::Sender<SunshineSettingsEvent>, path: PathBuf) -> Result<()> {
+    let dir = path
+        .parent()
+        .context("sunshine.conf has no parent directory")?
+        .to_path_buf();
+
+    let (change_tx, mut change_rx) = mpsc::channel::<()>(1);
+    let watched = path.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        match res {
+            Ok(event) if event.paths.iter().any(|p| p == &watched) => {
+                // A full channel already has a reload queued.
+                let _ = change_tx.try_send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("sunshine.conf watch error: {}", e),
+        }
+    })?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("watch {:?}", dir))?;
+
+    let mut last_value: Option<bool> = None;
+    info!("Sunshine monitor started (watching {:?})", path);
+
+    loop {
+        match read_config(&path) {
+            Ok(config) => {
+                debug!(
+                    "sunshine.conf: output_name={:?}, capture={:?}, display_cursor={:?}",
+                    config.output_name, config.capture, config.display_cursor
+                );
+                // Same inversion as the Windows monitor: Sunshine drawing the
+                // cursor into the video means the overlay is not needed.
+                let show_overlay = config.display_cursor != Some(true);
+                if last_value != Some(show_overlay) {
+                    info!(
+                        "Sunshine display_cursor: {:?} (overlay: {})",
+                        config.display_cursor,
+                        if show_overlay { "show" } else { "hide" }
+                    );
+                    last_value = Some(show_overlay);
+                    if tx
+                        .send(SunshineSettingsEvent {
+                            draw_cursor: Some(show_overlay),
+                            sunshine_running: true,
+                            ..Default::default()
+                        })
+                        .await
+                        .is_err()
+                    {
+                        info!("Settings receiver dropped, stopping Sunshine monitor");
+                        return Ok(());
+                    }
+                }
+            }
+            // Deleted or mid-rewrite; keep the last known value.
+            Err(e) => debug!("Cannot read sunshine.conf: {}", e),
+        }
+
+        if change_rx.recv().await.is_none() {
+            warn!("sunshine.conf watcher stopped");
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(RELOAD_DEBOUNCE_MS)).await;
+        // Coalesce notifications that arrived during the debounce.
+        while change_rx.try_recv().is_ok() {}
+    }
+}
@@ -1,10 +1,17 @@
 use anyhow::Result;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::mpsc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
 
+#[cfg(target_os = "linux")]
+mod linux;
+mod theme;
 #[cfg(target_os = "windows")]
 mod windows;
 
+/// How often the OS colour scheme is re-read.
+const THEME_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
 // ─── FFI push state ─────────────────────────────────────────────────────────
 //
 // When the agent is linked into Sunshine as a static library, Sunshine calls
@@ -15,9 +22,13 @@ mod windows;
 /// Current display_cursor value (set via FFI or defaults to `true`).
 static DISPLAY_CURSOR_FFI: AtomicBool = AtomicBool::new(true);
 
+/// Whether Sunshine has pushed a value over FFI yet.
+static DISPLAY_CURSOR_FFI_SET: AtomicBool = AtomicBool::new(false);
+
 /// Set `display_cursor` from the C FFI.  Thread-safe, lock-free.
 pub fn set_display_cursor_from_ffi(val: bool) {
     DISPLAY_CURSOR_FFI.store(val, Ordering::SeqCst);
+    DISPLAY_CURSOR_FFI_SET.store(true, Ordering::SeqCst);
 }
 
 /// Read the current FFI display_cursor value.
@@ -25,12 +36,75 @@ pub fn get_display_cursor_ffi() -> bool {
     DISPLAY_CURSOR_FFI.load(Ordering::SeqCst)
 }
 
-/// Event emitted when cursor overlay visibility should change.
-#[derive(Clone, Debug)]
+/// Host desktop colour scheme.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// Not detected (unsupported desktop, or the setting could not be read)
+    #[default]
+    Unknown,
+    Light,
+    Dark,
+}
+
+/// Event emitted when cursor overlay visibility, the stream settings or the
+/// desktop colour scheme change.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct SunshineSettingsEvent {
     /// Whether the agent's overlay cursor should be shown to the user.
-    /// `true` = show overlay cursor, `false` = hide overlay cursor.
-    pub draw_cursor: bool,
+    /// `true` = show overlay cursor, `false` = hide overlay cursor, `None` =
+    /// Sunshine's setting is unknown and clients should pick their own default.
+    pub draw_cursor: Option<bool>,
+    /// Whether Sunshine was found: its process on Windows, its config file
+    /// on Linux, or FFI calls when linked into it.
+    pub sunshine_running: bool,
+    /// Stream resolution in pixels, 0 when unknown.
+    pub width: u32,
+    pub height: u32,
+    /// Stream frame rate, 0 when unknown.
+    pub fps: u32,
+    /// Stream bitrate in kbps, 0 when unknown.
+    pub bitrate_kbps: u32,
+    /// Host desktop colour scheme.
+    pub color_scheme: ColorScheme,
+}
+
+/// Run the platform Sunshine monitor and stamp its events with the OS colour
+/// scheme, re-sending the last event whenever the scheme changes.
+///
+/// Nothing is sent until the platform monitor reports its initial state.
+pub async fn run_sunshine_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
+    let (monitor_tx, mut monitor_rx) = mpsc::channel::<SunshineSettingsEvent>(8);
+    let monitor = run_platform_monitor(monitor_tx);
+    tokio::pin!(monitor);
+
+    let mut theme_poll = interval(THEME_POLL_INTERVAL);
+    theme_poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut scheme = theme::detect_async().await;
+    tracing::info!("Desktop colour scheme: {:?}", scheme);
+    let mut last: Option<SunshineSettingsEvent> = None;
+
+    loop {
+        tokio::select! {
+            result = &mut monitor => return result,
+            Some(ev) = monitor_rx.recv() => last = Some(ev),
+            _ = theme_poll.tick() => {
+                let current = theme::detect_async().await;
+                if current == scheme {
+                    continue;
+                }
+                tracing::info!("Desktop colour scheme changed: {:?} -> {:?}", scheme, current);
+                scheme = current;
+            }
+        }
+        let Some(ev) = &last else { continue };
+        let ev = SunshineSettingsEvent {
+            color_scheme: scheme,
+            ..ev.clone()
+        };
+        if tx.send(ev).await.is_err() {
+            return Ok(());
+        }
+    }
 }
 
 /// Run the Sunshine monitor, watching for draw_cursor state changes.
@@ -39,42 +113,62 @@ pub struct SunshineSettingsEvent {
 /// the running Sunshine process memory (via PDB debug symbols) for the live
 /// runtime value of `draw_cursor`.
 #[cfg(target_os = "windows")]
-pub async fn run_sunshine_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
+async fn run_platform_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
     windows::run_monitor(tx).await
 }
 
-/// Non-Windows monitor: polls the FFI `AtomicBool` for `display_cursor` changes.
+/// Linux monitor: watches `~/.config/sunshine/sunshine.conf` for changes to
+/// its `display_cursor` setting.  Falls back to FFI polling when the file
+/// does not exist (e.g. when linked into Sunshine, or Sunshine not installed).
+#[cfg(target_os = "linux")]
+async fn run_platform_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
+    match linux::config_path() {
+        Some(path) if path.is_file() => linux::run_monitor(tx, path).await,
+        _ => run_ffi_monitor(tx).await,
+    }
+}
+
+/// macOS monitor: FFI polling only.
+#[cfg(target_os = "macos")]
+async fn run_platform_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
+    run_ffi_monitor(tx).await
+}
+
+/// Non-Windows fallback: polls the FFI `AtomicBool` for `display_cursor` changes.
 ///
 /// When linked into Sunshine, the host calls `deragabu_agent_set_display_cursor()`
 /// which updates the `AtomicBool`.  This task detects the change and broadcasts
 /// a `SunshineSettingsEvent` to all connected WebRTC clients.
 ///
-/// When running standalone (no FFI calls), the value stays at the default `true`
-/// (= Sunshine draws cursor in video), which is the safe default.
+/// When running standalone (no FFI calls), `draw_cursor` is reported as
+/// unknown with `sunshine_running: false`.
 #[cfg(not(target_os = "windows"))]
-pub async fn run_sunshine_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
+async fn run_ffi_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
+    let read = || {
+        DISPLAY_CURSOR_FFI_SET
+            .load(Ordering::SeqCst)
+            .then(|| DISPLAY_CURSOR_FFI.load(Ordering::SeqCst))
+    };
+    let event = |draw_cursor: Option<bool>| SunshineSettingsEvent {
+        draw_cursor,
+        sunshine_running: draw_cursor.is_some(),
+        ..Default::default()
+    };
+
     // Send initial state
-    let mut last_value = DISPLAY_CURSOR_FFI.load(Ordering::SeqCst);
-    let _ = tx
-        .send(SunshineSettingsEvent {
-            draw_cursor: last_value,
-        })
-        .await;
+    let mut last_value = read();
+    let _ = tx.send(event(last_value)).await;
     tracing::info!(
-        "Sunshine monitor started (FFI poll mode, initial draw_cursor={})",
+        "Sunshine monitor started (FFI poll mode, initial draw_cursor={:?})",
         last_value
     );
 
     loop {
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        let current = DISPLAY_CURSOR_FFI.load(Ordering::SeqCst);
+        let current = read();
         if current != last_value {
-            tracing::info!("display_cursor changed to {} (via FFI)", current);
-            let _ = tx
-                .send(SunshineSettingsEvent {
-                    draw_cursor: current,
-                })
-                .await;
+            tracing::info!("display_cursor changed to {:?} (via FFI)", current);
+            let _ = tx.send(event(current)).await;
             last_value = current;
         }
     }
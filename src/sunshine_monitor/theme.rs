@@ -0,0 +1,104 @@
+//! OS colour scheme (light/dark) detection.
+//!
+//! - Windows: `AppsUseLightTheme` under `HKCU\...\Themes\Personalize`
+//! - macOS: `AppleInterfaceStyle` in the global defaults domain
+//! - Linux: GNOME's `color-scheme`, falling back to a `-dark` GTK theme name
+//!
+//! All of these read the settings of the account the agent runs as.
+
+use super::ColorScheme;
+
+/// Detect the current scheme on a blocking thread (the macOS and Linux
+/// probes spawn a process).
+pub(super) async fn detect_async() -> ColorScheme {
+    tokio::task::spawn_blocking(detect)
+        .await
+        .unwrap_or(ColorScheme::Unknown)
+}
+
+#[cfg(target_os = "windows")]
+fn detect() -> ColorScheme {
+    use windows::core::w;
+    use windows::Win32::Foundation::ERROR_SUCCESS;
+    use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            w!("AppsUseLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut _),
+            Some(&mut size),
+        )
+    };
+    if status != ERROR_SUCCESS {
+        // Missing before Windows 10 1809, where apps are always light.
+        tracing::debug!("AppsUseLightTheme not readable: {:?}", status);
+        return ColorScheme::Unknown;
+    }
+    if value == 0 {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn detect() -> ColorScheme {
+    // The key only exists in dark mode; `defaults` fails when it is absent.
+    let output = match std::process::Command::new("defaults")
+        .args(["read", "-g", "AppleInterfaceStyle"])
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            tracing::debug!("Cannot run defaults: {}", e);
+            return ColorScheme::Unknown;
+        }
+    };
+    let style = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() && style.trim().eq_ignore_ascii_case("dark") {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect() -> ColorScheme {
+    match gsettings("color-scheme").as_deref() {
+        Some("prefer-dark") => ColorScheme::Dark,
+        Some("prefer-light") => ColorScheme::Light,
+        // `default` (or a desktop predating the key): go by the theme name,
+        // e.g. `Adwaita-dark`.
+        _ => match gsettings("gtk-theme") {
+            Some(theme) if theme.to_ascii_lowercase().contains("dark") => ColorScheme::Dark,
+            Some(_) => ColorScheme::Light,
+            None => ColorScheme::Unknown,
+        },
+    }
+}
+
+/// Read a key from `org.gnome.desktop.interface`, without the quotes
+/// `gsettings` prints around strings.
+#[cfg(target_os = "linux")]
+fn gsettings(key: &str) -> Option<String> {
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", key])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8_lossy(&output.stdout);
+    Some(value.trim().trim_matches('\'').to_string())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn detect() -> ColorScheme {
+    ColorScheme::Unknown
+}
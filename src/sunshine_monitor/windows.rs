@@ -1,14 +1,16 @@
 use anyhow::{anyhow, Context, Result};
-use std::ffi::c_void;
+use std::ffi::{c_void, OsStr, OsString};
 use std::mem;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use tokio::sync::mpsc;
 use tokio::time::{interval, Duration};
 use tracing::{debug, error, info, warn};
-use windows::Win32::Foundation::{CloseHandle, HANDLE, LUID, MAX_PATH};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HMODULE, LUID, MAX_PATH};
 use windows::Win32::Security::{
-    AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES,
-    SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+    AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_PRIVILEGE_ENABLED,
+    TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
 };
 use windows::Win32::System::Diagnostics::Debug::ReadProcessMemory;
 use windows::Win32::System::Diagnostics::ToolHelp::{
@@ -17,8 +19,11 @@ use windows::Win32::System::Diagnostics::ToolHelp::{
 use windows::Win32::System::ProcessStatus::{
     EnumProcessModulesEx, GetModuleFileNameExW, LIST_MODULES_ALL,
 };
+use windows::Win32::System::SystemInformation::{
+    IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_I386, IMAGE_FILE_MACHINE_UNKNOWN,
+};
 use windows::Win32::System::Threading::{
-    GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_QUERY_INFORMATION,
+    GetCurrentProcess, IsWow64Process2, OpenProcess, OpenProcessToken, PROCESS_QUERY_INFORMATION,
     PROCESS_VM_READ,
 };
 
@@ -36,11 +41,77 @@ const DEBUGINFO_URL_TEMPLATE: &str =
 /// Default Sunshine version (used when version detection fails).
 const DEFAULT_VERSION: &str = "2025.924.154138";
 
-/// How often (ms) to poll the running Sunshine process for `display_cursor`.
-const POLL_INTERVAL_MS: u64 = 100;
+/// Env var for how often (ms) to poll the running Sunshine process for
+/// `display_cursor`.
+const POLL_INTERVAL_ENV: &str = "SUNSHINE_POLL_MS";
+const DEFAULT_POLL_INTERVAL_MS: u64 = 100;
+const MIN_POLL_INTERVAL_MS: u64 = 20;
+
+/// Env var for how long to wait (seconds) before retrying when Sunshine is
+/// not found.
+const RETRY_INTERVAL_ENV: &str = "SUNSHINE_RETRY_SECS";
+const DEFAULT_RETRY_INTERVAL_SECS: u64 = 5;
+const MIN_RETRY_INTERVAL_SECS: u64 = 1;
+
+/// Env var naming a pre-staged local `.dbg` symbols file, used instead of
+/// downloading the debuginfo archive (for offline / locked-down machines).
+const LOCAL_DBG_ENV: &str = "SUNSHINE_PDB_PATH";
+
+/// Env var holding a known `display_cursor` RVA (hex).  When set, version
+/// detection and symbol handling are skipped entirely.
+const DISPLAY_CURSOR_RVA_ENV: &str = "SUNSHINE_DISPLAY_CURSOR_RVA";
+
+/// Env var for how many cached `sunshine-v*.dbg` versions to keep.
+const DBG_CACHE_KEEP_ENV: &str = "SUNSHINE_DBG_CACHE_KEEP";
+
+/// Default number of cached `.dbg` versions kept (current + one previous).
+const DEFAULT_DBG_CACHE_KEEP: usize = 2;
+
+/// Attempts made to download the debuginfo archive before giving up.
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Delay before the first download retry; doubled after each failure.
+const DOWNLOAD_RETRY_BASE_SECS: u64 = 2;
+
+/// Abort a download attempt when no data arrives for this long.
+const DOWNLOAD_READ_TIMEOUT_SECS: u64 = 60;
+
+/// Optional stream-setting globals read alongside `display_cursor`.  Each is
+/// a 32-bit integer; builds whose `.dbg` lacks a symbol simply leave the
+/// corresponding [`SunshineSettingsEvent`] field at 0 (unknown).
+const STREAM_SETTINGS: &[(StreamSetting, &str)] = &[
+    (StreamSetting::Width, "stream_width"),
+    (StreamSetting::Height, "stream_height"),
+    (StreamSetting::Fps, "stream_fps"),
+    (StreamSetting::BitrateKbps, "stream_bitrate_kbps"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StreamSetting {
+    Width,
+    Height,
+    Fps,
+    BitrateKbps,
+}
 
-/// How long to wait (seconds) before retrying when Sunshine is not found.
-const RETRY_INTERVAL_SECS: u64 = 5;
+impl StreamSetting {
+    fn apply(self, event: &mut SunshineSettingsEvent, value: u32) {
+        match self {
+            StreamSetting::Width => event.width = value,
+            StreamSetting::Height => event.height = value,
+            StreamSetting::Fps => event.fps = value,
+            StreamSetting::BitrateKbps => event.bitrate_kbps = value,
+        }
+    }
+}
+
+/// RVAs resolved from Sunshine's `.dbg`.
+#[derive(Clone, Debug)]
+struct SunshineSymbols {
+    display_cursor: u32,
+    /// Stream settings whose symbol was found; missing ones are omitted.
+    stream_settings: Vec<(StreamSetting, u32)>,
+}
 
 // ── RAII handle wrapper ────────────────────────────────────────────────────────
 
@@ -71,18 +142,11 @@ fn enable_debug_privilege() -> Result<()> {
         .context("OpenProcessToken failed")?;
         let _guard = SafeHandle(token);
 
-        let priv_name: Vec<u16> = "SeDebugPrivilege"
-            .encode_utf16()
-            .chain(std::iter::once(0))
-            .collect();
+        let priv_name = to_wide("SeDebugPrivilege");
 
         let mut luid = LUID::default();
-        LookupPrivilegeValueW(
-            None,
-            windows::core::PCWSTR(priv_name.as_ptr()),
-            &mut luid,
-        )
-        .context("LookupPrivilegeValueW failed")?;
+        LookupPrivilegeValueW(None, windows::core::PCWSTR(priv_name.as_ptr()), &mut luid)
+            .context("LookupPrivilegeValueW failed")?;
 
         let mut tp = TOKEN_PRIVILEGES {
             PrivilegeCount: 1,
@@ -99,6 +163,62 @@ fn enable_debug_privilege() -> Result<()> {
     }
 }
 
+// ── Wide strings ───────────────────────────────────────────────────────────────
+
+/// Largest buffer tried when reading a path (the NT extended-path limit).
+const MAX_WIDE_PATH: usize = 32_768;
+
+/// Encode `s` as a NUL-terminated UTF-16 string for `PCWSTR` arguments.
+fn to_wide(s: impl AsRef<OsStr>) -> Vec<u16> {
+    s.as_ref().encode_wide().chain(std::iter::once(0)).collect()
+}
+
+/// Slice of `buf` up to (not including) the first NUL.
+fn trim_wide(buf: &[u16]) -> &[u16] {
+    let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    &buf[..end]
+}
+
+/// Decode a (possibly NUL-terminated) UTF-16 buffer into a `String`.
+fn wide_to_string(buf: &[u16]) -> String {
+    String::from_utf16_lossy(trim_wide(buf))
+}
+
+/// Decode a (possibly NUL-terminated) UTF-16 buffer into a `PathBuf` without
+/// lossy conversion.
+fn wide_to_path(buf: &[u16]) -> PathBuf {
+    PathBuf::from(OsString::from_wide(trim_wide(buf)))
+}
+
+/// Call a Win32 "fill this buffer" API, growing the buffer while the result
+/// looks truncated.
+///
+/// `fill` receives the buffer and returns the number of characters written
+/// (0 on failure).  APIs such as `GetModuleFileNameExW` signal truncation by
+/// filling the whole buffer, so a length within one character of the buffer
+/// size triggers a retry with twice the capacity, up to `MAX_WIDE_PATH`.
+fn read_wide_growing(mut fill: impl FnMut(&mut [u16]) -> u32) -> Option<Vec<u16>> {
+    let mut capacity = MAX_PATH as usize;
+    loop {
+        let mut buf = vec![0u16; capacity];
+        let len = fill(&mut buf) as usize;
+        if len == 0 {
+            return None;
+        }
+        if len + 1 < capacity || capacity >= MAX_WIDE_PATH {
+            buf.truncate(len.min(capacity));
+            return Some(buf);
+        }
+        capacity = (capacity * 2).min(MAX_WIDE_PATH);
+    }
+}
+
+/// Full path of `module` in the process behind `handle`.
+fn module_file_name(handle: HANDLE, module: HMODULE) -> Option<PathBuf> {
+    read_wide_growing(|buf| unsafe { GetModuleFileNameExW(handle, module, buf) })
+        .map(|buf| wide_to_path(&buf))
+}
+
 // ── Process discovery ──────────────────────────────────────────────────────────
 
 /// Information about the running Sunshine process.
@@ -106,6 +226,28 @@ struct SunshineProcess {
     pid: u32,
     exe_path: PathBuf,
     module_base: usize,
+    /// Guest architecture when Sunshine runs under WOW64 (a 32-bit build on
+    /// 64-bit Windows); `None` for a native process
+    wow64_machine: Option<IMAGE_FILE_MACHINE>,
+}
+
+/// The WOW64 guest architecture of `process`, or `None` if it runs natively
+/// (or the query fails, e.g. on Windows before 10 1511).
+unsafe fn wow64_machine(process: HANDLE) -> Option<IMAGE_FILE_MACHINE> {
+    let mut machine = IMAGE_FILE_MACHINE_UNKNOWN;
+    if let Err(e) = IsWow64Process2(process, &mut machine, None) {
+        debug!("IsWow64Process2 failed: {}", e);
+        return None;
+    }
+    (machine != IMAGE_FILE_MACHINE_UNKNOWN).then_some(machine)
+}
+
+fn machine_name(machine: IMAGE_FILE_MACHINE) -> String {
+    if machine == IMAGE_FILE_MACHINE_I386 {
+        "x86".to_string()
+    } else {
+        format!("machine 0x{:04x}", machine.0)
+    }
 }
 
 /// Find the running Sunshine process, returning its PID, path, and module base.
@@ -135,27 +277,18 @@ fn find_sunshine_process() -> Result<Option<SunshineProcess>> {
         }
 
         loop {
-            let name_end = entry
-                .szExeFile
-                .iter()
-                .position(|&c| c == 0)
-                .unwrap_or(entry.szExeFile.len());
-            let name = String::from_utf16_lossy(&entry.szExeFile[..name_end]);
+            let name = wide_to_string(&entry.szExeFile);
 
             if name.eq_ignore_ascii_case(SUNSHINE_EXE_NAME) {
                 let pid = entry.th32ProcessID;
 
                 // Open with read access
-                let handle = OpenProcess(
-                    PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
-                    false,
-                    pid,
-                )?;
+                let handle = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid)?;
                 let _h_guard = SafeHandle(handle);
+                let wow64_machine = wow64_machine(handle);
 
                 // Enumerate modules to get base address and full path
-                let mut modules =
-                    [windows::Win32::Foundation::HMODULE::default(); 1];
+                let mut modules = [HMODULE::default(); 1];
                 let mut needed = 0u32;
                 if EnumProcessModulesEx(
                     handle,
@@ -169,18 +302,16 @@ fn find_sunshine_process() -> Result<Option<SunshineProcess>> {
                     let base_addr = modules[0].0 as usize;
 
                     // Get full path of the main module
-                    let mut name_buf = [0u16; MAX_PATH as usize];
-                    let len = GetModuleFileNameExW(handle, modules[0], &mut name_buf);
-                    let exe_path = if len > 0 {
-                        PathBuf::from(String::from_utf16_lossy(&name_buf[..len as usize]))
-                    } else {
+                    let exe_path = module_file_name(handle, modules[0]).unwrap_or_else(|| {
+                        warn!("GetModuleFileNameExW failed for Sunshine (pid {})", pid);
                         PathBuf::new()
-                    };
+                    });
 
                     return Ok(Some(SunshineProcess {
                         pid,
                         exe_path,
                         module_base: base_addr,
+                        wow64_machine,
                     }));
                 }
 
@@ -188,6 +319,7 @@ fn find_sunshine_process() -> Result<Option<SunshineProcess>> {
                     pid,
                     exe_path: PathBuf::new(),
                     module_base: 0,
+                    wow64_machine,
                 }));
             }
 
@@ -222,16 +354,12 @@ fn detect_sunshine_version(exe_path: &Path) -> String {
 
 /// Read the `ProductVersion` string from a PE file's version-info resource.
 fn read_product_version(exe_path: &Path) -> Result<String> {
+    use windows::core::PCWSTR;
     use windows::Win32::Storage::FileSystem::{
         GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW,
     };
-    use windows::core::PCWSTR;
 
-    let path_wide: Vec<u16> = exe_path
-        .to_string_lossy()
-        .encode_utf16()
-        .chain(std::iter::once(0))
-        .collect();
+    let path_wide = to_wide(exe_path);
 
     unsafe {
         let mut _handle = 0u32;
@@ -252,7 +380,7 @@ fn read_product_version(exe_path: &Path) -> Result<String> {
         // Query the root VS_FIXEDFILEINFO to get numeric version
         let mut ptr: *mut c_void = std::ptr::null_mut();
         let mut len = 0u32;
-        let sub_block: Vec<u16> = "\\".encode_utf16().chain(std::iter::once(0)).collect();
+        let sub_block = to_wide("\\");
 
         let vq_result = VerQueryValueW(
             buf.as_ptr() as *const c_void,
@@ -311,8 +439,8 @@ fn read_product_version(exe_path: &Path) -> Result<String> {
 
 /// Try to read the ProductVersion string from the version info string table.
 fn query_version_string(version_info: &[u8]) -> Result<String> {
-    use windows::Win32::Storage::FileSystem::VerQueryValueW;
     use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::VerQueryValueW;
 
     // Common language/codepage combinations
     let lang_codepages = [
@@ -324,7 +452,7 @@ fn query_version_string(version_info: &[u8]) -> Result<String> {
     unsafe {
         for lc in &lang_codepages {
             let query = format!("\\StringFileInfo\\{}\\ProductVersion", lc);
-            let query_wide: Vec<u16> = query.encode_utf16().chain(std::iter::once(0)).collect();
+            let query_wide = to_wide(&query);
 
             let mut ptr: *mut c_void = std::ptr::null_mut();
             let mut len = 0u32;
@@ -340,9 +468,7 @@ fn query_version_string(version_info: &[u8]) -> Result<String> {
                 && len > 0
             {
                 let slice = std::slice::from_raw_parts(ptr as *const u16, len as usize);
-                let end = slice.iter().position(|&c| c == 0).unwrap_or(slice.len());
-                let version = String::from_utf16_lossy(&slice[..end]);
-                let version = version.trim().to_string();
+                let version = wide_to_string(slice).trim().to_string();
                 if !version.is_empty() {
                     return Ok(version);
                 }
@@ -362,16 +488,178 @@ fn dbg_cache_dir() -> PathBuf {
         .join("sunshine-dbg")
 }
 
+/// Location of the cached `.dbg` for a Sunshine version.
+fn cached_dbg_path(version: &str) -> PathBuf {
+    dbg_cache_dir().join(format!("sunshine-v{}.dbg", version))
+}
+
+/// `display_cursor` RVA from `SUNSHINE_DISPLAY_CURSOR_RVA`, accepting hex
+/// with or without a `0x` prefix.  Read once on first use.
+fn display_cursor_rva_override() -> Option<u32> {
+    static RVA: OnceLock<Option<u32>> = OnceLock::new();
+    *RVA.get_or_init(|| {
+        let raw = std::env::var(DISPLAY_CURSOR_RVA_ENV).ok()?;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        let digits = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))
+            .unwrap_or(trimmed);
+        match u32::from_str_radix(digits, 16) {
+            Ok(rva) if rva != 0 => {
+                info!(
+                    "{}=0x{:08x}: skipping symbol resolution",
+                    DISPLAY_CURSOR_RVA_ENV, rva
+                );
+                Some(rva)
+            }
+            _ => {
+                warn!(
+                    "Invalid {}={:?}, resolving symbols instead",
+                    DISPLAY_CURSOR_RVA_ENV, raw
+                );
+                None
+            }
+        }
+    })
+}
+
+/// Integer from env var `name`, raised to `min` if lower; `default` when
+/// unset or invalid.
+fn env_u64_at_least(name: &str, default: u64, min: u64) -> u64 {
+    let Ok(raw) = std::env::var(name) else {
+        return default;
+    };
+    match raw.trim().parse::<u64>() {
+        Ok(n) if n < min => {
+            warn!("{}={} is below the minimum, using {}", name, n, min);
+            min
+        }
+        Ok(n) => n,
+        Err(_) => {
+            warn!("Invalid {}={:?}, using {}", name, raw, default);
+            default
+        }
+    }
+}
+
+/// Number of cached `.dbg` versions to keep, from `SUNSHINE_DBG_CACHE_KEEP`
+/// (default 2, minimum 1).  Read once on first use.
+fn dbg_cache_keep() -> usize {
+    static KEEP: OnceLock<usize> = OnceLock::new();
+    *KEEP.get_or_init(|| {
+        let Ok(raw) = std::env::var(DBG_CACHE_KEEP_ENV) else {
+            return DEFAULT_DBG_CACHE_KEEP;
+        };
+        match raw.trim().parse::<usize>() {
+            Ok(n) if n >= 1 => n,
+            _ => {
+                warn!(
+                    "Invalid {}={:?}, keeping {} versions",
+                    DBG_CACHE_KEEP_ENV, raw, DEFAULT_DBG_CACHE_KEEP
+                );
+                DEFAULT_DBG_CACHE_KEEP
+            }
+        }
+    })
+}
+
+/// Remove `extract-v*` directories and `debuginfo-v*.7z` archives left behind
+/// by a download that was interrupted (agent killed mid-extraction).
+fn remove_stale_downloads() {
+    let Ok(entries) = std::fs::read_dir(dbg_cache_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let result = if name.starts_with("extract-v") && path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else if name.starts_with("debuginfo-v") && name.ends_with(".7z") {
+            std::fs::remove_file(&path)
+        } else {
+            continue;
+        };
+        match result {
+            Ok(()) => info!("Removed leftover download {:?}", path),
+            Err(e) => debug!("Cannot remove leftover download {:?}: {}", path, e),
+        }
+    }
+}
+
+/// Delete all but the newest [`dbg_cache_keep`] cached `sunshine-v*.dbg`
+/// files (by modification time).  `in_use` is never deleted and counts
+/// towards the limit.
+fn prune_dbg_cache(in_use: &Path) {
+    let Ok(entries) = std::fs::read_dir(dbg_cache_dir()) else {
+        return;
+    };
+    let mut cached: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            name.starts_with("sunshine-v") && name.ends_with(".dbg")
+        })
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .filter(|(_, path)| path != in_use)
+        .collect();
+    // Newest first; the in-use file takes one of the kept slots.
+    cached.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in cached.into_iter().skip(dbg_cache_keep() - 1) {
+        match std::fs::remove_file(&path) {
+            Ok(()) => info!("Pruned stale cached symbols {:?}", path),
+            Err(e) => warn!("Cannot prune cached symbols {:?}: {}", path, e),
+        }
+    }
+}
+
+/// Resolve symbols from the local symbols file named by
+/// `SUNSHINE_PDB_PATH`, if set.  Returns `None` (falling back to the
+/// download path) when the variable is unset or the file is unusable.
+fn parse_local_dbg() -> Option<(SunshineSymbols, PathBuf)> {
+    let path = PathBuf::from(std::env::var_os(LOCAL_DBG_ENV).filter(|v| !v.is_empty())?);
+    if !path.is_file() {
+        warn!(
+            "{}={:?} does not exist, falling back to download",
+            LOCAL_DBG_ENV, path
+        );
+        return None;
+    }
+    match find_symbols(&path) {
+        Ok(symbols) => {
+            info!("Using local symbols from {}: {:?}", LOCAL_DBG_ENV, path);
+            Some((symbols, path))
+        }
+        Err(e) => {
+            warn!(
+                "Cannot use {}={:?} ({:#}), falling back to download",
+                LOCAL_DBG_ENV, path, e
+            );
+            None
+        }
+    }
+}
+
 /// Download the debuginfo archive and parse the `.dbg` file for the `display_cursor`
-/// symbol, returning its RVA (Relative Virtual Address).
-async fn download_and_parse_dbg(version: &str) -> Result<u32> {
+/// and stream-setting symbols, returning their RVAs (Relative Virtual Addresses)
+/// and the `.dbg` path.  A local file named by `SUNSHINE_PDB_PATH` takes precedence.
+/// Older cached versions beyond `SUNSHINE_DBG_CACHE_KEEP` are pruned afterwards.
+async fn download_and_parse_dbg(version: &str) -> Result<(SunshineSymbols, PathBuf)> {
+    if let Some(local) = parse_local_dbg() {
+        return Ok(local);
+    }
+
     let cache = dbg_cache_dir();
-    let dbg_path = cache.join(format!("sunshine-v{}.dbg", version));
+    let dbg_path = cached_dbg_path(version);
 
     // If .dbg is already cached, just parse it
     if dbg_path.exists() {
         info!("Using cached .dbg: {:?}", dbg_path);
-        return find_display_cursor_rva(&dbg_path);
+        let symbols = find_symbols(&dbg_path)?;
+        prune_dbg_cache(&dbg_path);
+        return Ok((symbols, dbg_path));
     }
 
     // Download the .7z archive
@@ -379,8 +667,7 @@ async fn download_and_parse_dbg(version: &str) -> Result<u32> {
     info!("Downloading Sunshine debuginfo from: {}", url);
 
     let seven_z_path = cache.join(format!("debuginfo-v{}.7z", version));
-    std::fs::create_dir_all(&cache)
-        .context("Failed to create debug info cache directory")?;
+    std::fs::create_dir_all(&cache).context("Failed to create debug info cache directory")?;
 
     download_file(&url, &seven_z_path)
         .await
@@ -410,40 +697,125 @@ async fn download_and_parse_dbg(version: &str) -> Result<u32> {
     info!("Found .dbg: {:?}", found_dbg);
 
     // Copy to cache location
-    std::fs::copy(&found_dbg, &dbg_path)
-        .context("Failed to cache .dbg file")?;
+    std::fs::copy(&found_dbg, &dbg_path).context("Failed to cache .dbg file")?;
 
     // Clean up extraction dir and archive
     std::fs::remove_dir_all(&extract_dir).ok();
     std::fs::remove_file(&seven_z_path).ok();
 
-    find_display_cursor_rva(&dbg_path)
+    let symbols = find_symbols(&dbg_path)?;
+    prune_dbg_cache(&dbg_path);
+    Ok((symbols, dbg_path))
 }
 
 /// Download a file from a URL to a local path.
+///
+/// Retries up to [`DOWNLOAD_ATTEMPTS`] times with exponential backoff.  A
+/// retry resumes from the bytes already on disk via an HTTP `Range` request
+/// when the server honours it, and starts over otherwise.
 async fn download_file(url: &str, dest: &Path) -> Result<()> {
     let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(120))
+        .connect_timeout(Duration::from_secs(30))
+        .read_timeout(Duration::from_secs(DOWNLOAD_READ_TIMEOUT_SECS))
         .build()
         .context("Failed to create HTTP client")?;
 
-    let response = client
-        .get(url)
+    // Never resume from a file this call didn't write.
+    let _ = std::fs::remove_file(dest);
+
+    let mut delay = Duration::from_secs(DOWNLOAD_RETRY_BASE_SECS);
+    for attempt in 1..=DOWNLOAD_ATTEMPTS {
+        match download_attempt(&client, url, dest).await {
+            Ok(total) => {
+                info!("Downloaded {} bytes to {:?}", total, dest);
+                return Ok(());
+            }
+            Err(e) if attempt < DOWNLOAD_ATTEMPTS => {
+                warn!(
+                    "Download attempt {}/{} failed: {:#}; retrying in {:?}",
+                    attempt, DOWNLOAD_ATTEMPTS, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => {
+                return Err(e.context(format!("giving up after {} attempts", DOWNLOAD_ATTEMPTS)));
+            }
+        }
+    }
+    unreachable!("DOWNLOAD_ATTEMPTS is at least 1")
+}
+
+/// One download attempt, streaming the body to `dest` and resuming from its
+/// current length when non-empty.  Returns the final file size.
+async fn download_attempt(client: &reqwest::Client, url: &str, dest: &Path) -> Result<u64> {
+    use tokio::io::AsyncWriteExt;
+
+    let existing = std::fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url);
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    let mut response = request
         .send()
         .await
         .context("HTTP request failed")?
         .error_for_status()
         .context("HTTP error response")?;
 
-    let bytes = response
-        .bytes()
+    let resumed = existing > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    if existing > 0 {
+        if resumed {
+            info!("Resuming download at byte {}", existing);
+        } else {
+            info!("Server ignored range request, restarting download");
+        }
+    }
+    let mut written = if resumed { existing } else { 0 };
+    let total = response.content_length().map(|len| len + written);
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(dest)
         .await
-        .context("Failed to read response body")?;
+        .with_context(|| format!("Failed to open {:?}", dest))?;
 
-    std::fs::write(dest, &bytes).context("Failed to write file")?;
+    // Log roughly every 10% (or every 8 MiB when the size is unknown).
+    let step = total.map_or(8 << 20, |t| (t / 10).max(1));
+    let mut next_log = written + step;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .context("Failed to read response body")?
+    {
+        file.write_all(&chunk)
+            .await
+            .context("Failed to write file")?;
+        written += chunk.len() as u64;
+        if written >= next_log {
+            match total {
+                Some(t) => info!(
+                    "Downloaded {} / {} bytes ({}%)",
+                    written,
+                    t,
+                    written * 100 / t
+                ),
+                None => info!("Downloaded {} bytes", written),
+            }
+            next_log = written + step;
+        }
+    }
+    file.flush().await.context("Failed to write file")?;
 
-    info!("Downloaded {} bytes to {:?}", bytes.len(), dest);
-    Ok(())
+    if let Some(t) = total {
+        if written != t {
+            return Err(anyhow!("download truncated: {} of {} bytes", written, t));
+        }
+    }
+    Ok(written)
 }
 
 /// Recursively search for the `sunshine.dbg` file in a directory.
@@ -484,9 +856,10 @@ fn walkdir(dir: &Path) -> Result<Vec<PathBuf>> {
     Ok(results)
 }
 
-/// Parse a `.dbg` file (PE with COFF symbols) and find the RVA of the
-/// `display_cursor` global variable.
-fn find_display_cursor_rva(dbg_path: &Path) -> Result<u32> {
+/// Parse a `.dbg` file (PE with COFF symbols) and find the RVAs of the
+/// `display_cursor` global variable (required) and of any [`STREAM_SETTINGS`]
+/// globals present in this build.
+fn find_symbols(dbg_path: &Path) -> Result<SunshineSymbols> {
     use object::{Object, ObjectSymbol, SymbolKind};
 
     let data = std::fs::read(dbg_path)
@@ -498,76 +871,265 @@ fn find_display_cursor_rva(dbg_path: &Path) -> Result<u32> {
     let image_base = file.relative_address_base();
     info!(".dbg image base: 0x{:016x}", image_base);
 
+    let mut display_cursor = None;
+    let mut stream_settings = Vec::new();
     for symbol in file.symbols() {
         let name = match symbol.name() {
             Ok(n) => n,
             Err(_) => continue,
         };
 
-        // Match the exact `display_cursor` data symbol (skip section/refptr symbols)
-        if name == "display_cursor" && symbol.kind() == SymbolKind::Data {
-            let addr = symbol.address();
-            if addr != 0 {
-                let rva = addr.wrapping_sub(image_base);
-                info!(
-                    ".dbg symbol '{}': VA=0x{:016x}, RVA=0x{:08x}",
-                    name, addr, rva
-                );
-                return Ok(rva as u32);
-            }
+        // Match exact data symbols only (skip section/refptr symbols)
+        if symbol.kind() != SymbolKind::Data || symbol.address() == 0 {
+            continue;
+        }
+        let setting = STREAM_SETTINGS.iter().find(|(_, sym)| *sym == name);
+        if name != "display_cursor" && setting.is_none() {
+            continue;
+        }
+
+        let addr = symbol.address();
+        let rva = addr.wrapping_sub(image_base) as u32;
+        info!(
+            ".dbg symbol '{}': VA=0x{:016x}, RVA=0x{:08x}",
+            name, addr, rva
+        );
+        match setting {
+            Some((setting, _)) => stream_settings.push((*setting, rva)),
+            None => display_cursor = Some(rva),
         }
     }
 
-    Err(anyhow!(
-        "Symbol 'display_cursor' not found in .dbg {:?}",
-        dbg_path
-    ))
+    let display_cursor = display_cursor
+        .ok_or_else(|| anyhow!("Symbol 'display_cursor' not found in .dbg {:?}", dbg_path))?;
+    for (setting, sym) in STREAM_SETTINGS {
+        if !stream_settings.iter().any(|(s, _)| s == setting) {
+            info!(
+                "Symbol '{}' not in this build, {:?} will be reported as unknown",
+                sym, setting
+            );
+        }
+    }
+    Ok(SunshineSymbols {
+        display_cursor,
+        stream_settings,
+    })
+}
+
+/// CodeView RSDS identity of a PE image: GUID and age.
+type DebugId = ([u8; 16], u32);
+
+/// Read the CodeView (RSDS) debug directory entry from a PE file's headers.
+/// Returns `None` if the image carries no CodeView record.
+fn read_debug_id(path: &Path) -> Result<Option<DebugId>> {
+    use object::Object;
+
+    let data = std::fs::read(path).with_context(|| format!("Cannot read {:?}", path))?;
+    let file =
+        object::File::parse(&*data).with_context(|| format!("Failed to parse {:?}", path))?;
+    let info = file
+        .pdb_info()
+        .with_context(|| format!("Invalid debug directory in {:?}", path))?;
+    Ok(info.map(|cv| {
+        debug!(
+            "{:?}: CodeView guid={} age={} pdb={:?}",
+            path,
+            format_guid(&cv.guid()),
+            cv.age(),
+            String::from_utf8_lossy(cv.path())
+        );
+        (cv.guid(), cv.age())
+    }))
+}
+
+fn format_guid(guid: &[u8; 16]) -> String {
+    guid.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check that the `.dbg` symbols were produced for the running executable by
+/// comparing their CodeView GUID/age.  Only a definite mismatch fails; images
+/// without a CodeView record (common for MinGW builds) can't be checked and
+/// are trusted on the version match alone.
+fn verify_debug_id(exe_path: &Path, dbg_path: &Path) -> Result<()> {
+    let exe_id = read_debug_id(exe_path)?;
+    let dbg_id = read_debug_id(dbg_path)?;
+    match (exe_id, dbg_id) {
+        (Some(exe), Some(dbg)) if exe != dbg => Err(anyhow!(
+            "debug symbols do not match sunshine.exe (exe guid={} age={}, .dbg guid={} age={})",
+            format_guid(&exe.0),
+            exe.1,
+            format_guid(&dbg.0),
+            dbg.1
+        )),
+        (Some(_), Some(_)) => {
+            info!("Debug symbols match sunshine.exe (CodeView GUID/age)");
+            Ok(())
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            warn!("Only one of sunshine.exe / .dbg has a CodeView record, cannot verify symbols");
+            Ok(())
+        }
+        (None, None) => {
+            debug!("No CodeView records present, trusting version match");
+            Ok(())
+        }
+    }
 }
 
 // ── Process memory reading ─────────────────────────────────────────────────────
 
-/// Read a single `bool` (1 byte) from a remote process at the given address.
-fn read_process_bool(pid: u32, address: usize) -> Result<bool> {
+/// Read `N` bytes from a remote process at the given address.
+fn read_process_bytes<const N: usize>(pid: u32, address: usize) -> Result<[u8; N]> {
     unsafe {
-        let handle = OpenProcess(PROCESS_VM_READ, false, pid)
-            .context("OpenProcess failed (need admin?)")?;
+        let handle =
+            OpenProcess(PROCESS_VM_READ, false, pid).context("OpenProcess failed (need admin?)")?;
         let _guard = SafeHandle(handle);
 
-        let mut value: u8 = 0;
+        let mut value = [0u8; N];
         let mut bytes_read: usize = 0;
 
         ReadProcessMemory(
             handle,
             address as *const c_void,
-            &mut value as *mut u8 as *mut c_void,
-            1,
+            value.as_mut_ptr() as *mut c_void,
+            N,
             Some(&mut bytes_read),
         )
         .context("ReadProcessMemory failed")?;
 
-        if bytes_read != 1 {
-            return Err(anyhow!("ReadProcessMemory: expected 1 byte, got {}", bytes_read));
+        if bytes_read != N {
+            return Err(anyhow!(
+                "ReadProcessMemory: expected {} bytes, got {}",
+                N,
+                bytes_read
+            ));
         }
 
-        Ok(value != 0)
+        Ok(value)
     }
 }
 
+/// Read a single `bool` (1 byte) from a remote process at the given address.
+fn read_process_bool(pid: u32, address: usize) -> Result<bool> {
+    Ok(read_process_bytes::<1>(pid, address)?[0] != 0)
+}
+
+/// Read a little-endian `u32` from a remote process at the given address.
+fn read_process_u32(pid: u32, address: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(read_process_bytes::<4>(pid, address)?))
+}
+
 // ── Main monitor entry point ───────────────────────────────────────────────────
 
+/// Cannot monitor without a trustworthy symbol offset.  Report Sunshine as
+/// running with an unknown `draw_cursor`, leaving the overlay decision to
+/// clients, and keep the task alive so it doesn't crash the agent.
+async fn run_fallback(tx: &mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
+    let _ = tx
+        .send(SunshineSettingsEvent {
+            draw_cursor: None,
+            sunshine_running: true,
+            ..Default::default()
+        })
+        .await;
+    warn!("Sunshine monitor running in fallback mode (draw_cursor unknown)");
+    loop {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+    }
+}
+
+/// Phases 2–3 of [`run_monitor`]: detect the running version, resolve its
+/// symbols (reusing `resolved` when the version is unchanged) and check them
+/// against the executable.
+async fn resolve_symbols(
+    proc: &SunshineProcess,
+    resolved: &mut Option<(String, SunshineSymbols, PathBuf)>,
+) -> Result<SunshineSymbols> {
+    // Only x64 debuginfo is published; RVAs from it mean nothing in another
+    // build.
+    if let Some(machine) = proc.wow64_machine {
+        return Err(anyhow!(
+            "Sunshine (PID {}) is a 32-bit {} process but only x64 debug symbols are available; \
+             set {} to monitor it",
+            proc.pid,
+            machine_name(machine),
+            DISPLAY_CURSOR_RVA_ENV
+        ));
+    }
+
+    // ── Phase 2: detect version ─────────────────────────────────────────
+    let version = detect_sunshine_version(&proc.exe_path);
+    info!("Sunshine version: {}", version);
+
+    // ── Phase 3: download .dbg and find display_cursor RVA ──────────────
+    let cached = resolved
+        .as_ref()
+        .filter(|(v, _, _)| *v == version)
+        .map(|(_, symbols, path)| (symbols.clone(), path.clone()));
+    if let (None, Some((old, _, _))) = (&cached, &*resolved) {
+        info!(
+            "Sunshine version changed ({} → {}), re-resolving symbols",
+            old, version
+        );
+    }
+    let (symbols, dbg_path) = match cached {
+        Some(hit) => hit,
+        None => {
+            let (symbols, dbg_path) = download_and_parse_dbg(&version)
+                .await
+                .context("Failed to resolve display_cursor from .dbg")?;
+            info!("display_cursor RVA: 0x{:08x}", symbols.display_cursor);
+            *resolved = Some((version, symbols.clone(), dbg_path.clone()));
+            (symbols, dbg_path)
+        }
+    };
+
+    // Reading memory at an RVA from the wrong build would return garbage.
+    verify_debug_id(&proc.exe_path, &dbg_path).context("Refusing to read display_cursor")?;
+    Ok(symbols)
+}
+
 /// Start the Sunshine monitor.
 ///
 /// 1. Locate the running `sunshine.exe` process.
 /// 2. Detect its version and download the matching `.dbg` with debug symbols.
-/// 3. Parse the `.dbg` to find the `display_cursor` global variable's RVA.
-/// 4. Periodically read the live value from process memory.
-/// 5. Emit [`SunshineSettingsEvent`] whenever the value changes.
-/// 6. If the process exits, re-discover and re-attach automatically.
+/// 3. Parse the `.dbg` to find the `display_cursor` global variable's RVA,
+///    after checking its CodeView GUID/age against the running executable.
+/// 4. Periodically read the live value from process memory, together with
+///    whichever stream settings (resolution, fps, bitrate) this build exposes.
+/// 5. Emit [`SunshineSettingsEvent`] whenever any of them changes.
+/// 6. If the process exits, re-discover and re-attach automatically.  The
+///    resolved RVA is reused across restarts; symbols are only re-resolved
+///    when the restarted Sunshine reports a different version.
+///
+/// While no process is found, Sunshine is reported as not running.  When
+/// symbols cannot be resolved, it is reported as running with an unknown
+/// `draw_cursor`.
+///
+/// `SUNSHINE_DISPLAY_CURSOR_RVA` replaces steps 2–3 with a fixed RVA; if
+/// that address cannot be read as a bool, `draw_cursor` is reported unknown.
 pub async fn run_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()> {
-    info!("Sunshine monitor starting…");
+    let poll_interval = Duration::from_millis(env_u64_at_least(
+        POLL_INTERVAL_ENV,
+        DEFAULT_POLL_INTERVAL_MS,
+        MIN_POLL_INTERVAL_MS,
+    ));
+    let retry_interval = Duration::from_secs(env_u64_at_least(
+        RETRY_INTERVAL_ENV,
+        DEFAULT_RETRY_INTERVAL_SECS,
+        MIN_RETRY_INTERVAL_SECS,
+    ));
+    info!(
+        "Sunshine monitor starting (poll every {:?}, retry every {:?})…",
+        poll_interval, retry_interval
+    );
+    remove_stale_downloads();
 
     // Outer loop: re-discovers the Sunshine process when it exits.
-    let mut last_value: Option<bool> = None;
+    let mut last_value: Option<SunshineSettingsEvent> = None;
+    // (version, RVAs, .dbg path) from the last successful symbol resolution.
+    let mut resolved: Option<(String, SunshineSymbols, PathBuf)> = None;
+    let mut attached_before = false;
 
     loop {
         // ── Phase 1: find the running Sunshine process ──────────────────────
@@ -579,59 +1141,88 @@ pub async fn run_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()>
                         "Found Sunshine (PID {}) but cannot read module base (access denied?)",
                         p.pid
                     );
-                    tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+                    tokio::time::sleep(retry_interval).await;
                 }
                 Ok(None) => {
+                    let not_running = SunshineSettingsEvent::default();
+                    if last_value.as_ref() != Some(&not_running) {
+                        info!("Sunshine is not running");
+                        last_value = Some(not_running.clone());
+                        if tx.send(not_running).await.is_err() {
+                            info!("Settings receiver dropped, stopping Sunshine monitor");
+                            return Ok(());
+                        }
+                    }
                     debug!("Sunshine process not found, retrying…");
-                    tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+                    tokio::time::sleep(retry_interval).await;
                 }
                 Err(e) => {
                     warn!("Error locating Sunshine process: {}", e);
-                    tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+                    tokio::time::sleep(retry_interval).await;
                 }
             }
         };
 
         info!(
-            "Sunshine process found: PID={}, base=0x{:016x}, path={:?}",
-            proc.pid, proc.module_base, proc.exe_path
+            "Sunshine process found: PID={}, base=0x{:016x}, path={:?}{}",
+            proc.pid,
+            proc.module_base,
+            proc.exe_path,
+            proc.wow64_machine
+                .map(|m| format!(" (WOW64, {})", machine_name(m)))
+                .unwrap_or_default()
         );
 
-        // ── Phase 2: detect version ─────────────────────────────────────────
-        let version = detect_sunshine_version(&proc.exe_path);
-        info!("Sunshine version: {}", version);
-
-        // ── Phase 3: download .dbg and find display_cursor RVA ──────────────
-        let rva = match download_and_parse_dbg(&version).await {
-            Ok(rva) => {
-                info!("display_cursor RVA: 0x{:08x}", rva);
-                rva
-            }
-            Err(e) => {
-                error!("Failed to resolve display_cursor from .dbg: {}", e);
-                // Cannot monitor without the symbol offset. Send default (false)
-                // and keep the task alive so it doesn't crash the agent.
-                // draw_cursor=false means "hide overlay" — safe default when
-                // we can't determine Sunshine's state.
-                let _ = tx
-                    .send(SunshineSettingsEvent { draw_cursor: false })
-                    .await;
-                warn!("Sunshine monitor running in fallback mode (draw_cursor=false, overlay hidden)");
-                loop {
-                    tokio::time::sleep(Duration::from_secs(3600)).await;
+        let rva_override = display_cursor_rva_override();
+        let symbols = match rva_override {
+            Some(rva) => SunshineSymbols {
+                display_cursor: rva,
+                stream_settings: Vec::new(),
+            },
+            None => match resolve_symbols(&proc, &mut resolved).await {
+                Ok(symbols) => symbols,
+                Err(e) => {
+                    error!("{:#}", e);
+                    return run_fallback(&tx).await;
                 }
-            }
+            },
         };
 
-        let target_addr = proc.module_base + rva as usize;
+        let target_addr = proc.module_base + symbols.display_cursor as usize;
         info!(
             "Will read display_cursor at 0x{:016x} (base 0x{:016x} + RVA 0x{:08x})",
-            target_addr, proc.module_base, rva
+            target_addr, proc.module_base, symbols.display_cursor
         );
 
+        // A hand-supplied RVA has no symbols to vouch for it; require one
+        // successful read of a plausible bool before trusting it.
+        if rva_override.is_some() {
+            match read_process_bytes::<1>(proc.pid, target_addr) {
+                Ok([b]) if b <= 1 => {}
+                Ok([b]) => {
+                    warn!(
+                        "{} points at byte 0x{:02x}, not a bool; ignoring it",
+                        DISPLAY_CURSOR_RVA_ENV, b
+                    );
+                    return run_fallback(&tx).await;
+                }
+                Err(e) => {
+                    warn!(
+                        "{} is not readable ({:#}); ignoring it",
+                        DISPLAY_CURSOR_RVA_ENV, e
+                    );
+                    return run_fallback(&tx).await;
+                }
+            }
+        }
+        if attached_before {
+            info!("Re-attached to Sunshine (PID {})", proc.pid);
+        }
+        attached_before = true;
+
         // ── Phase 4: poll loop ──────────────────────────────────────────────
         let mut consecutive_fails = 0u32;
-        let mut poll = interval(Duration::from_millis(POLL_INTERVAL_MS));
+        let mut poll = interval(poll_interval);
 
         loop {
             poll.tick().await;
@@ -645,19 +1236,39 @@ pub async fn run_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()>
                     //   Sunshine draws cursor → overlay NOT needed → draw_cursor=false
                     //   Sunshine hides cursor → overlay needed     → draw_cursor=true
                     let show_overlay = !val;
-                    if last_value != Some(show_overlay) {
-                        info!(
-                            "Sunshine display_cursor: {:?} → {} (overlay: {})",
-                            last_value.map(|v| v.to_string()).unwrap_or("(init)".into()),
-                            val,
-                            if show_overlay { "show" } else { "hide" }
-                        );
-                        last_value = Some(show_overlay);
-                        if tx
-                            .send(SunshineSettingsEvent { draw_cursor: show_overlay })
-                            .await
-                            .is_err()
-                        {
+                    let mut event = SunshineSettingsEvent {
+                        draw_cursor: Some(show_overlay),
+                        sunshine_running: true,
+                        ..Default::default()
+                    };
+                    // A failed read leaves the field unknown rather than
+                    // counting towards the process-exit heuristic.
+                    for (setting, rva) in &symbols.stream_settings {
+                        match read_process_u32(proc.pid, proc.module_base + *rva as usize) {
+                            Ok(v) => setting.apply(&mut event, v),
+                            Err(e) => debug!("Reading {:?} failed: {}", setting, e),
+                        }
+                    }
+                    if last_value.as_ref() != Some(&event) {
+                        let last_draw = last_value.as_ref().and_then(|v| v.draw_cursor);
+                        if last_draw != Some(show_overlay) {
+                            info!(
+                                "Sunshine display_cursor: {} → {} (overlay: {})",
+                                last_draw
+                                    .map(|v| (!v).to_string())
+                                    .unwrap_or("(unknown)".into()),
+                                val,
+                                if show_overlay { "show" } else { "hide" }
+                            );
+                        }
+                        if !symbols.stream_settings.is_empty() {
+                            debug!(
+                                "Sunshine stream: {}x{} @ {} fps, {} kbps",
+                                event.width, event.height, event.fps, event.bitrate_kbps
+                            );
+                        }
+                        last_value = Some(event.clone());
+                        if tx.send(event).await.is_err() {
                             info!("Settings receiver dropped, stopping Sunshine monitor");
                             return Ok(());
                         }
@@ -666,7 +1277,10 @@ pub async fn run_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()>
                 Err(e) => {
                     consecutive_fails += 1;
                     if consecutive_fails <= 3 {
-                        debug!("ReadProcessMemory failed (attempt {}): {}", consecutive_fails, e);
+                        debug!(
+                            "ReadProcessMemory failed (attempt {}): {}",
+                            consecutive_fails, e
+                        );
                     } else if consecutive_fails == 4 {
                         warn!(
                             "Sunshine process may have exited (consecutive read failures: {})",
@@ -684,6 +1298,48 @@ pub async fn run_monitor(tx: mpsc::Sender<SunshineSettingsEvent>) -> Result<()>
         }
 
         // Small delay before restarting discovery
-        tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+        tokio::time::sleep(retry_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_wide_growing_retries_truncated_reads() {
+        let mut capacities = Vec::new();
+        let result = read_wide_growing(|buf| {
+            capacities.push(buf.len());
+            if capacities.len() == 1 {
+                // Filled the whole buffer: looks truncated
+                buf.fill(u16::from(b'a'));
+                buf.len() as u32
+            } else {
+                buf[..5].fill(u16::from(b'b'));
+                5
+            }
+        });
+
+        assert_eq!(capacities, [MAX_PATH as usize, 2 * MAX_PATH as usize]);
+        assert_eq!(result, Some(vec![u16::from(b'b'); 5]));
+    }
+
+    #[test]
+    fn read_wide_growing_stops_at_the_cap() {
+        let mut calls = 0;
+        let result = read_wide_growing(|buf| {
+            calls += 1;
+            buf.len() as u32
+        })
+        .unwrap();
+
+        assert_eq!(result.len(), MAX_WIDE_PATH);
+        assert_eq!(calls, 8);
+    }
+
+    #[test]
+    fn read_wide_growing_reports_failure() {
+        assert_eq!(read_wide_growing(|_| 0), None);
     }
 }
@@ -0,0 +1,65 @@
+//! Synthetic RGBA images shared by the unit tests.
+
+pub(crate) fn solid(width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+    color.repeat((width * height) as usize)
+}
+
+/// Horizontal color ramp with alpha increasing top to bottom (never zero, so
+/// the color channels stay meaningful after a lossless encode).
+pub(crate) fn gradient(width: u32, height: u32) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            rgba.push((x * 255 / (width - 1)) as u8);
+            rgba.push(255 - (x * 255 / (width - 1)) as u8);
+            rgba.push((y * 255 / (height - 1)) as u8);
+            rgba.push(16 + (y * 239 / (height - 1)) as u8);
+        }
+    }
+    rgba
+}
+
+/// Decode a static WebP and return RGBA pixels (opaque images decode as RGB).
+pub(crate) fn decode_webp(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let image = webp::Decoder::new(data)
+        .decode()
+        .expect("WebP decode failed");
+    assert_eq!((image.width(), image.height()), (width, height));
+
+    if image.is_alpha() {
+        image.to_vec()
+    } else {
+        image
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect()
+    }
+}
+
+/// Maximum per-channel difference tolerated after a lossy round-trip.
+const TOLERANCE: u8 = 2;
+
+/// Assert that the corners and center pixel of two RGBA buffers match within
+/// `TOLERANCE`.
+pub(crate) fn assert_samples(expected: &[u8], actual: &[u8], width: u32, height: u32) {
+    assert_eq!(actual.len(), expected.len(), "buffer length");
+    let samples = [
+        (0, 0),
+        (width - 1, 0),
+        (0, height - 1),
+        (width - 1, height - 1),
+        (width / 2, height / 2),
+    ];
+    for (x, y) in samples {
+        let i = ((y * width + x) * 4) as usize;
+        let (e, a) = (&expected[i..i + 4], &actual[i..i + 4]);
+        assert!(
+            e.iter().zip(a).all(|(e, a)| e.abs_diff(*a) <= TOLERANCE),
+            "pixel ({}, {}): expected {:?}, got {:?}",
+            x,
+            y,
+            e,
+            a
+        );
+    }
+}
@@ -0,0 +1,3089 @@
+use anyhow::{bail, Context, Result};
+use axum::body::Body;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    response::{Html, IntoResponse},
+    routing::{get, post},
+    Json, Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use bytes::Bytes;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify};
+use tokio::time::{interval, sleep_until, Duration};
+use tower_http::cors::CorsLayer;
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
+
+use webrtc::api::APIBuilder;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::data_channel_state::RTCDataChannelState;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
+use webrtc::ice_transport::ice_credential_type::RTCIceCredentialType;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::{OnPeerConnectionStateChangeHdlrFn, RTCPeerConnection};
+
+use crate::client_config::ClientConfig;
+use crate::clipboard_sync::file_transfer::{IncomingTransfers, CHUNK_SIZE, MAX_FILE_SIZE};
+use crate::clipboard_sync::{
+    self, apply_to_clipboard, ClipboardContent, ClipboardEvent, ClipboardFile, ImageFormat,
+};
+use crate::cursor::{
+    cursor_message::Payload, ClientCacheHello, ClipboardAvailable, ClipboardContentType,
+    ClipboardData, ClipboardHistory, ClipboardHistoryRequest, ClipboardRequest,
+    ColorScheme as ProtoColorScheme, CursorAck, CursorMessage, CursorMode, CursorSignal,
+    FileTransferBegin, FileTransferChunk, FileTransferEnd, HelloData, MessageType, SettingsData,
+};
+use crate::cursor_capture::{
+    build_external_cursor, cache_len, check_cursor_dimensions, create_hide_message,
+    create_position_message, create_scaled_cursor_message, cursor_codecs, decode_cursor_rgba,
+    dpi_scale, get_cached_cursor, get_last_cursor_id, inject_cursor, negotiate_cursor_codec,
+    request_recapture, CachedCursor, CursorCodec, CursorEvent, CursorQuality, SeatId, DEFAULT_SEAT,
+};
+use crate::metrics;
+use crate::sunshine_monitor::{ColorScheme, SunshineSettingsEvent};
+use crate::AgentEvent;
+
+#[cfg(feature = "webtransport")]
+mod webtransport;
+
+#[derive(Deserialize)]
+struct OfferRequest {
+    sdp: String,
+    #[serde(rename = "type")]
+    sdp_type: String,
+    /// The client trickles ICE candidates through `POST /ice/{session_id}`,
+    /// so the answer is returned without waiting for gathering to finish
+    #[serde(default)]
+    trickle: bool,
+}
+
+#[derive(Serialize)]
+struct AnswerResponse {
+    sdp: String,
+    #[serde(rename = "type")]
+    sdp_type: String,
+    /// Trickle ICE session for `POST /ice/{session_id}` (trickle offers only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+}
+
+/// Error body of `POST /offer`: a stable `error` code (`invalid_request`,
+/// `invalid_sdp`, `unsupported_sdp`, `unauthorized` or `internal_error`)
+/// for clients to act on, and a human-readable `message`.  Internal failures
+/// only get a generic message; the detail goes to the server log.
+#[derive(Serialize)]
+struct OfferError {
+    #[serde(skip)]
+    status: StatusCode,
+    error: &'static str,
+    message: String,
+}
+
+impl OfferError {
+    fn client(status: StatusCode, error: &'static str, message: impl Into<String>) -> Self {
+        OfferError {
+            status,
+            error,
+            message: message.into(),
+        }
+    }
+
+    /// A failure on our side: log `detail`, tell the client nothing specific.
+    fn internal(context: &str, detail: impl std::fmt::Display) -> Self {
+        error!("Offer failed, {}: {}", context, detail);
+        OfferError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: "internal_error",
+            message: "The server could not set up the connection".to_string(),
+        }
+    }
+
+    /// Classify a `set_remote_description` failure: errors about the offer's
+    /// content are the client's, anything else is ours.
+    fn remote_description(e: webrtc::Error) -> Self {
+        use webrtc::Error::*;
+        match e {
+            Sdp(_) => Self::client(
+                StatusCode::BAD_REQUEST,
+                "invalid_sdp",
+                format!("Invalid SDP offer: {}", e),
+            ),
+            ErrSessionDescriptionNoFingerprint
+            | ErrSessionDescriptionInvalidFingerprint
+            | ErrSessionDescriptionConflictingFingerprints
+            | ErrSessionDescriptionMissingIceUfrag
+            | ErrSessionDescriptionMissingIcePwd
+            | ErrSessionDescriptionConflictingIceUfrag
+            | ErrSessionDescriptionConflictingIcePwd
+            | ErrPeerConnRemoteDescriptionWithoutMidValue
+            | ErrIncorrectSDPSemantics => Self::client(
+                StatusCode::BAD_REQUEST,
+                "unsupported_sdp",
+                format!("Unsupported SDP offer: {}", e),
+            ),
+            e => Self::internal("set_remote_description", e),
+        }
+    }
+}
+
+impl IntoResponse for OfferError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+/// Body of `POST /ice/{session_id}`
+#[derive(Deserialize)]
+struct IceRequest {
+    /// Client candidates gathered since its last post
+    #[serde(default)]
+    candidates: Vec<RTCIceCandidateInit>,
+}
+
+/// Response to `POST /ice/{session_id}`
+#[derive(Serialize)]
+struct IceResponse {
+    /// Server candidates gathered since the client last polled
+    candidates: Vec<RTCIceCandidateInit>,
+    /// Server-side gathering has finished; no more candidates will follow
+    complete: bool,
+}
+
+/// Body of `GET /health`
+#[derive(Serialize)]
+struct HealthStatus {
+    status: &'static str,
+    uptime_secs: u64,
+}
+
+/// Diagnostic snapshot returned by `GET /admin/status`
+#[derive(Serialize)]
+struct AdminStatus {
+    cursor: Option<CursorStatus>,
+    cursor_hidden: bool,
+    dpi_scale: f32,
+    /// Last `draw_cursor` value broadcast by the Sunshine monitor (`null` if
+    /// none yet or unknown)
+    draw_cursor: Option<bool>,
+    /// Whether the Sunshine monitor last found Sunshine (`null` if it has not
+    /// reported yet)
+    sunshine_running: Option<bool>,
+    cursor_cache_size: usize,
+    connected_clients: usize,
+    /// Peer connections currently held open by the server
+    peer_connections: usize,
+    /// Times a client's send buffer crossed the high-water mark and cursor
+    /// frames were paused
+    backpressure_engaged: u64,
+}
+
+#[derive(Serialize)]
+struct CursorStatus {
+    id: String,
+    width: u32,
+    height: u32,
+    hotspot_x: i32,
+    hotspot_y: i32,
+    is_animated: bool,
+    frame_count: u32,
+    frame_delay_ms: u32,
+    webp_bytes: usize,
+}
+
+/// Body of `POST /admin/cursor`
+#[derive(Deserialize)]
+struct InjectCursorRequest {
+    /// Base64-encoded PNG or WebP (static or animated)
+    image: String,
+    hotspot_x: i32,
+    hotspot_y: i32,
+    /// Optional expected dimensions; rejected if they don't match the image
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct InjectCursorResponse {
+    cursor_id: String,
+    width: u32,
+    height: u32,
+    is_animated: bool,
+}
+
+struct AppState {
+    tx_broadcast: Arc<broadcast::Sender<AgentEvent>>,
+    api: webrtc::api::API,
+    /// STUN/TURN servers from `ICE_SERVERS`; empty means host candidates only
+    ice_servers: Vec<RTCIceServer>,
+    /// Live peer connections by id; entries are dropped once the connection
+    /// fails, disconnects or closes
+    peer_connections: Mutex<HashMap<u64, Arc<RTCPeerConnection>>>,
+    next_peer_id: AtomicU64,
+    /// Most recent settings event seen on the broadcast path
+    last_settings: Mutex<Option<SunshineSettingsEvent>>,
+    /// Most recent host clipboard event, served to `ClipboardRequest` pulls
+    last_clipboard: Mutex<Option<ClipboardEvent>>,
+    /// `CLIPBOARD_NOTIFY_ONLY`: announce clipboard changes with
+    /// `ClipboardAvailable` instead of pushing the content
+    clipboard_notify_only: bool,
+    /// `AUTO_SUPPRESS_ON_DRAW_CURSOR`: stop sending cursor images while
+    /// Sunshine draws the cursor into the video itself
+    auto_suppress_on_draw_cursor: bool,
+    /// Pause cursor sends once a channel buffers more than this (`DC_BUFFER_HIGH_WATER`)
+    buffer_high_water: usize,
+    /// Resume once the buffer drains below this (`DC_BUFFER_LOW_WATER`)
+    buffer_low_water: usize,
+    /// Per-client cursor image/hide messages per second (`MAX_CURSOR_MSGS_PER_SEC`, 0 = unlimited)
+    max_cursor_msgs_per_sec: usize,
+    /// Cursor events superseded while a client was over its rate limit
+    cursor_events_coalesced: AtomicU64,
+    /// Number of times backpressure engaged, across all clients
+    backpressure_engaged: AtomicU64,
+    /// Number of times a client's sender fell behind the broadcast channel
+    broadcast_lagged: AtomicU64,
+    /// When the server started (for `/health` uptime)
+    started_at: Instant,
+    /// Cursor events (image, hide, position) put on the broadcast channel
+    cursor_events_broadcast: AtomicU64,
+    /// Clipboard updates sent to clients (per client)
+    clipboard_sent: AtomicU64,
+    /// Clipboard updates received from clients
+    clipboard_received: AtomicU64,
+    /// Bearer token for `/admin/*` routes (`ADMIN_TOKEN`); admin routes are disabled when unset
+    admin_token: Option<String>,
+    /// Bearer token required on `/offer` (`AUTH_TOKEN`); signaling is open when unset
+    auth_token: Option<String>,
+    /// Latest heartbeat round-trip time per peer connection id, in ms
+    client_rtt_ms: Mutex<HashMap<u64, u64>>,
+    /// Trickle ICE sessions by session id, dropped with their peer connection
+    trickle_sessions: Mutex<HashMap<String, TrickleSession>>,
+}
+
+/// A peer connection negotiated with trickle ICE.
+struct TrickleSession {
+    peer_id: u64,
+    peer_connection: Arc<RTCPeerConnection>,
+    local: Arc<Mutex<LocalCandidates>>,
+}
+
+/// Server candidates not yet handed to the client.
+#[derive(Default)]
+struct LocalCandidates {
+    pending: Vec<RTCIceCandidateInit>,
+    complete: bool,
+}
+
+/// Per-client state shared between sender task and message handler
+struct ClientState {
+    dpr: f32,
+    /// Cursor size requested with `preferred_cursor_size`; `None` sends
+    /// cursors at capture size
+    preferred_cursor_size: Option<u32>,
+    /// Image codec for `CursorData`, negotiated from `cursor_codecs`
+    cursor_codec: CursorCodec,
+    /// `Preview` caps cursor size for low-bandwidth clients (`cursor_quality`)
+    cursor_quality: CursorQuality,
+    /// Cursors the client has cached, so a `CursorSignal` is enough
+    sent_cursor_ids: HashSet<(SeatId, String)>,
+    /// The client acks `CursorData`; until it does, cursors sent in full wait
+    /// in `pending_cursor_acks` and are re-sent rather than signalled
+    cursor_acks: bool,
+    /// Cursors sent in full but not yet acked
+    pending_cursor_acks: HashSet<(SeatId, String)>,
+    /// blake3 hash of the last clipboard payload sent to this client (dedup)
+    last_clipboard_hash: Option<String>,
+    /// When this client last triggered a live cursor re-capture (rate limit)
+    last_recapture: Option<Instant>,
+    /// Reassembly state for files this client is sending us
+    incoming_files: IncomingTransfers,
+    /// `Hidden` while the client has asked not to receive cursor images
+    cursor_mode: CursorMode,
+    /// Set while Sunshine draws the cursor into the video and
+    /// `AUTO_SUPPRESS_ON_DRAW_CURSOR` is on; cursor images are held back as
+    /// in `Hidden` mode
+    draw_cursor_suppressed: bool,
+    /// Latest heartbeat round-trip time, once the client has echoed one
+    rtt_ms: Option<u64>,
+    /// Rate limit for cursor image/hide messages (`MAX_CURSOR_MSGS_PER_SEC`)
+    cursor_budget: TokenBucket,
+    /// Signalled when a config change needs the current cursor re-sent;
+    /// the sender task sends it within `cursor_budget`
+    resend_cursor: Arc<Notify>,
+}
+
+impl ClientState {
+    fn new(max_cursor_msgs_per_sec: usize) -> Self {
+        ClientState {
+            dpr: 1.0,
+            preferred_cursor_size: None,
+            cursor_codec: CursorCodec::Webp,
+            cursor_quality: CursorQuality::Full,
+            sent_cursor_ids: HashSet::new(),
+            cursor_acks: false,
+            pending_cursor_acks: HashSet::new(),
+            last_clipboard_hash: None,
+            last_recapture: None,
+            incoming_files: IncomingTransfers::default(),
+            cursor_mode: CursorMode::Bitmap,
+            draw_cursor_suppressed: false,
+            rtt_ms: None,
+            cursor_budget: TokenBucket::new(max_cursor_msgs_per_sec),
+            resend_cursor: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Record that `cursor_id` went out as full `CursorData` for `seat`.
+    fn cursor_data_sent(&mut self, seat: SeatId, cursor_id: String) {
+        if self.cursor_acks {
+            self.pending_cursor_acks.insert((seat, cursor_id));
+        } else {
+            self.sent_cursor_ids.insert((seat, cursor_id));
+        }
+    }
+
+    /// Forget every cursor the client has, e.g. after its scale changed.
+    fn forget_sent_cursors(&mut self) {
+        self.sent_cursor_ids.clear();
+        self.pending_cursor_acks.clear();
+    }
+}
+
+/// Token bucket allowing `rate` events per second, with up to one second's
+/// worth in a burst.  A zero rate never limits.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: usize) -> Self {
+        TokenBucket {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+
+    /// Take a token if one is available.
+    fn try_take(&mut self) -> bool {
+        if self.rate == 0.0 {
+            return true;
+        }
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Time until the next token is available.
+    fn wait_time(&mut self) -> Duration {
+        if self.rate == 0.0 {
+            return Duration::ZERO;
+        }
+        self.refill();
+        Duration::from_secs_f64(((1.0 - self.tokens) / self.rate).max(0.0))
+    }
+}
+
+/// A client's message channel: a WebRTC data channel or, with the
+/// `webtransport` feature, a WebTransport stream.  Each `send` carries one
+/// encoded `CursorMessage`.
+trait ClientChannel: Send + Sync + 'static {
+    fn send(&self, data: &Bytes) -> impl Future<Output = Result<()>> + Send;
+
+    /// Bytes queued on the channel but not yet sent.
+    fn buffered_amount(&self) -> impl Future<Output = usize> + Send;
+
+    fn is_open(&self) -> bool;
+}
+
+impl ClientChannel for RTCDataChannel {
+    async fn send(&self, data: &Bytes) -> Result<()> {
+        RTCDataChannel::send(self, data).await?;
+        Ok(())
+    }
+
+    async fn buffered_amount(&self) -> usize {
+        RTCDataChannel::buffered_amount(self).await
+    }
+
+    fn is_open(&self) -> bool {
+        self.ready_state() == RTCDataChannelState::Open
+    }
+}
+
+/// Which transports clients can connect over (`TRANSPORT`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransportMode {
+    WebRtc,
+    WebTransport,
+    Both,
+}
+
+impl TransportMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "webrtc" => Some(TransportMode::WebRtc),
+            "webtransport" => Some(TransportMode::WebTransport),
+            "both" => Some(TransportMode::Both),
+            _ => None,
+        }
+    }
+
+    fn webrtc(self) -> bool {
+        self != TransportMode::WebTransport
+    }
+
+    fn webtransport(self) -> bool {
+        self != TransportMode::WebRtc
+    }
+}
+
+/// What a client data channel carries, from its label.
+///
+/// A client may open one channel (any label) for everything, or split the
+/// traffic so large clipboard and file payloads don't queue ahead of cursor
+/// frames: `"cursor"` (typically unordered), `"clipboard"` and `"files"`
+/// (ordered, reliable).  Anything but `"clipboard"` and `"files"` is treated
+/// as the cursor channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelRole {
+    Cursor,
+    Clipboard,
+    Files,
+}
+
+impl ChannelRole {
+    fn from_label(label: &str) -> Self {
+        match label {
+            "clipboard" => ChannelRole::Clipboard,
+            "files" => ChannelRole::Files,
+            _ => ChannelRole::Cursor,
+        }
+    }
+}
+
+/// A peer's open clipboard and file channels.  Traffic for a missing channel
+/// falls back to the next one down: files → clipboard → cursor.
+struct PeerChannels<C = RTCDataChannel> {
+    clipboard: Option<Arc<C>>,
+    files: Option<Arc<C>>,
+}
+
+impl<C> Default for PeerChannels<C> {
+    fn default() -> Self {
+        PeerChannels {
+            clipboard: None,
+            files: None,
+        }
+    }
+}
+
+impl<C> PeerChannels<C> {
+    /// Channel for clipboard messages.
+    fn clipboard_or(&self, cursor: &Arc<C>) -> Arc<C> {
+        self.clipboard.clone().unwrap_or_else(|| cursor.clone())
+    }
+
+    /// Channel for file transfer messages.
+    fn files_or(&self, cursor: &Arc<C>) -> Arc<C> {
+        self.files
+            .clone()
+            .unwrap_or_else(|| self.clipboard_or(cursor))
+    }
+
+    fn slot(&mut self, role: ChannelRole) -> Option<&mut Option<Arc<C>>> {
+        match role {
+            ChannelRole::Cursor => None,
+            ChannelRole::Clipboard => Some(&mut self.clipboard),
+            ChannelRole::Files => Some(&mut self.files),
+        }
+    }
+}
+
+/// Default per-client event queue length (`BROADCAST_CAPACITY`).  A client
+/// falling further behind than this misses events and is resynced.
+const DEFAULT_BROADCAST_CAPACITY: usize = 256;
+
+/// Default data-channel high-water mark: pause cursor sends above 1 MiB buffered.
+const DEFAULT_BUFFER_HIGH_WATER: usize = 1024 * 1024;
+/// Default low-water mark: resume once the buffer drains below 256 KiB.
+const DEFAULT_BUFFER_LOW_WATER: usize = 256 * 1024;
+
+/// An RTT this many times the previous sample (and above
+/// [`RTT_SPIKE_MIN_MS`]) is logged as a spike.
+const RTT_SPIKE_FACTOR: u64 = 3;
+/// RTTs below this are never reported as spikes.
+const RTT_SPIKE_MIN_MS: u64 = 250;
+
+/// Most cursor ids accepted from one `ClientCacheHello`.
+const MAX_KNOWN_CURSOR_IDS: usize = 1024;
+
+/// Version of `proto/cursor.proto`; bump on incompatible changes (and in the
+/// comment at the top of the file).  Served on `/proto` and in `HelloData`.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Default per-client cap on cursor image/hide messages per second
+/// (`MAX_CURSOR_MSGS_PER_SEC`, 0 = unlimited).
+const DEFAULT_MAX_CURSOR_MSGS_PER_SEC: usize = 120;
+
+/// Minimum interval between `RecaptureLive` requests from a single client.
+const RECAPTURE_MIN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A file transfer is abandoned if the channel buffer stays above the
+/// low-water mark this long.
+const FILE_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Run WebRTC signaling + data channel server
+pub async fn run_webrtc_server(
+    bind_addr: String,
+    mut rx: mpsc::Receiver<AgentEvent>,
+) -> Result<()> {
+    let broadcast_capacity = env_usize("BROADCAST_CAPACITY", DEFAULT_BROADCAST_CAPACITY).max(16);
+    let (tx_broadcast, _) = broadcast::channel::<AgentEvent>(broadcast_capacity);
+    let tx_broadcast = Arc::new(tx_broadcast);
+
+    // Create WebRTC API (data-channel-only, no media codecs needed)
+    let api = APIBuilder::new().build();
+
+    let addr: SocketAddr = bind_addr.parse()?;
+
+    let transport = match std::env::var("TRANSPORT") {
+        Ok(v) => TransportMode::parse(&v)
+            .with_context(|| format!("TRANSPORT={:?}, expected webrtc, webtransport or both", v))?,
+        Err(_) => TransportMode::WebRtc,
+    };
+    if transport.webtransport() && !cfg!(feature = "webtransport") {
+        bail!("WebTransport needs a build with the webtransport feature");
+    }
+
+    let admin_token = std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty());
+    if admin_token.is_none() {
+        info!("ADMIN_TOKEN not set, /admin routes disabled");
+    }
+
+    let auth_token = std::env::var("AUTH_TOKEN").ok().filter(|t| !t.is_empty());
+    if auth_token.is_some() {
+        info!("AUTH_TOKEN set, /offer requires a bearer token");
+    } else {
+        warn!(
+            "AUTH_TOKEN not set, /offer accepts offers from anyone who can reach {}",
+            addr
+        );
+    }
+
+    let ice_servers = std::env::var("ICE_SERVERS")
+        .map(|v| parse_ice_servers(&v))
+        .unwrap_or_default();
+    if ice_servers.is_empty() {
+        info!("ICE_SERVERS not set, using host candidates only (LAN)");
+    } else {
+        for server in &ice_servers {
+            if server.username.is_empty() {
+                info!("ICE server: {}", server.urls[0]);
+            } else {
+                info!("ICE server: {} (user {})", server.urls[0], server.username);
+            }
+        }
+    }
+
+    let tls_files = match (env_path("TLS_CERT"), env_path("TLS_KEY")) {
+        (Some(cert), Some(key)) => Some((cert, key)),
+        (None, None) => None,
+        _ => bail!("TLS_CERT and TLS_KEY must be set together"),
+    };
+    let tls_config = match &tls_files {
+        Some((cert, key)) => {
+            // rustls needs a process-wide crypto provider; an earlier
+            // installation (e.g. by a restarted server) is fine.
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let config = RustlsConfig::from_pem_file(cert, key)
+                .await
+                .with_context(|| format!("load TLS certificate {:?} / key {:?}", cert, key))?;
+            info!("TLS enabled (certificate {:?})", cert);
+            Some(config)
+        }
+        None => None,
+    };
+
+    // WebTransport shares BIND_ADDR (over UDP) and the HTTP server's
+    // certificate; browsers only open sessions over TLS.
+    #[cfg(feature = "webtransport")]
+    let webtransport_endpoint = match &tls_files {
+        Some((cert, key)) if transport.webtransport() => Some(webtransport::bind(addr, cert, key)?),
+        _ if transport.webtransport() => bail!("WebTransport needs TLS_CERT and TLS_KEY"),
+        _ => None,
+    };
+
+    let clipboard_notify_only = crate::env_flag("CLIPBOARD_NOTIFY_ONLY", false);
+    if clipboard_notify_only {
+        info!("CLIPBOARD_NOTIFY_ONLY set, clients must pull clipboard content on demand");
+    }
+
+    let auto_suppress_on_draw_cursor = crate::env_flag("AUTO_SUPPRESS_ON_DRAW_CURSOR", false);
+    if auto_suppress_on_draw_cursor {
+        info!(
+            "AUTO_SUPPRESS_ON_DRAW_CURSOR set, cursor images pause while Sunshine draws the cursor"
+        );
+    }
+
+    let buffer_high_water = env_usize("DC_BUFFER_HIGH_WATER", DEFAULT_BUFFER_HIGH_WATER);
+    let mut buffer_low_water = env_usize("DC_BUFFER_LOW_WATER", DEFAULT_BUFFER_LOW_WATER);
+    if buffer_low_water >= buffer_high_water {
+        warn!(
+            "DC_BUFFER_LOW_WATER ({}) must be below DC_BUFFER_HIGH_WATER ({}), using {}",
+            buffer_low_water,
+            buffer_high_water,
+            buffer_high_water / 4
+        );
+        buffer_low_water = buffer_high_water / 4;
+    }
+
+    let max_cursor_msgs_per_sec =
+        env_usize("MAX_CURSOR_MSGS_PER_SEC", DEFAULT_MAX_CURSOR_MSGS_PER_SEC);
+    if max_cursor_msgs_per_sec == 0 {
+        info!("MAX_CURSOR_MSGS_PER_SEC=0, cursor updates are not rate limited");
+    }
+
+    let state = Arc::new(AppState {
+        tx_broadcast: tx_broadcast.clone(),
+        api,
+        ice_servers,
+        peer_connections: Mutex::new(HashMap::new()),
+        next_peer_id: AtomicU64::new(0),
+        last_settings: Mutex::new(None),
+        last_clipboard: Mutex::new(None),
+        clipboard_notify_only,
+        auto_suppress_on_draw_cursor,
+        buffer_high_water,
+        buffer_low_water,
+        max_cursor_msgs_per_sec,
+        cursor_events_coalesced: AtomicU64::new(0),
+        backpressure_engaged: AtomicU64::new(0),
+        broadcast_lagged: AtomicU64::new(0),
+        started_at: Instant::now(),
+        cursor_events_broadcast: AtomicU64::new(0),
+        clipboard_sent: AtomicU64::new(0),
+        clipboard_received: AtomicU64::new(0),
+        admin_token,
+        auth_token,
+        client_rtt_ms: Mutex::new(HashMap::new()),
+        trickle_sessions: Mutex::new(HashMap::new()),
+    });
+
+    let cursor_debounce = Duration::from_millis(env_usize("CURSOR_DEBOUNCE_MS", 0) as u64);
+    if !cursor_debounce.is_zero() {
+        info!("Debouncing cursor changes by {:?}", cursor_debounce);
+    }
+
+    // Broadcast task: forward cursor events from capture to all clients.
+    // With CURSOR_DEBOUNCE_MS set, a cursor change within the window of the
+    // previous one is held and only the latest per seat goes out when it
+    // closes; a hide drops its seat's held change, positions and other
+    // events pass straight through.
+    let tx_broadcast_clone = tx_broadcast.clone();
+    let state_bcast = state.clone();
+    tokio::spawn(async move {
+        let mut last_change: Option<tokio::time::Instant> = None;
+        let mut held: HashMap<SeatId, AgentEvent> = HashMap::new();
+        loop {
+            let release_at = last_change.map(|t| t + cursor_debounce);
+            let event = tokio::select! {
+                event = rx.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = sleep_until(release_at.unwrap_or_else(tokio::time::Instant::now)),
+                    if !held.is_empty() =>
+                {
+                    last_change = Some(tokio::time::Instant::now());
+                    for (_, event) in held.drain() {
+                        broadcast_event(&state_bcast, &tx_broadcast_clone, event).await;
+                    }
+                    continue;
+                }
+            };
+            match &event {
+                AgentEvent::Cursor(ev @ CursorEvent::CursorChanged { .. })
+                    if !cursor_debounce.is_zero() =>
+                {
+                    let now = tokio::time::Instant::now();
+                    if !held.is_empty() || release_at.is_some_and(|t| now < t) {
+                        held.insert(ev.seat(), event);
+                        continue;
+                    }
+                    last_change = Some(now);
+                }
+                AgentEvent::Cursor(CursorEvent::CursorHidden { seat }) => {
+                    held.remove(seat);
+                }
+                _ => {}
+            }
+            broadcast_event(&state_bcast, &tx_broadcast_clone, event).await;
+        }
+    });
+
+    #[cfg(feature = "webtransport")]
+    let webtransport_server = webtransport_endpoint
+        .map(|endpoint| tokio::spawn(webtransport::serve(endpoint, state.clone())));
+
+    // HTTP signaling server with CORS
+    let mut app = Router::new()
+        .route("/", get(serve_test_page))
+        .route("/proto", get(serve_proto))
+        .route("/health", get(handle_health))
+        .route("/metrics", get(handle_metrics))
+        .route("/cursor.png", get(handle_cursor_png))
+        .route("/cursor.webp", get(handle_cursor_webp));
+    if transport.webrtc() {
+        app = app
+            .route("/offer", post(handle_offer))
+            .route("/ice/:session_id", post(handle_ice));
+    }
+    let app = app
+        .route("/admin/status", get(handle_admin_status))
+        .route("/admin/cursor", post(handle_admin_cursor))
+        .layer(CorsLayer::permissive())
+        .with_state(state.clone());
+
+    let server_name = if transport.webrtc() {
+        "WebRTC signaling server"
+    } else {
+        "HTTP server"
+    };
+    if let Some(tls_config) = tls_config {
+        info!("{} listening on: https://{}", server_name, addr);
+
+        let handle = axum_server::Handle::new();
+        let shutdown_handle = handle.clone();
+        tokio::spawn(async move {
+            crate::shutdown::requested().await;
+            shutdown_handle.graceful_shutdown(None);
+        });
+        axum_server::bind_rustls(addr, tls_config)
+            .handle(handle)
+            .serve(app.into_make_service())
+            .await?;
+    } else {
+        info!("{} listening on: {}", server_name, addr);
+
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        axum::serve(listener, app)
+            .with_graceful_shutdown(crate::shutdown::requested())
+            .await?;
+    }
+
+    // Stop accepting offers first, then close every open peer connection
+    // (which closes its data channels and ends the sender tasks).
+    let peers: Vec<_> = state.peer_connections.lock().await.drain().collect();
+    info!(
+        "Signaling server stopped, closing {} peer connection(s)",
+        peers.len()
+    );
+    for (id, pc) in peers {
+        if let Err(e) = pc.close().await {
+            debug!("Error closing peer connection {}: {}", id, e);
+        }
+    }
+    #[cfg(feature = "webtransport")]
+    if let Some(server) = webtransport_server {
+        let _ = server.await;
+    }
+
+    Ok(())
+}
+
+/// Record `event` in the server state and send it to every client.
+async fn broadcast_event(state: &AppState, tx: &broadcast::Sender<AgentEvent>, event: AgentEvent) {
+    if !matches!(event, AgentEvent::Cursor(CursorEvent::Moved { .. })) {
+        debug!("Broadcasting cursor event: {:?}", event);
+    }
+    match &event {
+        AgentEvent::Settings(ev) => {
+            *state.last_settings.lock().await = Some(ev.clone());
+        }
+        AgentEvent::Clipboard(ev) => {
+            *state.last_clipboard.lock().await = Some(ev.clone());
+        }
+        AgentEvent::Cursor(_) => {
+            state
+                .cursor_events_broadcast
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    let _ = tx.send(event);
+}
+
+/// Non-empty path from an environment variable.
+fn env_path(name: &str) -> Option<std::path::PathBuf> {
+    std::env::var_os(name)
+        .filter(|v| !v.is_empty())
+        .map(Into::into)
+}
+
+/// Serve built-in test client page
+async fn serve_test_page() -> Html<&'static str> {
+    Html(include_str!("../../test-client.html"))
+}
+
+/// Serve the raw Protobuf schema (single source of truth, consumed by the JS client)
+async fn serve_proto() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header("X-Schema-Version", SCHEMA_VERSION.to_string())
+        .body(Body::from(include_str!("../../proto/cursor.proto")))
+        .unwrap()
+}
+
+/// `GET /cursor.png`: the current cursor decoded to PNG (first frame if
+/// animated), or 204 while the cursor is hidden.  Requires `AUTH_TOKEN` when set.
+async fn handle_cursor_png(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    current_cursor_image(&state, &headers, "image/png", |cached| {
+        let rgba = decode_cursor_rgba(cached)?;
+        clipboard_sync::encode_rgba_to_png(&rgba, cached.width, cached.height)
+    })
+}
+
+/// `GET /cursor.webp`: the current cursor's cached WebP as sent to clients.
+async fn handle_cursor_webp(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    current_cursor_image(&state, &headers, "image/webp", |cached| {
+        Ok(cached.webp_data.clone())
+    })
+}
+
+/// Shared body of the cursor snapshot endpoints.
+fn current_cursor_image(
+    state: &AppState,
+    headers: &HeaderMap,
+    content_type: &'static str,
+    encode: impl FnOnce(&CachedCursor) -> Result<Vec<u8>>,
+) -> Response<Body> {
+    let status_only = |status: StatusCode| {
+        Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    if let Some(token) = state.auth_token.as_deref() {
+        if !is_bearer_authorized(headers, token) {
+            return status_only(StatusCode::UNAUTHORIZED);
+        }
+    }
+    let Some(id) = get_last_cursor_id() else {
+        return status_only(StatusCode::NO_CONTENT);
+    };
+    let Some(cached) = get_cached_cursor(&id) else {
+        return status_only(StatusCode::NOT_FOUND);
+    };
+    match encode(&cached) {
+        Ok(image) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CACHE_CONTROL, "no-store")
+            .header("X-Cursor-Id", cached.id.as_str())
+            .header(
+                "X-Cursor-Hotspot",
+                format!("{},{}", cached.hotspot_x, cached.hotspot_y),
+            )
+            .body(Body::from(image))
+            .unwrap(),
+        Err(e) => {
+            warn!("Cannot render cursor {} as {}: {}", id, content_type, e);
+            status_only(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Liveness probe
+async fn handle_health(State(state): State<Arc<AppState>>) -> Json<HealthStatus> {
+    Json(HealthStatus {
+        status: "ok",
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    })
+}
+
+/// Basic counters in Prometheus text exposition format
+async fn handle_metrics(State(state): State<Arc<AppState>>) -> Response<Body> {
+    use std::fmt::Write;
+
+    let (draw_cursor, sunshine_running) = match state.last_settings.lock().await.as_ref() {
+        Some(s) => (s.draw_cursor, Some(s.sunshine_running)),
+        None => (None, None),
+    };
+    let mut metrics: Vec<(&str, &str, &str, u64)> = vec![
+        (
+            "deragabu_uptime_seconds",
+            "gauge",
+            "Seconds since the signaling server started",
+            state.started_at.elapsed().as_secs(),
+        ),
+        (
+            "deragabu_connected_clients",
+            "gauge",
+            "Data channels with an active sender",
+            metrics::connected_clients() as u64,
+        ),
+        (
+            "deragabu_cursor_cache_entries",
+            "gauge",
+            "Cursors held in the cursor cache",
+            cache_len() as u64,
+        ),
+        (
+            "deragabu_cursor_events_broadcast_total",
+            "counter",
+            "Cursor events broadcast to clients",
+            state.cursor_events_broadcast.load(Ordering::Relaxed),
+        ),
+        (
+            "deragabu_clipboard_sent_total",
+            "counter",
+            "Clipboard updates sent to clients",
+            state.clipboard_sent.load(Ordering::Relaxed),
+        ),
+        (
+            "deragabu_clipboard_received_total",
+            "counter",
+            "Clipboard updates received from clients",
+            state.clipboard_received.load(Ordering::Relaxed),
+        ),
+        (
+            "deragabu_backpressure_engaged_total",
+            "counter",
+            "Times a client send buffer crossed the high-water mark",
+            state.backpressure_engaged.load(Ordering::Relaxed),
+        ),
+        (
+            "deragabu_broadcast_lagged_total",
+            "counter",
+            "Times a client fell behind the event broadcast and was resynced",
+            state.broadcast_lagged.load(Ordering::Relaxed),
+        ),
+        (
+            "deragabu_cursor_events_coalesced_total",
+            "counter",
+            "Cursor updates superseded while a client was over MAX_CURSOR_MSGS_PER_SEC",
+            state.cursor_events_coalesced.load(Ordering::Relaxed),
+        ),
+    ];
+    // Omitted until a client has answered a heartbeat
+    if let Some(rtt) = state.client_rtt_ms.lock().await.values().max() {
+        metrics.push((
+            "deragabu_client_rtt_max_ms",
+            "gauge",
+            "Highest latest heartbeat round-trip time across clients",
+            *rtt,
+        ));
+    }
+    // Omitted until the Sunshine monitor has reported
+    if let Some(running) = sunshine_running {
+        metrics.push((
+            "deragabu_sunshine_running",
+            "gauge",
+            "Whether the Sunshine monitor found Sunshine",
+            u64::from(running),
+        ));
+    }
+    // Also omitted while Sunshine's setting is unknown
+    if let Some(draw) = draw_cursor {
+        metrics.push((
+            "deragabu_draw_cursor",
+            "gauge",
+            "Whether clients should draw the overlay cursor",
+            u64::from(draw),
+        ));
+    }
+
+    let mut body = String::new();
+    for (name, kind, help, value) in metrics {
+        let _ = writeln!(
+            body,
+            "# HELP {} {}\n# TYPE {} {}\n{} {}",
+            name, help, name, kind, name, value
+        );
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Gate an `/admin/*` request on the configured `ADMIN_TOKEN`.
+/// Admin routes answer 404 when no token is configured.
+fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let token = state.admin_token.as_deref().ok_or(StatusCode::NOT_FOUND)?;
+    if !is_bearer_authorized(headers, token) {
+        warn!("Rejected unauthorized admin request");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+/// Return a diagnostic snapshot of the agent state (requires `ADMIN_TOKEN`)
+async fn handle_admin_status(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<AdminStatus>, StatusCode> {
+    require_admin(&state, &headers)?;
+
+    let last_id = get_last_cursor_id();
+    let cursor = last_id
+        .as_deref()
+        .and_then(get_cached_cursor)
+        .map(|c| CursorStatus {
+            webp_bytes: c.webp_data.len(),
+            id: c.id,
+            width: c.width,
+            height: c.height,
+            hotspot_x: c.hotspot_x,
+            hotspot_y: c.hotspot_y,
+            is_animated: c.is_animated,
+            frame_count: c.frame_count,
+            frame_delay_ms: c.frame_delay_ms,
+        });
+    let (draw_cursor, sunshine_running) = match state.last_settings.lock().await.as_ref() {
+        Some(s) => (s.draw_cursor, Some(s.sunshine_running)),
+        None => (None, None),
+    };
+
+    Ok(Json(AdminStatus {
+        cursor,
+        cursor_hidden: last_id.is_none(),
+        dpi_scale: dpi_scale(),
+        draw_cursor,
+        sunshine_running,
+        cursor_cache_size: cache_len(),
+        connected_clients: metrics::connected_clients(),
+        peer_connections: state.peer_connections.lock().await.len(),
+        backpressure_engaged: state.backpressure_engaged.load(Ordering::Relaxed),
+    }))
+}
+
+/// Inject a synthetic cursor and broadcast it to all clients (requires `ADMIN_TOKEN`)
+async fn handle_admin_cursor(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<InjectCursorRequest>,
+) -> Result<Json<InjectCursorResponse>, (StatusCode, String)> {
+    use base64::Engine;
+
+    require_admin(&state, &headers).map_err(|code| (code, String::new()))?;
+
+    let image = base64::engine::general_purpose::STANDARD
+        .decode(req.image.trim())
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid base64 image: {}", e),
+            )
+        })?;
+
+    let cached = build_external_cursor(&image, req.hotspot_x, req.hotspot_y).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid cursor image: {}", e),
+        )
+    })?;
+
+    if req.width.is_some_and(|w| w != cached.width)
+        || req.height.is_some_and(|h| h != cached.height)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Image is {}x{}, which does not match the given dimensions",
+                cached.width, cached.height
+            ),
+        ));
+    }
+
+    let response = InjectCursorResponse {
+        cursor_id: cached.id.clone(),
+        width: cached.width,
+        height: cached.height,
+        is_animated: cached.is_animated,
+    };
+    info!(
+        "Injected cursor {} ({}x{}, hotspot {},{}, animated={})",
+        cached.id,
+        cached.width,
+        cached.height,
+        cached.hotspot_x,
+        cached.hotspot_y,
+        cached.is_animated
+    );
+
+    let cursor_id = inject_cursor(cached);
+    broadcast_event(
+        &state,
+        &state.tx_broadcast,
+        AgentEvent::Cursor(CursorEvent::CursorChanged {
+            seat: DEFAULT_SEAT,
+            cursor_id,
+        }),
+    )
+    .await;
+
+    Ok(Json(response))
+}
+
+/// Next broadcast event for a client's sender task.
+enum Received {
+    Event(AgentEvent),
+    /// The receiver lagged and dropped `missed` events; `current` stands in
+    /// for them.
+    Resync {
+        missed: u64,
+        current: CursorEvent,
+    },
+}
+
+/// Receive the next broadcast event, or `None` once the channel is closed.
+///
+/// A lagging receiver has lost events, possibly a CursorChanged/CursorHidden
+/// that would leave the client on a stale cursor, so it gets the current
+/// cursor from `current_id` instead (a signal if the client already has it).
+async fn recv_or_resync(
+    rx: &mut broadcast::Receiver<AgentEvent>,
+    current_id: impl Fn() -> Option<String>,
+) -> Option<Received> {
+    match rx.recv().await {
+        Ok(event) => Some(Received::Event(event)),
+        Err(broadcast::error::RecvError::Lagged(missed)) => Some(Received::Resync {
+            missed,
+            current: CursorEvent::current(DEFAULT_SEAT, current_id()),
+        }),
+        Err(broadcast::error::RecvError::Closed) => None,
+    }
+}
+
+/// Whether a peer connection in state `s` is finished and should be released.
+fn peer_connection_ended(s: RTCPeerConnectionState) -> bool {
+    matches!(
+        s,
+        RTCPeerConnectionState::Failed
+            | RTCPeerConnectionState::Disconnected
+            | RTCPeerConnectionState::Closed
+    )
+}
+
+/// State-change handler for peer connection `peer_id`: logs each change and
+/// releases the connection once it has ended.  It holds a weak ref so the map
+/// entry isn't kept alive by its own callback.
+fn peer_state_handler(
+    weak_state: Weak<AppState>,
+    peer_id: u64,
+    span: Span,
+) -> OnPeerConnectionStateChangeHdlrFn {
+    Box::new(move |s: RTCPeerConnectionState| {
+        let _entered = span.enter();
+        info!("Peer connection {} state: {:?}", peer_id, s);
+        let weak_state = weak_state.clone();
+        Box::pin(
+            async move {
+                if !peer_connection_ended(s) {
+                    return;
+                }
+                let Some(state) = weak_state.upgrade() else {
+                    return;
+                };
+                state.client_rtt_ms.lock().await.remove(&peer_id);
+                state
+                    .trickle_sessions
+                    .lock()
+                    .await
+                    .retain(|_, s| s.peer_id != peer_id);
+                // A Disconnected → Closed sequence reports twice; only the
+                // first finds the connection.
+                let released = {
+                    let mut peers = state.peer_connections.lock().await;
+                    peers.remove(&peer_id).map(|pc| (pc, peers.len()))
+                };
+                if let Some((pc, remaining)) = released {
+                    info!(
+                        "Released peer connection {} ({} remaining)",
+                        peer_id, remaining
+                    );
+                    if s != RTCPeerConnectionState::Closed {
+                        // Closing from inside the state callback would wait on itself.
+                        tokio::spawn(
+                            async move {
+                                if let Err(e) = pc.close().await {
+                                    debug!("Error closing peer connection {}: {}", peer_id, e);
+                                }
+                            }
+                            .in_current_span(),
+                        );
+                    }
+                }
+            }
+            .instrument(span.clone()),
+        )
+    })
+}
+
+/// Close peer connection `peer_id` from another task, as the state-change
+/// callback does; the callback then releases it.
+async fn close_peer(app_state: &AppState, peer_id: u64) {
+    let pc = app_state
+        .peer_connections
+        .lock()
+        .await
+        .get(&peer_id)
+        .cloned();
+    if let Some(pc) = pc {
+        tokio::spawn(
+            async move {
+                if let Err(e) = pc.close().await {
+                    debug!("Error closing peer connection {}: {}", peer_id, e);
+                }
+            }
+            .in_current_span(),
+        );
+    }
+}
+
+/// Handle SDP offer from client, create peer connection, return SDP answer
+async fn handle_offer(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    offer_req: Result<Json<OfferRequest>, JsonRejection>,
+) -> Result<Json<AnswerResponse>, OfferError> {
+    if let Some(token) = state.auth_token.as_deref() {
+        if !is_bearer_authorized(&headers, token) {
+            warn!("Rejected WebRTC offer without a valid bearer token");
+            return Err(OfferError::client(
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "Unauthorized",
+            ));
+        }
+    }
+    let Json(offer_req) = offer_req.map_err(|e| {
+        OfferError::client(StatusCode::BAD_REQUEST, "invalid_request", e.body_text())
+    })?;
+    if offer_req.sdp_type != "offer" {
+        return Err(OfferError::client(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            format!("Expected type \"offer\", got {:?}", offer_req.sdp_type),
+        ));
+    }
+    info!("Received WebRTC offer from client");
+
+    let config = RTCConfiguration {
+        ice_servers: state.ice_servers.clone(),
+        ..Default::default()
+    };
+
+    let peer_connection = Arc::new(
+        state
+            .api
+            .new_peer_connection(config)
+            .await
+            .map_err(|e| OfferError::internal("creating the peer connection", e))?,
+    );
+
+    // Store to keep alive until the connection terminates
+    let peer_id = state.next_peer_id.fetch_add(1, Ordering::Relaxed);
+    state
+        .peer_connections
+        .lock()
+        .await
+        .insert(peer_id, peer_connection.clone());
+
+    // Every log line about this client runs in its `client` span, so
+    // concurrent clients can be told apart.
+    let span = info_span!("client", id = %new_session_id());
+    info!(parent: &span, "New client session (peer connection {})", peer_id);
+
+    // Log connection state changes and release terminated connections.
+    peer_connection.on_peer_connection_state_change(peer_state_handler(
+        Arc::downgrade(&state),
+        peer_id,
+        span.clone(),
+    ));
+
+    // Handle incoming data channels from client.  All of a peer's channels
+    // share one client state; the cursor channel runs the sender task.
+    let app_state = state.clone();
+    let client_state = Arc::new(Mutex::new(ClientState::new(
+        app_state.max_cursor_msgs_per_sec,
+    )));
+    let channels = Arc::new(Mutex::new(PeerChannels::default()));
+    let span_dc = span.clone();
+    peer_connection.on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
+        let span = span_dc.clone();
+        let role = ChannelRole::from_label(dc.label());
+        info!(parent: &span, "Client opened data channel: {} ({:?})", dc.label(), role);
+        let app_state = app_state.clone();
+        let client_state = client_state.clone();
+        let channels = channels.clone();
+
+        Box::pin(async move {
+            let state_msg = app_state.clone();
+
+            if role == ChannelRole::Cursor {
+                register_cursor_sender(
+                    &dc,
+                    app_state,
+                    client_state.clone(),
+                    channels.clone(),
+                    span.clone(),
+                );
+            } else {
+                let dc_open = dc.clone();
+                let channels_open = channels.clone();
+                let span_open = span.clone();
+                dc.on_open(Box::new(move || {
+                    info!(parent: &span_open, "{:?} channel open", role);
+                    Box::pin(async move {
+                        if let Some(slot) = channels_open.lock().await.slot(role) {
+                            *slot = Some(dc_open);
+                        }
+                    })
+                }));
+            }
+
+            // On message: every channel accepts every client message
+            let cs_msg = client_state.clone();
+            let dc_msg = dc.clone();
+            let channels_msg = channels.clone();
+            let span_msg = span.clone();
+            dc.on_message(Box::new(move |msg: DataChannelMessage| {
+                let cs = cs_msg.clone();
+                let dc = dc_msg.clone();
+                let app_state = state_msg.clone();
+                let channels = channels_msg.clone();
+
+                Box::pin(
+                    async move {
+                        let handled =
+                            handle_client_message(&dc, &app_state, peer_id, &cs, &channels, msg)
+                                .await;
+                        if handled.is_err() {
+                            close_peer(&app_state, peer_id).await;
+                        }
+                    }
+                    .instrument(span_msg.clone()),
+                )
+            }));
+
+            let dc_close = dc.clone();
+            dc.on_close(Box::new(move || {
+                info!(parent: &span, "DataChannel closed ({:?})", role);
+                let dc = dc_close.clone();
+                let channels = channels.clone();
+                Box::pin(async move {
+                    let mut channels = channels.lock().await;
+                    if let Some(slot) = channels.slot(role) {
+                        if slot.as_ref().is_some_and(|c| Arc::ptr_eq(c, &dc)) {
+                            *slot = None;
+                        }
+                    }
+                })
+            }));
+        })
+    }));
+
+    // Set remote description (client's offer)
+    let offer = RTCSessionDescription::offer(offer_req.sdp).map_err(|e| {
+        OfferError::client(
+            StatusCode::BAD_REQUEST,
+            "invalid_sdp",
+            format!("Invalid SDP offer: {}", e),
+        )
+    })?;
+
+    peer_connection
+        .set_remote_description(offer)
+        .await
+        .map_err(OfferError::remote_description)?;
+
+    // Trickled server candidates are queued until the client polls for them.
+    let local_candidates = Arc::new(Mutex::new(LocalCandidates::default()));
+    if offer_req.trickle {
+        let local = local_candidates.clone();
+        peer_connection.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let local = local.clone();
+            Box::pin(async move {
+                let mut local = local.lock().await;
+                match candidate.map(|c| c.to_json()) {
+                    Some(Ok(mut init)) => {
+                        // The answer has a single (data channel) m-line; an
+                        // empty mid is rejected by browsers, the index is not.
+                        init.sdp_mid = None;
+                        local.pending.push(init);
+                    }
+                    Some(Err(e)) => debug!("Cannot serialize ICE candidate: {}", e),
+                    None => {
+                        debug!("ICE gathering complete (trickle)");
+                        local.complete = true;
+                    }
+                }
+            })
+        }));
+    }
+
+    // Create and set local answer
+    let answer = peer_connection
+        .create_answer(None)
+        .await
+        .map_err(|e| OfferError::internal("create_answer", e))?;
+
+    peer_connection
+        .set_local_description(answer)
+        .await
+        .map_err(|e| OfferError::internal("set_local_description", e))?;
+
+    // Without trickle the answer has to carry every candidate, so wait for
+    // ICE gathering (with timeout).
+    if !offer_req.trickle {
+        let mut gather_complete = peer_connection.gathering_complete_promise().await;
+        tokio::select! {
+            _ = gather_complete.recv() => { debug!("ICE gathering complete"); }
+            _ = tokio::time::sleep(Duration::from_secs(5)) => { debug!("ICE gathering timeout"); }
+        }
+    }
+
+    let local_desc = peer_connection
+        .local_description()
+        .await
+        .ok_or_else(|| OfferError::internal("answering", "no local description available"))?;
+
+    // The answer's ICE password is random, so its hash makes an unguessable
+    // session id.
+    let session_id = if offer_req.trickle {
+        let id = blake3::hash(local_desc.sdp.as_bytes()).to_hex()[..32].to_string();
+        state.trickle_sessions.lock().await.insert(
+            id.clone(),
+            TrickleSession {
+                peer_id,
+                peer_connection: peer_connection.clone(),
+                local: local_candidates,
+            },
+        );
+        Some(id)
+    } else {
+        None
+    };
+
+    info!(
+        parent: &span,
+        "Sending SDP answer to client{}",
+        if session_id.is_some() { " (trickle ICE)" } else { "" }
+    );
+
+    Ok(Json(AnswerResponse {
+        sdp: local_desc.sdp,
+        sdp_type: "answer".to_string(),
+        session_id,
+    }))
+}
+
+/// Trickle ICE exchange: add the client's new candidates to its peer
+/// connection and return the server candidates gathered since the last call.
+/// Clients poll this until `complete` is set.
+async fn handle_ice(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<IceRequest>,
+) -> Result<Json<IceResponse>, (StatusCode, String)> {
+    if let Some(token) = state.auth_token.as_deref() {
+        if !is_bearer_authorized(&headers, token) {
+            return Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()));
+        }
+    }
+
+    let (peer_connection, local) = {
+        let sessions = state.trickle_sessions.lock().await;
+        let session = sessions
+            .get(&session_id)
+            .ok_or((StatusCode::NOT_FOUND, "Unknown ICE session".to_string()))?;
+        (session.peer_connection.clone(), session.local.clone())
+    };
+
+    // An empty candidate is the browser's end-of-candidates marker.
+    for candidate in req
+        .candidates
+        .into_iter()
+        .filter(|c| !c.candidate.is_empty())
+    {
+        if let Err(e) = peer_connection.add_ice_candidate(candidate).await {
+            debug!("Rejected client ICE candidate: {}", e);
+        }
+    }
+
+    let mut local = local.lock().await;
+    Ok(Json(IceResponse {
+        candidates: std::mem::take(&mut local.pending),
+        complete: local.complete,
+    }))
+}
+
+/// Start the per-client sender once the cursor channel opens: cursor and
+/// settings events go out on `dc`, clipboard events and file transfers on
+/// the peer's clipboard/files channels when it has opened them.
+fn register_cursor_sender(
+    dc: &Arc<RTCDataChannel>,
+    app_state: Arc<AppState>,
+    client_state: Arc<Mutex<ClientState>>,
+    channels: Arc<Mutex<PeerChannels>>,
+    span: Span,
+) {
+    let rx = app_state.tx_broadcast.subscribe();
+    let dc_sender = dc.clone();
+    dc.on_open(Box::new(move || {
+        info!(parent: &span, "DataChannel open, starting cursor sender");
+        let dc = dc_sender;
+
+        Box::pin(async move {
+            dc.set_buffered_amount_low_threshold(app_state.buffer_low_water)
+                .await;
+            let drained = Arc::new(Notify::new());
+            let drained_cb = drained.clone();
+            dc.on_buffered_amount_low(Box::new(move || {
+                drained_cb.notify_one();
+                Box::pin(async {})
+            }))
+            .await;
+            tokio::spawn(
+                run_cursor_sender(dc, app_state, client_state, channels, rx, drained)
+                    .instrument(span),
+            );
+        })
+    }));
+}
+
+/// A client's sender task: sends the server hello and the current cursor,
+/// then forwards broadcast events until `dc` fails.  `drained` is notified
+/// once the channel's buffered amount falls below the low-water mark.
+async fn run_cursor_sender<C: ClientChannel>(
+    dc: Arc<C>,
+    app_state: Arc<AppState>,
+    client_state: Arc<Mutex<ClientState>>,
+    channels: Arc<Mutex<PeerChannels<C>>>,
+    mut rx: broadcast::Receiver<AgentEvent>,
+    drained: Arc<Notify>,
+) {
+    metrics::client_connected();
+
+    if let Err(e) = send_proto(&dc, create_hello_message(&app_state)).await {
+        warn!("Failed to send server hello: {}", e);
+    }
+
+    // Send the current cursor right away; broadcast events only
+    // cover future changes, so the client would show nothing
+    // until the cursor next changes.  A failed send ends the loop
+    // below on its first event.
+    {
+        let mut cs = client_state.lock().await;
+        let draw_cursor = app_state
+            .last_settings
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|s| s.draw_cursor);
+        if app_state.auto_suppress_on_draw_cursor && draw_cursor == Some(false) {
+            let _ = suppress_on_draw_cursor(&dc, &mut cs, draw_cursor).await;
+        } else {
+            let initial = CursorEvent::current(DEFAULT_SEAT, get_last_cursor_id());
+            let _ = send_cursor_event(&dc, &mut cs, &initial).await;
+        }
+    }
+
+    let mut heartbeat = interval(Duration::from_secs(30));
+    heartbeat.tick().await; // skip first tick
+
+    // Backpressure: while the channel buffers more than the
+    // high-water mark, hold back cursor events (keeping only the
+    // latest image/hide and the latest position of each seat)
+    // until `drained` reports it below the low-water mark.
+    let mut paused = false;
+    let mut pending_cursor: HashMap<SeatId, CursorEvent> = HashMap::new();
+    let mut pending_position: HashMap<SeatId, CursorEvent> = HashMap::new();
+
+    // Rate limit: an image/hide over the client's budget waits here
+    // (replaced by a newer one for its seat) until `throttle_deadline`.
+    let mut throttled: HashMap<SeatId, CursorEvent> = HashMap::new();
+    let mut throttle_deadline: Option<tokio::time::Instant> = None;
+    let resend = client_state.lock().await.resend_cursor.clone();
+
+    loop {
+        tokio::select! {
+            received = recv_or_resync(&mut rx, get_last_cursor_id) => {
+                match received {
+                    Some(Received::Event(event)) => {
+                        let mut cs = client_state.lock().await;
+                        let err = match &event {
+                            AgentEvent::Cursor(ev) => {
+                                if !paused {
+                                    let buffered = dc.buffered_amount().await;
+                                    if buffered > app_state.buffer_high_water {
+                                        paused = true;
+                                        app_state
+                                            .backpressure_engaged
+                                            .fetch_add(1, Ordering::Relaxed);
+                                        debug!(
+                                            "Backpressure engaged ({} bytes buffered)",
+                                            buffered
+                                        );
+                                    }
+                                }
+                                if paused {
+                                    let pending = match ev {
+                                        CursorEvent::Moved { .. } => &mut pending_position,
+                                        _ => &mut pending_cursor,
+                                    };
+                                    pending.insert(ev.seat(), ev.clone());
+                                    Ok(())
+                                } else if matches!(ev, CursorEvent::Moved { .. }) {
+                                    send_cursor_event(&dc, &mut cs, ev).await
+                                } else {
+                                    send_or_throttle(
+                                        &dc,
+                                        &mut cs,
+                                        ev.clone(),
+                                        &mut throttled,
+                                        &app_state.cursor_events_coalesced,
+                                    )
+                                    .await
+                                    .map(|wait| {
+                                        throttle_deadline = wait.or(throttle_deadline)
+                                    })
+                                }
+                            }
+                            AgentEvent::Clipboard(ev) => {
+                                let (clip_dc, files_dc) = {
+                                    let channels = channels.lock().await;
+                                    (channels.clipboard_or(&dc), channels.files_or(&dc))
+                                };
+                                let result = send_clipboard_event(
+                                    &clip_dc, &files_dc, &mut cs, ev, &app_state,
+                                )
+                                .await;
+                                // A failing clipboard channel is dropped from
+                                // `channels` on close; only the cursor
+                                // channel failing ends this task.
+                                if Arc::ptr_eq(&clip_dc, &dc) { result } else { Ok(()) }
+                            }
+                            AgentEvent::Settings(ev) => {
+                                let result = send_settings_event(&dc, ev).await;
+                                if result.is_ok()
+                                    && app_state.auto_suppress_on_draw_cursor
+                                {
+                                    suppress_on_draw_cursor(&dc, &mut cs, ev.draw_cursor)
+                                        .await
+                                } else {
+                                    result
+                                }
+                            }
+                        };
+                        if err.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Received::Resync { missed, current }) => {
+                        warn!(
+                            "Broadcast lagged, missed {} events; resyncing cursor",
+                            missed
+                        );
+                        app_state.broadcast_lagged.fetch_add(1, Ordering::Relaxed);
+                        if paused {
+                            pending_cursor.insert(DEFAULT_SEAT, current);
+                        } else {
+                            let mut cs = client_state.lock().await;
+                            let sent = send_or_throttle(
+                                &dc,
+                                &mut cs,
+                                current,
+                                &mut throttled,
+                                &app_state.cursor_events_coalesced,
+                            )
+                            .await;
+                            match sent {
+                                Ok(wait) => {
+                                    throttle_deadline = wait.or(throttle_deadline)
+                                }
+                                Err(()) => break,
+                            }
+                        }
+                    }
+                    None => {
+                        info!("Broadcast closed");
+                        break;
+                    }
+                }
+            }
+            _ = resend.notified() => {
+                // A config change invalidated the client's cursor
+                let current = CursorEvent::current(DEFAULT_SEAT, get_last_cursor_id());
+                if paused {
+                    pending_cursor.insert(DEFAULT_SEAT, current);
+                } else {
+                    let mut cs = client_state.lock().await;
+                    let sent = send_or_throttle(
+                        &dc,
+                        &mut cs,
+                        current,
+                        &mut throttled,
+                        &app_state.cursor_events_coalesced,
+                    )
+                    .await;
+                    match sent {
+                        Ok(wait) => throttle_deadline = wait.or(throttle_deadline),
+                        Err(()) => break,
+                    }
+                }
+            }
+            _ = drained.notified(), if paused => {
+                // The notification may be a stale permit from before
+                // the pause; only resume once drained.
+                if dc.buffered_amount().await > app_state.buffer_low_water {
+                    continue;
+                }
+                paused = false;
+                debug!("Backpressure released");
+                let mut cs = client_state.lock().await;
+                let mut failed = false;
+                for (_, ev) in pending_cursor.drain() {
+                    let sent =
+                        send_or_throttle(
+                        &dc,
+                        &mut cs,
+                        ev,
+                        &mut throttled,
+                        &app_state.cursor_events_coalesced,
+                    )
+                            .await;
+                    match sent {
+                        Ok(wait) => throttle_deadline = wait.or(throttle_deadline),
+                        Err(()) => failed = true,
+                    }
+                }
+                for (_, ev) in pending_position.drain() {
+                    failed |= send_cursor_event(&dc, &mut cs, &ev).await.is_err();
+                }
+                if failed {
+                    break;
+                }
+            }
+            _ = sleep_until(
+                throttle_deadline.unwrap_or_else(tokio::time::Instant::now),
+            ),
+                if throttle_deadline.is_some() && !paused =>
+            {
+                throttle_deadline = None;
+                let parked = std::mem::take(&mut throttled);
+                let mut cs = client_state.lock().await;
+                let mut failed = false;
+                for (_, ev) in parked {
+                    let sent =
+                        send_or_throttle(
+                        &dc,
+                        &mut cs,
+                        ev,
+                        &mut throttled,
+                        &app_state.cursor_events_coalesced,
+                    )
+                            .await;
+                    match sent {
+                        Ok(wait) => throttle_deadline = wait.or(throttle_deadline),
+                        Err(()) => failed = true,
+                    }
+                }
+                if failed {
+                    break;
+                }
+            }
+            _ = heartbeat.tick() => {
+                let msg = create_heartbeat_message();
+                let mut buf = Vec::new();
+                if msg.encode(&mut buf).is_err() {
+                    continue;
+                }
+                if dc.send(&Bytes::from(buf)).await.is_err() {
+                    break;
+                }
+                debug!("Heartbeat sent");
+            }
+        }
+    }
+    metrics::client_disconnected();
+    info!("Cursor sender stopped");
+}
+
+/// Handle one message from any of a client's data channels: DPR config as
+/// text, or a binary clipboard push/pull, file transfer, re-capture request,
+/// heartbeat ack or cursor mode change.  Replies go out on the channel the
+/// request arrived on.  `Err` means the client must be disconnected.
+async fn handle_client_message<C: ClientChannel>(
+    dc: &Arc<C>,
+    app_state: &AppState,
+    peer_id: u64,
+    cs: &Mutex<ClientState>,
+    channels: &Mutex<PeerChannels<C>>,
+    msg: DataChannelMessage,
+) -> Result<(), ()> {
+    if msg.is_string {
+        let text = String::from_utf8_lossy(&msg.data);
+        debug!("Client text message: {}", text);
+
+        if let Some(config) = ClientConfig::parse(&text) {
+            if let Some(version) = config.schema_version.filter(|v| *v != SCHEMA_VERSION) {
+                warn!(
+                    "Client {} speaks schema version {}, server has {}; disconnecting it",
+                    peer_id, version, SCHEMA_VERSION
+                );
+                return Err(());
+            }
+            apply_client_config(&mut *cs.lock().await, &config);
+        }
+        return Ok(());
+    }
+
+    let Ok(client_msg) = CursorMessage::decode(msg.data.as_ref()) else {
+        return Ok(());
+    };
+    if client_msg.r#type == MessageType::Clipboard as i32 {
+        if let Some(Payload::ClipboardData(clip_data)) = client_msg.payload {
+            app_state.clipboard_received.fetch_add(1, Ordering::Relaxed);
+            handle_client_clipboard(clip_data, &mut cs.lock().await.incoming_files);
+        }
+    } else if client_msg.r#type == MessageType::FileTransferBegin as i32
+        || client_msg.r#type == MessageType::FileTransferChunk as i32
+        || client_msg.r#type == MessageType::FileTransferEnd as i32
+    {
+        if let Some(payload) = client_msg.payload {
+            handle_client_file_transfer(payload, &mut cs.lock().await.incoming_files);
+        }
+    } else if client_msg.r#type == MessageType::RecaptureLive as i32 {
+        handle_recapture_request(&mut *cs.lock().await);
+    } else if client_msg.r#type == MessageType::HeartbeatAck as i32 {
+        handle_heartbeat_ack(
+            app_state,
+            peer_id,
+            &mut *cs.lock().await,
+            client_msg.timestamp,
+        )
+        .await;
+    } else if client_msg.r#type == MessageType::SetCursorMode as i32 {
+        if let Some(Payload::SetCursorMode(req)) = client_msg.payload {
+            handle_set_cursor_mode(dc, &mut *cs.lock().await, req.mode()).await;
+        }
+    } else if client_msg.r#type == MessageType::CursorAck as i32 {
+        if let Some(Payload::CursorAck(ack)) = client_msg.payload {
+            handle_cursor_ack(&mut *cs.lock().await, ack);
+        }
+    } else if client_msg.r#type == MessageType::ClientCacheHello as i32 {
+        if let Some(Payload::ClientCacheHello(hello)) = client_msg.payload {
+            handle_client_cache_hello(&mut *cs.lock().await, hello);
+        }
+    } else if client_msg.r#type == MessageType::ClipboardHistoryRequest as i32 {
+        if let Some(Payload::ClipboardHistoryRequest(req)) = client_msg.payload {
+            handle_clipboard_history_request(dc, req).await;
+        }
+    } else if client_msg.r#type == MessageType::ClipboardRequest as i32 {
+        if let Some(Payload::ClipboardRequest(req)) = client_msg.payload {
+            let files_dc = channels.lock().await.files_or(dc);
+            handle_clipboard_request(dc, &files_dc, app_state, req).await;
+        }
+    }
+    Ok(())
+}
+
+/// Handle a reconnecting client's `ClientCacheHello`: treat the cursors it
+/// still has cached as already sent, so they are switched to with a
+/// `CursorSignal` rather than re-sent.  The cursor sent when the channel
+/// opened may already have gone out in full.
+fn handle_client_cache_hello(state: &mut ClientState, hello: ClientCacheHello) {
+    let count = hello.known_cursor_ids.len().min(MAX_KNOWN_CURSOR_IDS);
+    if hello.known_cursor_ids.len() > MAX_KNOWN_CURSOR_IDS {
+        warn!(
+            "ClientCacheHello lists {} cursors, keeping the first {}",
+            hello.known_cursor_ids.len(),
+            MAX_KNOWN_CURSOR_IDS
+        );
+    }
+    state.sent_cursor_ids.extend(
+        hello
+            .known_cursor_ids
+            .into_iter()
+            .take(count)
+            .map(|id| (DEFAULT_SEAT, id)),
+    );
+    info!("Client reports {} cached cursor(s)", count);
+}
+
+/// Handle `CursorAck`: the client has the image cached, so later changes to
+/// this cursor only need a signal.  The first ack switches the client to
+/// ack mode.  Acks for cursors no longer pending (e.g. sent before a scale
+/// change) are ignored.
+fn handle_cursor_ack(state: &mut ClientState, ack: CursorAck) {
+    if !state.cursor_acks {
+        debug!("Client acknowledges cursor data");
+        state.cursor_acks = true;
+    }
+    let key = (ack.seat_id, ack.cursor_id);
+    if state.pending_cursor_acks.remove(&key) {
+        state.sent_cursor_ids.insert(key);
+    } else {
+        debug!(
+            "Ignoring ack for cursor {} on seat {} (not pending)",
+            key.1, key.0
+        );
+    }
+}
+
+/// Handle a client's `RecaptureLive` request: ask the capture loop to
+/// re-capture the current cursor from the OS, and forget that this client has
+/// it so the fresh encode is sent as full data rather than a signal.
+fn handle_recapture_request(state: &mut ClientState) {
+    if state
+        .last_recapture
+        .is_some_and(|t| t.elapsed() < RECAPTURE_MIN_INTERVAL)
+    {
+        debug!("Ignoring RecaptureLive request (rate limited)");
+        return;
+    }
+    state.last_recapture = Some(Instant::now());
+
+    if let Some(id) = get_last_cursor_id() {
+        let key = (DEFAULT_SEAT, id);
+        state.sent_cursor_ids.remove(&key);
+        state.pending_cursor_acks.remove(&key);
+    }
+    info!("Client requested live cursor re-capture");
+    request_recapture();
+}
+
+/// Handle `HeartbeatAck`: the client echoes the heartbeat's send time, so
+/// the difference to now is the round trip.  A sudden spike is logged as an
+/// early sign of a lagging client.
+async fn handle_heartbeat_ack(
+    app_state: &AppState,
+    peer_id: u64,
+    state: &mut ClientState,
+    sent_ms: u64,
+) {
+    let now = now_ms();
+    if sent_ms == 0 || sent_ms > now {
+        debug!("Ignoring heartbeat ack with invalid timestamp {}", sent_ms);
+        return;
+    }
+    let rtt = now - sent_ms;
+    match state.rtt_ms {
+        Some(prev) if rtt >= RTT_SPIKE_MIN_MS && rtt > prev.max(1) * RTT_SPIKE_FACTOR => {
+            warn!("Client {} RTT spiked: {} ms -> {} ms", peer_id, prev, rtt);
+        }
+        _ => debug!("Client {} RTT: {} ms", peer_id, rtt),
+    }
+    state.rtt_ms = Some(rtt);
+    app_state.client_rtt_ms.lock().await.insert(peer_id, rtt);
+}
+
+/// Apply a client config message to `state`.  A change to how cursors are
+/// rendered for the client has the sender task re-send the current cursor.
+fn apply_client_config(state: &mut ClientState, config: &ClientConfig) {
+    let mut changed = false;
+    if let Some(new_dpr) = config.device_pixel_ratio {
+        if new_dpr > 0.0 && new_dpr <= 10.0 && (new_dpr - state.dpr).abs() > 0.01 {
+            info!("Client DPR: {:.2} -> {:.2}", state.dpr, new_dpr);
+            state.dpr = new_dpr;
+            changed = true;
+        }
+    }
+    if let Some(size) = config.preferred_cursor_size {
+        let size = (size > 0).then_some(size);
+        if let Some(Err(e)) = size.map(|s| check_cursor_dimensions(s, s)) {
+            warn!("Ignoring preferred_cursor_size: {}", e);
+        } else if size != state.preferred_cursor_size {
+            info!(
+                "Client cursor size: {:?} -> {:?}",
+                state.preferred_cursor_size, size
+            );
+            state.preferred_cursor_size = size;
+            changed = true;
+        }
+    }
+    if let Some(accepted) = &config.cursor_codecs {
+        let codec = negotiate_cursor_codec(accepted);
+        if codec != state.cursor_codec {
+            info!(
+                "Client cursor codec: {:?} -> {:?}",
+                state.cursor_codec, codec
+            );
+            state.cursor_codec = codec;
+            changed = true;
+        }
+    }
+    if let Some(name) = &config.cursor_quality {
+        match CursorQuality::from_name(name) {
+            Some(quality) if quality != state.cursor_quality => {
+                info!(
+                    "Client cursor quality: {:?} -> {:?}",
+                    state.cursor_quality, quality
+                );
+                state.cursor_quality = quality;
+                changed = true;
+            }
+            Some(_) => {}
+            None => warn!("Ignoring unknown cursor_quality {:?}", name),
+        }
+    }
+    if config.cursor_ack == Some(true) && !state.cursor_acks {
+        debug!("Client acknowledges cursor data");
+        state.cursor_acks = true;
+    }
+    if changed {
+        state.forget_sent_cursors();
+        state.resend_cursor.notify_one();
+    }
+}
+
+/// Handle `SetCursorMode`: a hidden client gets one hide message and no
+/// further cursor images; switching back re-sends the current cursor.
+async fn handle_set_cursor_mode<C: ClientChannel>(
+    dc: &Arc<C>,
+    state: &mut ClientState,
+    mode: CursorMode,
+) {
+    let mode = match mode {
+        CursorMode::Hidden => CursorMode::Hidden,
+        CursorMode::Bitmap | CursorMode::Unspecified => CursorMode::Bitmap,
+    };
+    if mode == state.cursor_mode {
+        return;
+    }
+    info!("Client cursor mode: {:?}", mode);
+    state.cursor_mode = mode;
+
+    if mode == CursorMode::Hidden {
+        if state.draw_cursor_suppressed {
+            return;
+        }
+        if let Err(e) = send_proto(dc, create_hide_message(DEFAULT_SEAT)).await {
+            debug!("Failed to send hide message: {}", e);
+        }
+    } else {
+        let current = CursorEvent::current(DEFAULT_SEAT, get_last_cursor_id());
+        let _ = send_cursor_event(dc, state, &current).await;
+    }
+}
+
+/// Apply `AUTO_SUPPRESS_ON_DRAW_CURSOR` for a new settings event.  Sunshine
+/// drawing the cursor into the video (`draw_cursor == false`) sends one hide
+/// and holds back cursor images; the overlay being needed again re-sends the
+/// current cursor.  An unknown setting leaves the overlay on.
+async fn suppress_on_draw_cursor<C: ClientChannel>(
+    dc: &Arc<C>,
+    state: &mut ClientState,
+    draw_cursor: Option<bool>,
+) -> Result<(), ()> {
+    let suppressed = draw_cursor == Some(false);
+    if suppressed == state.draw_cursor_suppressed {
+        return Ok(());
+    }
+    if suppressed {
+        info!("Sunshine draws the cursor, suppressing cursor images");
+        // Sent before the flag is set, which would swallow it
+        send_cursor_event(dc, state, &CursorEvent::CursorHidden { seat: DEFAULT_SEAT }).await?;
+        state.draw_cursor_suppressed = true;
+        Ok(())
+    } else {
+        info!("Overlay cursor needed again, resuming cursor images");
+        state.draw_cursor_suppressed = false;
+        let current = CursorEvent::current(DEFAULT_SEAT, get_last_cursor_id());
+        send_cursor_event(dc, state, &current).await
+    }
+}
+
+/// Send a cursor image/hide event if the client's rate budget allows.
+/// Otherwise park it in `throttled`, superseding any event already parked
+/// for its seat, and return the deadline for retrying.
+async fn send_or_throttle<C: ClientChannel>(
+    dc: &Arc<C>,
+    state: &mut ClientState,
+    event: CursorEvent,
+    throttled: &mut HashMap<SeatId, CursorEvent>,
+    coalesced: &AtomicU64,
+) -> Result<Option<tokio::time::Instant>, ()> {
+    if throttled.is_empty() && state.cursor_budget.try_take() {
+        send_cursor_event(dc, state, &event).await?;
+        return Ok(None);
+    }
+    if throttled.insert(event.seat(), event).is_some() {
+        coalesced.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(Some(
+        tokio::time::Instant::now() + state.cursor_budget.wait_time(),
+    ))
+}
+
+/// Send cursor event as protobuf binary over data channel
+async fn send_cursor_event<C: ClientChannel>(
+    dc: &Arc<C>,
+    state: &mut ClientState,
+    event: &CursorEvent,
+) -> Result<(), ()> {
+    // The client draws its own cursor, or Sunshine draws it into the video;
+    // positions are still forwarded.
+    if (state.cursor_mode == CursorMode::Hidden || state.draw_cursor_suppressed)
+        && !matches!(event, CursorEvent::Moved { .. })
+    {
+        return Ok(());
+    }
+
+    match event {
+        CursorEvent::CursorChanged { seat, cursor_id } => {
+            if get_cached_cursor(cursor_id).is_some() {
+                if state.sent_cursor_ids.contains(&(*seat, cursor_id.clone())) {
+                    let msg = create_signal_message(*seat, cursor_id);
+                    let mut buf = Vec::new();
+                    if let Err(e) = msg.encode(&mut buf) {
+                        error!("Encode error: {}", e);
+                        return Ok(());
+                    }
+                    if let Err(e) = dc.send(&Bytes::from(buf)).await {
+                        error!("DC send error: {}", e);
+                        return Err(());
+                    }
+                } else if let Some(data_msg) = create_scaled_cursor_message(
+                    *seat,
+                    cursor_id,
+                    state.dpr,
+                    state.preferred_cursor_size,
+                    state.cursor_codec,
+                    state.cursor_quality,
+                ) {
+                    let mut buf = Vec::new();
+                    if let Err(e) = data_msg.encode(&mut buf) {
+                        error!("Encode error: {}", e);
+                        return Ok(());
+                    }
+                    debug!(
+                        "Sending cursor data (dpr={:.2}): {} ({} bytes)",
+                        state.dpr,
+                        cursor_id,
+                        buf.len()
+                    );
+                    if let Err(e) = dc.send(&Bytes::from(buf)).await {
+                        error!("DC send error: {}", e);
+                        return Err(());
+                    }
+                    state.cursor_data_sent(*seat, cursor_id.clone());
+                }
+            }
+        }
+        CursorEvent::CursorHidden { seat } => {
+            let msg = create_hide_message(*seat);
+            let mut buf = Vec::new();
+            if let Err(e) = msg.encode(&mut buf) {
+                error!("Encode error: {}", e);
+                return Ok(());
+            }
+            if let Err(e) = dc.send(&Bytes::from(buf)).await {
+                error!("DC send error: {}", e);
+                return Err(());
+            }
+        }
+        CursorEvent::Moved { seat, x, y } => {
+            let msg = create_position_message(*seat, *x, *y);
+            let mut buf = Vec::new();
+            if let Err(e) = msg.encode(&mut buf) {
+                error!("Encode error: {}", e);
+                return Ok(());
+            }
+            if let Err(e) = dc.send(&Bytes::from(buf)).await {
+                error!("DC send error: {}", e);
+                return Err(());
+            }
+        }
+    }
+    Ok(())
+}
+
+// ── Clipboard helpers ────────────────────────────────────────────────────────
+
+/// Send a clipboard event to a single client, deduplicating by content hash.
+///
+/// With `notify_only` the client receives a [`ClipboardAvailable`] announcement
+/// and pulls the content itself with a `ClipboardRequest`.  File bytes are
+/// streamed on `files_dc`.
+async fn send_clipboard_event<C: ClientChannel>(
+    dc: &Arc<C>,
+    files_dc: &Arc<C>,
+    state: &mut ClientState,
+    event: &ClipboardEvent,
+    app_state: &AppState,
+) -> Result<(), ()> {
+    // Skip if this client already has this clipboard content.
+    if state.last_clipboard_hash.as_deref() == Some(&event.content_hash) {
+        return Ok(());
+    }
+
+    let notify_only = app_state.clipboard_notify_only;
+    let msg = if notify_only {
+        CursorMessage {
+            r#type: MessageType::ClipboardAvailable.into(),
+            payload: Some(Payload::ClipboardAvailable(build_clipboard_available(
+                event,
+            ))),
+            timestamp: now_ms(),
+        }
+    } else {
+        CursorMessage {
+            r#type: MessageType::Clipboard.into(),
+            payload: Some(Payload::ClipboardData(build_clipboard_proto(event))),
+            timestamp: now_ms(),
+        }
+    };
+
+    let mut buf = Vec::new();
+    if let Err(e) = msg.encode(&mut buf) {
+        error!("Clipboard encode error: {}", e);
+        return Ok(());
+    }
+
+    debug!(
+        "Sending clipboard to client ({} bytes, hash prefix: {}…)",
+        buf.len(),
+        &event.content_hash[..8]
+    );
+
+    if let Err(e) = dc.send(&Bytes::from(buf)).await {
+        error!("DC send error (clipboard): {}", e);
+        return Err(());
+    }
+
+    if !notify_only {
+        spawn_file_transfers(files_dc, event, app_state.buffer_low_water);
+    }
+    app_state.clipboard_sent.fetch_add(1, Ordering::Relaxed);
+    state.last_clipboard_hash = Some(event.content_hash.clone());
+    Ok(())
+}
+
+/// Build a [`ClipboardData`] protobuf message from a [`ClipboardEvent`].
+fn build_clipboard_proto(event: &ClipboardEvent) -> ClipboardData {
+    match &event.content {
+        ClipboardContent::Text(text) => ClipboardData {
+            content_type: ClipboardContentType::Text.into(),
+            payload: text.as_bytes().to_vec(),
+            content_hash: event.content_hash.clone(),
+            filenames: vec![],
+            file_sizes: vec![],
+            alt_text: String::new(),
+            width: 0,
+            height: 0,
+        },
+        ClipboardContent::Html { html, text } => ClipboardData {
+            content_type: ClipboardContentType::Html.into(),
+            payload: html.as_bytes().to_vec(),
+            content_hash: event.content_hash.clone(),
+            filenames: vec![],
+            file_sizes: vec![],
+            alt_text: text.clone(),
+            width: 0,
+            height: 0,
+        },
+        ClipboardContent::Rtf { rtf, text } => ClipboardData {
+            content_type: ClipboardContentType::Rtf.into(),
+            payload: rtf.as_bytes().to_vec(),
+            content_hash: event.content_hash.clone(),
+            filenames: vec![],
+            file_sizes: vec![],
+            alt_text: text.clone(),
+            width: 0,
+            height: 0,
+        },
+        ClipboardContent::Image {
+            data,
+            format,
+            width,
+            height,
+        } => ClipboardData {
+            content_type: image_content_type(*format).into(),
+            payload: data.clone(),
+            content_hash: event.content_hash.clone(),
+            filenames: vec![],
+            file_sizes: vec![],
+            alt_text: String::new(),
+            width: *width,
+            height: *height,
+        },
+        ClipboardContent::Files(files) => ClipboardData {
+            content_type: ClipboardContentType::Files.into(),
+            payload: vec![],
+            content_hash: event.content_hash.clone(),
+            filenames: files.iter().map(ClipboardFile::name).collect(),
+            file_sizes: files.iter().map(|f| f.size).collect(),
+            alt_text: String::new(),
+            width: 0,
+            height: 0,
+        },
+    }
+}
+
+/// Proto content type for an encoded clipboard image.
+fn image_content_type(format: ImageFormat) -> ClipboardContentType {
+    match format {
+        ImageFormat::Png => ClipboardContentType::Image,
+        ImageFormat::Webp => ClipboardContentType::ImageWebp,
+    }
+}
+
+/// Build the metadata-only [`ClipboardAvailable`] announcement for an event.
+fn build_clipboard_available(event: &ClipboardEvent) -> ClipboardAvailable {
+    let (content_type, size) = match &event.content {
+        ClipboardContent::Text(text) => (ClipboardContentType::Text, text.len()),
+        ClipboardContent::Html { html, text } => {
+            (ClipboardContentType::Html, html.len() + text.len())
+        }
+        ClipboardContent::Rtf { rtf, text } => (ClipboardContentType::Rtf, rtf.len() + text.len()),
+        ClipboardContent::Image { data, format, .. } => (image_content_type(*format), data.len()),
+        ClipboardContent::Files(files) => (
+            ClipboardContentType::Files,
+            files.iter().map(|f| f.size as usize).sum(),
+        ),
+    };
+    ClipboardAvailable {
+        content_type: content_type.into(),
+        size: size as u64,
+        content_hash: event.content_hash.clone(),
+    }
+}
+
+/// Answer a client's `ClipboardRequest` with the full [`ClipboardData`] of the
+/// current host clipboard.  Requests for a stale hash are ignored; the client
+/// will have been sent a newer `ClipboardAvailable` already.
+async fn handle_clipboard_request<C: ClientChannel>(
+    dc: &Arc<C>,
+    files_dc: &Arc<C>,
+    app_state: &AppState,
+    req: ClipboardRequest,
+) {
+    let Some(event) = app_state.last_clipboard.lock().await.clone() else {
+        debug!("ClipboardRequest received but no host clipboard seen yet");
+        return;
+    };
+    if !req.content_hash.is_empty() && req.content_hash != event.content_hash {
+        debug!(
+            "Ignoring ClipboardRequest for stale hash {}",
+            req.content_hash
+        );
+        return;
+    }
+
+    let msg = CursorMessage {
+        r#type: MessageType::Clipboard.into(),
+        payload: Some(Payload::ClipboardData(build_clipboard_proto(&event))),
+        timestamp: now_ms(),
+    };
+    let mut buf = Vec::new();
+    if let Err(e) = msg.encode(&mut buf) {
+        error!("Clipboard encode error: {}", e);
+        return;
+    }
+    debug!(
+        "Sending requested clipboard to client ({} bytes)",
+        buf.len()
+    );
+    if let Err(e) = dc.send(&Bytes::from(buf)).await {
+        error!("DC send error (clipboard): {}", e);
+        return;
+    }
+    spawn_file_transfers(files_dc, &event, app_state.buffer_low_water);
+}
+
+/// Answer a `ClipboardHistoryRequest` with the most recent host clipboard
+/// entries, newest first.
+async fn handle_clipboard_history_request<C: ClientChannel>(
+    dc: &Arc<C>,
+    req: ClipboardHistoryRequest,
+) {
+    let entries: Vec<ClipboardData> = clipboard_sync::history(req.count as usize)
+        .iter()
+        .map(build_clipboard_proto)
+        .collect();
+    debug!(
+        "Sending {} clipboard history entries (requested {})",
+        entries.len(),
+        req.count
+    );
+
+    let msg = CursorMessage {
+        r#type: MessageType::ClipboardHistory.into(),
+        payload: Some(Payload::ClipboardHistory(ClipboardHistory { entries })),
+        timestamp: now_ms(),
+    };
+    if let Err(e) = send_proto(dc, msg).await {
+        error!("Failed to send clipboard history: {}", e);
+    }
+}
+
+// ── File transfer helpers ─────────────────────────────────────────────────────
+
+/// If `event` is a file list, stream the file bytes to the client in a
+/// separate task so its event loop keeps running.
+fn spawn_file_transfers<C: ClientChannel>(dc: &Arc<C>, event: &ClipboardEvent, low_water: usize) {
+    let ClipboardContent::Files(files) = &event.content else {
+        return;
+    };
+    let dc = dc.clone();
+    let files = files.clone();
+    let id_prefix = event.content_hash[..12].to_string();
+    tokio::spawn(
+        async move {
+            for (i, file) in files.iter().enumerate() {
+                let transfer_id = format!("{}-{}", id_prefix, i);
+                if let Err(e) = stream_file(&dc, &transfer_id, file, low_water).await {
+                    warn!(
+                        "File transfer {} ({:?}) aborted: {}",
+                        transfer_id, file.path, e
+                    );
+                    return;
+                }
+            }
+            info!("Sent {} clipboard file(s) to client", files.len());
+        }
+        .in_current_span(),
+    );
+}
+
+/// Send one file as `FileTransferBegin`, chunks and `FileTransferEnd`.
+///
+/// Chunks are only queued while the channel's buffered amount is below
+/// `low_water`, so a slow client throttles the transfer rather than piling
+/// data into the SCTP buffer ahead of cursor updates.  The transfer fails if
+/// the channel closes or stops draining (see [`wait_for_drain`]).
+async fn stream_file<C: ClientChannel>(
+    dc: &Arc<C>,
+    transfer_id: &str,
+    file: &ClipboardFile,
+    low_water: usize,
+) -> Result<()> {
+    use tokio::io::AsyncReadExt;
+
+    if file.size > MAX_FILE_SIZE {
+        return Err(anyhow::anyhow!("file too large ({} bytes)", file.size));
+    }
+    let mut reader = tokio::fs::File::open(&file.path).await?;
+
+    send_proto(
+        dc,
+        CursorMessage {
+            r#type: MessageType::FileTransferBegin.into(),
+            payload: Some(Payload::FileTransferBegin(FileTransferBegin {
+                transfer_id: transfer_id.to_string(),
+                filename: file.name(),
+                size: file.size,
+            })),
+            timestamp: now_ms(),
+        },
+    )
+    .await?;
+
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut seq = 0u32;
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        wait_for_drain(dc.as_ref(), low_water).await?;
+        hasher.update(&buf[..n]);
+        send_proto(
+            dc,
+            CursorMessage {
+                r#type: MessageType::FileTransferChunk.into(),
+                payload: Some(Payload::FileTransferChunk(FileTransferChunk {
+                    transfer_id: transfer_id.to_string(),
+                    seq,
+                    data: buf[..n].to_vec(),
+                })),
+                timestamp: now_ms(),
+            },
+        )
+        .await?;
+        seq += 1;
+    }
+
+    send_proto(
+        dc,
+        CursorMessage {
+            r#type: MessageType::FileTransferEnd.into(),
+            payload: Some(Payload::FileTransferEnd(FileTransferEnd {
+                transfer_id: transfer_id.to_string(),
+                blake3: hasher.finalize().to_hex().to_string(),
+            })),
+            timestamp: now_ms(),
+        },
+    )
+    .await?;
+    debug!("File transfer {} complete ({} chunks)", transfer_id, seq);
+    Ok(())
+}
+
+/// Wait until `dc` buffers at most `low_water` bytes.  Fails once the
+/// channel is no longer open or after [`FILE_DRAIN_TIMEOUT`].
+async fn wait_for_drain<C: ClientChannel>(dc: &C, low_water: usize) -> Result<()> {
+    let deadline = tokio::time::Instant::now() + FILE_DRAIN_TIMEOUT;
+    loop {
+        if !dc.is_open() {
+            return Err(anyhow::anyhow!("data channel closed"));
+        }
+        if dc.buffered_amount().await <= low_water {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(anyhow::anyhow!(
+                "data channel did not drain within {:?}",
+                FILE_DRAIN_TIMEOUT
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Encode and send a message over the data channel.
+async fn send_proto<C: ClientChannel>(dc: &Arc<C>, msg: CursorMessage) -> Result<()> {
+    let mut buf = Vec::new();
+    msg.encode(&mut buf)?;
+    dc.send(&Bytes::from(buf)).await?;
+    Ok(())
+}
+
+/// Feed a file-transfer message from a client into its reassembly state, and
+/// apply the file list to the host clipboard once the whole set has arrived.
+fn handle_client_file_transfer(payload: Payload, transfers: &mut IncomingTransfers) {
+    if !clipboard_sync::clipboard_direction().client_to_host() {
+        debug!("Ignoring client file transfer (CLIPBOARD_DIRECTION)");
+        return;
+    }
+    let result = match payload {
+        Payload::FileTransferBegin(begin) => transfers
+            .begin(&begin.transfer_id, &begin.filename, begin.size)
+            .map(|_| None),
+        Payload::FileTransferChunk(chunk) => transfers
+            .chunk(&chunk.transfer_id, chunk.seq, &chunk.data)
+            .map(|_| None),
+        Payload::FileTransferEnd(end) => transfers.end(&end.transfer_id, &end.blake3),
+        _ => return,
+    };
+
+    match result {
+        Ok(Some(files)) => {
+            if let Err(e) = apply_to_clipboard(&ClipboardContent::Files(files), "") {
+                error!("Failed to apply received files to host clipboard: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!("File transfer from client failed: {}", e),
+    }
+}
+
+/// Apply clipboard data received from a client to the host clipboard.  A
+/// file list is only announced here; it is applied once its bytes arrive.
+fn handle_client_clipboard(clip_data: ClipboardData, transfers: &mut IncomingTransfers) {
+    if !clipboard_sync::clipboard_direction().client_to_host() {
+        debug!("Ignoring client clipboard push (CLIPBOARD_DIRECTION)");
+        return;
+    }
+    let content_type = clip_data.content_type;
+    let hash = clip_data.content_hash.clone();
+
+    let content = if content_type == ClipboardContentType::Text as i32 {
+        match String::from_utf8(clip_data.payload) {
+            Ok(text) => ClipboardContent::Text(text),
+            Err(e) => {
+                error!("Invalid UTF-8 in clipboard text from client: {}", e);
+                return;
+            }
+        }
+    } else if content_type == ClipboardContentType::Html as i32 {
+        match String::from_utf8(clip_data.payload) {
+            Ok(html) => ClipboardContent::Html {
+                html,
+                text: clip_data.alt_text,
+            },
+            Err(e) => {
+                error!("Invalid UTF-8 in clipboard HTML from client: {}", e);
+                return;
+            }
+        }
+    } else if content_type == ClipboardContentType::Rtf as i32 {
+        // RTF is 7-bit ASCII with escapes; tolerate stray bytes rather than reject.
+        ClipboardContent::Rtf {
+            rtf: String::from_utf8_lossy(&clip_data.payload).into_owned(),
+            text: clip_data.alt_text,
+        }
+    } else if content_type == ClipboardContentType::Image as i32
+        || content_type == ClipboardContentType::ImageWebp as i32
+    {
+        let format = if content_type == ClipboardContentType::ImageWebp as i32 {
+            ImageFormat::Webp
+        } else {
+            ImageFormat::Png
+        };
+        ClipboardContent::Image {
+            data: clip_data.payload,
+            format,
+            // Informational only; apply_to_clipboard decodes the real size.
+            width: clip_data.width,
+            height: clip_data.height,
+        }
+    } else if content_type == ClipboardContentType::Files as i32 {
+        if let Err(e) = transfers.expect(&hash, &clip_data.filenames, &clip_data.file_sizes) {
+            warn!("Rejected file list from client: {}", e);
+        }
+        return;
+    } else {
+        warn!("Received unknown clipboard content type: {}", content_type);
+        return;
+    };
+
+    if let Err(e) = apply_to_clipboard(&content, &hash) {
+        error!("Failed to apply client clipboard to host: {}", e);
+    }
+}
+
+// ── Settings helpers ──────────────────────────────────────────────────────────
+
+/// Send a Sunshine settings event (draw_cursor state) to a client.
+async fn send_settings_event<C: ClientChannel>(
+    dc: &Arc<C>,
+    event: &SunshineSettingsEvent,
+) -> Result<(), ()> {
+    let msg = CursorMessage {
+        r#type: MessageType::Settings.into(),
+        payload: Some(Payload::SettingsData(SettingsData {
+            draw_cursor: event.draw_cursor,
+            sunshine_running: event.sunshine_running,
+            width: event.width,
+            height: event.height,
+            fps: event.fps,
+            bitrate_kbps: event.bitrate_kbps,
+            color_scheme: match event.color_scheme {
+                ColorScheme::Unknown => ProtoColorScheme::Unspecified,
+                ColorScheme::Light => ProtoColorScheme::Light,
+                ColorScheme::Dark => ProtoColorScheme::Dark,
+            }
+            .into(),
+        })),
+        timestamp: now_ms(),
+    };
+
+    let mut buf = Vec::new();
+    if let Err(e) = msg.encode(&mut buf) {
+        error!("Settings encode error: {}", e);
+        return Ok(());
+    }
+
+    debug!(
+        "Sending settings to client: draw_cursor={:?}, sunshine_running={}, color_scheme={:?}",
+        event.draw_cursor, event.sunshine_running, event.color_scheme
+    );
+
+    if let Err(e) = dc.send(&Bytes::from(buf)).await {
+        error!("DC send error (settings): {}", e);
+        return Err(());
+    }
+
+    Ok(())
+}
+
+// ── JSON / misc helpers ───────────────────────────────────────────────────────
+
+/// Read a byte count from the environment, falling back to `default` when
+/// unset or invalid.
+fn env_usize(name: &str, default: usize) -> usize {
+    match std::env::var(name) {
+        Ok(v) => v.trim().parse().unwrap_or_else(|_| {
+            warn!("Invalid {}={:?}, using {}", name, v, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// Parse `ICE_SERVERS`: comma-separated STUN/TURN URLs, each optionally
+/// carrying long-term credentials as `turn:user:pass@host:port`.
+/// Malformed entries are logged and skipped.
+fn parse_ice_servers(spec: &str) -> Vec<RTCIceServer> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .filter_map(|entry| {
+            let Some((scheme, rest)) = entry.split_once(':') else {
+                warn!("Ignoring ICE server {:?}: missing scheme", entry);
+                return None;
+            };
+            if !matches!(scheme, "stun" | "stuns" | "turn" | "turns") {
+                warn!(
+                    "Ignoring ICE server {:?}: unsupported scheme {:?}",
+                    entry, scheme
+                );
+                return None;
+            }
+            // The password may itself contain '@', so split on the last one.
+            match rest.rsplit_once('@') {
+                Some((creds, host)) => {
+                    let Some((username, credential)) = creds.split_once(':') else {
+                        warn!(
+                            "Ignoring ICE server {:?}: credentials must be user:pass",
+                            entry
+                        );
+                        return None;
+                    };
+                    Some(RTCIceServer {
+                        urls: vec![format!("{}:{}", scheme, host)],
+                        username: username.to_string(),
+                        credential: credential.to_string(),
+                        credential_type: RTCIceCredentialType::Password,
+                    })
+                }
+                None => Some(RTCIceServer {
+                    urls: vec![entry.to_string()],
+                    ..Default::default()
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Check for `Authorization: Bearer <token>` using a constant-time comparison.
+fn is_bearer_authorized(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+}
+
+/// Compare two byte strings without short-circuiting on the first mismatch.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// The `ServerHello` describing what this server supports.
+fn create_hello_message(app_state: &AppState) -> CursorMessage {
+    let direction = clipboard_sync::clipboard_direction();
+    let clipboard = direction.host_to_client() || direction.client_to_host();
+    let clipboard_formats = if clipboard {
+        vec![
+            ClipboardContentType::Text,
+            ClipboardContentType::Html,
+            ClipboardContentType::Rtf,
+            ClipboardContentType::Image,
+            ClipboardContentType::ImageWebp,
+            ClipboardContentType::Files,
+        ]
+    } else {
+        Vec::new()
+    };
+    CursorMessage {
+        r#type: MessageType::ServerHello.into(),
+        payload: Some(Payload::ServerHello(HelloData {
+            schema_version: SCHEMA_VERSION,
+            agent_version: env!("CARGO_PKG_VERSION").to_string(),
+            animated_cursors: true,
+            clipboard_formats: clipboard_formats.into_iter().map(Into::into).collect(),
+            clipboard_host_to_client: direction.host_to_client(),
+            clipboard_client_to_host: direction.client_to_host(),
+            clipboard_notify_only: app_state.clipboard_notify_only,
+            file_transfer: clipboard,
+            settings: true,
+            cursor_position: true,
+            cursor_ack: true,
+            cursor_codecs: cursor_codecs()
+                .into_iter()
+                .map(|c| c.to_proto().into())
+                .collect(),
+        })),
+        timestamp: now_ms(),
+    }
+}
+
+fn create_heartbeat_message() -> CursorMessage {
+    CursorMessage {
+        r#type: MessageType::Heartbeat.into(),
+        payload: None,
+        timestamp: now_ms(),
+    }
+}
+
+fn create_signal_message(seat: SeatId, cursor_id: &str) -> CursorMessage {
+    CursorMessage {
+        r#type: MessageType::CursorSignal.into(),
+        payload: Some(Payload::CursorSignal(CursorSignal {
+            cursor_id: cursor_id.to_string(),
+            seat_id: seat,
+        })),
+        timestamp: now_ms(),
+    }
+}
+
+/// Short random id for a client's log span.  `RandomState` is seeded
+/// randomly per instance, which is enough for telling clients apart.
+fn new_session_id() -> String {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(now_ms());
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app_state() -> Arc<AppState> {
+        let (tx_broadcast, _) = broadcast::channel(DEFAULT_BROADCAST_CAPACITY);
+        Arc::new(AppState {
+            tx_broadcast: Arc::new(tx_broadcast),
+            api: APIBuilder::new().build(),
+            ice_servers: Vec::new(),
+            peer_connections: Mutex::new(HashMap::new()),
+            next_peer_id: AtomicU64::new(0),
+            last_settings: Mutex::new(None),
+            last_clipboard: Mutex::new(None),
+            clipboard_notify_only: false,
+            auto_suppress_on_draw_cursor: false,
+            buffer_high_water: DEFAULT_BUFFER_HIGH_WATER,
+            buffer_low_water: DEFAULT_BUFFER_LOW_WATER,
+            max_cursor_msgs_per_sec: DEFAULT_MAX_CURSOR_MSGS_PER_SEC,
+            cursor_events_coalesced: AtomicU64::new(0),
+            backpressure_engaged: AtomicU64::new(0),
+            broadcast_lagged: AtomicU64::new(0),
+            started_at: Instant::now(),
+            cursor_events_broadcast: AtomicU64::new(0),
+            clipboard_sent: AtomicU64::new(0),
+            clipboard_received: AtomicU64::new(0),
+            admin_token: None,
+            auth_token: None,
+            client_rtt_ms: Mutex::new(HashMap::new()),
+            trickle_sessions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    #[tokio::test]
+    async fn peer_map_shrinks_back_after_reconnects() {
+        let state = test_app_state();
+        let new_pc = || async {
+            Arc::new(
+                state
+                    .api
+                    .new_peer_connection(RTCConfiguration::default())
+                    .await
+                    .unwrap(),
+            )
+        };
+        let states = [
+            RTCPeerConnectionState::Connecting,
+            RTCPeerConnectionState::Connected,
+            RTCPeerConnectionState::Disconnected,
+            RTCPeerConnectionState::Closed,
+        ];
+
+        state
+            .peer_connections
+            .lock()
+            .await
+            .insert(0, new_pc().await);
+        for peer_id in 1..=5 {
+            let pc = new_pc().await;
+            state.peer_connections.lock().await.insert(peer_id, pc);
+            state.client_rtt_ms.lock().await.insert(peer_id, 20);
+            let mut handler = peer_state_handler(Arc::downgrade(&state), peer_id, Span::none());
+            for s in states {
+                handler(s).await;
+                let live = state.peer_connections.lock().await.contains_key(&peer_id);
+                assert_eq!(live, !peer_connection_ended(s), "peer {} {:?}", peer_id, s);
+            }
+            assert_eq!(state.peer_connections.lock().await.len(), 1);
+            assert!(state.client_rtt_ms.lock().await.is_empty());
+        }
+        assert!(state.peer_connections.lock().await.contains_key(&0));
+    }
+
+    fn changed(id: &str) -> AgentEvent {
+        AgentEvent::Cursor(CursorEvent::CursorChanged {
+            seat: DEFAULT_SEAT,
+            cursor_id: id.to_string(),
+        })
+    }
+
+    fn changed_id(event: &CursorEvent) -> Option<&str> {
+        match event {
+            CursorEvent::CursorChanged { cursor_id, .. } => Some(cursor_id),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn lagging_receiver_resyncs_to_latest_cursor() {
+        let (tx, mut rx) = broadcast::channel(2);
+        for id in ["a", "b", "c", "d", "e"] {
+            tx.send(changed(id)).unwrap();
+        }
+
+        let latest = || Some("e".to_string());
+        match recv_or_resync(&mut rx, latest).await {
+            Some(Received::Resync { missed, current }) => {
+                assert_eq!(missed, 3);
+                assert_eq!(changed_id(&current), Some("e"));
+            }
+            _ => panic!("lagged receiver was not resynced"),
+        }
+        // Then the events still buffered, ending on the latest cursor
+        for expected in ["d", "e"] {
+            match recv_or_resync(&mut rx, latest).await {
+                Some(Received::Event(AgentEvent::Cursor(ev))) => {
+                    assert_eq!(changed_id(&ev), Some(expected))
+                }
+                _ => panic!("expected cursor event {}", expected),
+            }
+        }
+
+        drop(tx);
+        assert!(recv_or_resync(&mut rx, latest).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn lagging_receiver_resyncs_to_hidden_cursor() {
+        let (tx, mut rx) = broadcast::channel(1);
+        tx.send(changed("a")).unwrap();
+        tx.send(changed("b")).unwrap();
+
+        match recv_or_resync(&mut rx, || None).await {
+            Some(Received::Resync {
+                current: CursorEvent::CursorHidden { seat },
+                ..
+            }) => {
+                assert_eq!(seat, DEFAULT_SEAT)
+            }
+            _ => panic!("expected a resync to the hidden cursor"),
+        }
+    }
+
+    #[tokio::test]
+    async fn config_change_resend_respects_suppression() {
+        let config = ClientConfig::parse(r#"{"device_pixel_ratio":2.0}"#).unwrap();
+        let mut state = ClientState::new(60);
+        apply_client_config(&mut state, &config);
+        assert_eq!(state.dpr, 2.0);
+        let resend = state.resend_cursor.clone();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), resend.notified())
+                .await
+                .is_ok()
+        );
+
+        // An unopened channel fails every send, so `Err` means a send was
+        // attempted
+        let dc = Arc::new(RTCDataChannel::default());
+        let coalesced = AtomicU64::new(0);
+        let mut throttled = HashMap::new();
+        let current = CursorEvent::current(DEFAULT_SEAT, None);
+        state.draw_cursor_suppressed = true;
+        let sent =
+            send_or_throttle(&dc, &mut state, current.clone(), &mut throttled, &coalesced).await;
+        assert_eq!(sent, Ok(None));
+        state.draw_cursor_suppressed = false;
+        let sent = send_or_throttle(&dc, &mut state, current, &mut throttled, &coalesced).await;
+        assert_eq!(sent, Err(()));
+    }
+
+    #[tokio::test]
+    async fn cursor_resend_waits_for_rate_budget() {
+        let dc = Arc::new(RTCDataChannel::default());
+        let coalesced = AtomicU64::new(0);
+        let mut throttled = HashMap::new();
+        let mut state = ClientState::new(1);
+        // Suppressed, so sends within budget succeed without a channel
+        state.draw_cursor_suppressed = true;
+        let current = CursorEvent::current(DEFAULT_SEAT, None);
+
+        let sent =
+            send_or_throttle(&dc, &mut state, current.clone(), &mut throttled, &coalesced).await;
+        assert_eq!(sent, Ok(None));
+        for _ in 0..2 {
+            let sent =
+                send_or_throttle(&dc, &mut state, current.clone(), &mut throttled, &coalesced)
+                    .await;
+            assert!(matches!(sent, Ok(Some(_))));
+        }
+        assert_eq!(throttled.len(), 1);
+        assert_eq!(coalesced.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn drain_wait_ends_when_channel_is_not_open() {
+        let dc = RTCDataChannel::default();
+        assert!(wait_for_drain(&dc, usize::MAX).await.is_err());
+    }
+
+    #[test]
+    fn transport_parses_env_values() {
+        assert_eq!(TransportMode::parse("webrtc"), Some(TransportMode::WebRtc));
+        assert_eq!(
+            TransportMode::parse(" WebTransport "),
+            Some(TransportMode::WebTransport)
+        );
+        assert_eq!(TransportMode::parse("both"), Some(TransportMode::Both));
+        assert_eq!(TransportMode::parse("quic"), None);
+        assert!(TransportMode::Both.webrtc() && TransportMode::Both.webtransport());
+        assert!(!TransportMode::WebTransport.webrtc());
+        assert!(!TransportMode::WebRtc.webtransport());
+    }
+}
@@ -0,0 +1,409 @@
+//! WebTransport (HTTP/3) transport: built with the `webtransport` feature and
+//! enabled with `TRANSPORT=webtransport` or `TRANSPORT=both`.
+//!
+//! A client opens a session to `https://<BIND_ADDR>/webtransport` (UDP, same
+//! port as the HTTP server, certificate from `TLS_CERT` / `TLS_KEY`), adding
+//! `?token=<AUTH_TOKEN>` when one is set since browsers can't send headers
+//! with it.  The server then opens one bidirectional stream that takes the
+//! place of the data channel: each message, in either direction, is a
+//! protobuf varint length followed by an encoded `CursorMessage` (protobuf's
+//! delimited format).  A client frame starting with `{` is a JSON client
+//! config instead.
+
+use std::net::SocketAddr;
+use std::path::Path;
+
+use axum::http::{Method, Request, Response};
+use h3::ext::Protocol;
+use quinn::crypto::rustls::QuicServerConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+use super::*;
+
+/// Path clients open sessions on.
+const SESSION_PATH: &str = "/webtransport";
+
+/// Largest frame accepted from a client; clipboard images are the biggest.
+const MAX_FRAME_LEN: u64 = 32 * 1024 * 1024;
+
+/// Keep-alive interval, well inside quinn's default 30 s idle timeout.
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// Stream type opening a WebTransport bidirectional stream.
+const WEBTRANSPORT_STREAM: u64 = 0x41;
+
+/// Bind the QUIC endpoint on `addr`, with the certificate chain and key
+/// from the given PEM files.
+pub(super) fn bind(addr: SocketAddr, cert: &Path, key: &Path) -> Result<quinn::Endpoint> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("load TLS certificate {:?}", cert))?;
+    let key =
+        PrivateKeyDer::from_pem_file(key).with_context(|| format!("load TLS key {:?}", key))?;
+
+    let mut tls = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_protocol_versions(&[&rustls::version::TLS13])?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)?;
+    tls.alpn_protocols = vec![b"h3".to_vec()];
+
+    let mut config = quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls)?));
+    let mut transport = quinn::TransportConfig::default();
+    transport.keep_alive_interval(Some(KEEP_ALIVE));
+    config.transport_config(Arc::new(transport));
+
+    let endpoint = quinn::Endpoint::server(config, addr)
+        .with_context(|| format!("bind WebTransport endpoint on {}", addr))?;
+    info!(
+        "WebTransport listening on: https://{}{} (UDP)",
+        addr, SESSION_PATH
+    );
+    Ok(endpoint)
+}
+
+/// Accept WebTransport sessions until shutdown.
+pub(super) async fn serve(endpoint: quinn::Endpoint, state: Arc<AppState>) {
+    loop {
+        let incoming = tokio::select! {
+            incoming = endpoint.accept() => match incoming {
+                Some(incoming) => incoming,
+                None => break,
+            },
+            _ = crate::shutdown::requested() => break,
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(incoming, state).await {
+                debug!("WebTransport connection ended: {:#}", e);
+            }
+        });
+    }
+    info!("WebTransport server stopped");
+    endpoint.close(0u32.into(), b"shutdown");
+    endpoint.wait_idle().await;
+}
+
+/// Serve one QUIC connection: wait for its session request and run the
+/// session.
+async fn handle_connection(incoming: quinn::Incoming, state: Arc<AppState>) -> Result<()> {
+    let conn = incoming.await?;
+    let mut h3_conn: h3::server::Connection<_, Bytes> = h3::server::builder()
+        .enable_webtransport(true)
+        .enable_extended_connect(true)
+        .enable_datagram(true)
+        .max_webtransport_sessions(1)
+        .send_grease(true)
+        .build(h3_quinn::Connection::new(conn.clone()))
+        .await?;
+
+    while let Some(resolver) = h3_conn.accept().await? {
+        let (req, mut stream) = resolver.resolve_request().await?;
+        if let Err(status) = check_session_request(&req, state.auth_token.as_deref()) {
+            warn!(
+                "Rejected WebTransport request {} {} from {}: {}",
+                req.method(),
+                req.uri().path(),
+                conn.remote_address(),
+                status
+            );
+            stream
+                .send_response(Response::builder().status(status).body(())?)
+                .await?;
+            stream.finish().await?;
+            continue;
+        }
+        stream
+            .send_response(
+                Response::builder()
+                    .status(StatusCode::OK)
+                    .header("sec-webtransport-http3-draft", "draft02")
+                    .body(())?,
+            )
+            .await?;
+
+        let session_id = stream.id().into_inner();
+        let (mut send, recv) = conn.open_bi().await?;
+        send.write_all(&stream_header(session_id)).await?;
+        let channel = Arc::new(SessionStream {
+            send: Mutex::new(send),
+            conn: conn.clone(),
+        });
+
+        // The client ends the session by finishing its CONNECT stream.
+        let session_closed = async { while let Ok(Some(_)) = stream.recv_data().await {} };
+        let result = run_session(channel, recv, session_closed, &state).await;
+        conn.close(0u32.into(), b"");
+        return result;
+    }
+    Ok(())
+}
+
+/// Status to refuse a session request with, if any.
+fn check_session_request(req: &Request<()>, auth_token: Option<&str>) -> Result<(), StatusCode> {
+    if req.method() != Method::CONNECT
+        || req.extensions().get::<Protocol>() != Some(&Protocol::WEB_TRANSPORT)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if req.uri().path() != SESSION_PATH {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if let Some(token) = auth_token {
+        let query_token = req
+            .uri()
+            .query()
+            .and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("token=")));
+        let authorized = query_token
+            .is_some_and(|t| constant_time_eq(t.as_bytes(), token.as_bytes()))
+            || is_bearer_authorized(req.headers(), token);
+        if !authorized {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    }
+    Ok(())
+}
+
+/// Run an accepted session: the sender task as for a WebRTC client, and the
+/// client's messages until its side of the stream ends, `session_closed`
+/// completes or shutdown.
+async fn run_session(
+    channel: Arc<SessionStream>,
+    recv: quinn::RecvStream,
+    session_closed: impl Future<Output = ()>,
+    state: &Arc<AppState>,
+) -> Result<()> {
+    let peer_id = state.next_peer_id.fetch_add(1, Ordering::Relaxed);
+    let span = info_span!("client", id = %new_session_id());
+    info!(
+        parent: &span,
+        "New WebTransport session from {} (peer {})",
+        channel.conn.remote_address(),
+        peer_id
+    );
+
+    let client_state = Arc::new(Mutex::new(ClientState::new(state.max_cursor_msgs_per_sec)));
+    let channels = Arc::new(Mutex::new(PeerChannels::default()));
+    // Writes wait for QUIC flow control, so the sender never pauses and
+    // `drained` is never notified.
+    let sender = tokio::spawn(
+        run_cursor_sender(
+            channel.clone(),
+            state.clone(),
+            client_state.clone(),
+            channels.clone(),
+            state.tx_broadcast.subscribe(),
+            Arc::new(Notify::new()),
+        )
+        .instrument(span.clone()),
+    );
+
+    let mut reader = BufReader::new(recv);
+    let messages = async {
+        while let Some(data) = read_frame(&mut reader).await? {
+            let msg = DataChannelMessage {
+                is_string: data.first() == Some(&b'{'),
+                data,
+            };
+            let handled =
+                handle_client_message(&channel, state, peer_id, &client_state, &channels, msg)
+                    .await;
+            if handled.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+    .instrument(span.clone());
+    let result = tokio::select! {
+        result = messages => result,
+        _ = session_closed => Ok(()),
+        _ = crate::shutdown::requested() => Ok(()),
+    };
+
+    sender.abort();
+    state.client_rtt_ms.lock().await.remove(&peer_id);
+    info!(parent: &span, "WebTransport session {} closed", peer_id);
+    result
+}
+
+/// Server end of a session's message stream.
+struct SessionStream {
+    send: Mutex<quinn::SendStream>,
+    conn: quinn::Connection,
+}
+
+impl ClientChannel for SessionStream {
+    async fn send(&self, data: &Bytes) -> Result<()> {
+        self.send
+            .lock()
+            .await
+            .write_all(&encode_frame(data))
+            .await?;
+        Ok(())
+    }
+
+    async fn buffered_amount(&self) -> usize {
+        // Nothing queues here: writes wait for QUIC flow control instead.
+        0
+    }
+
+    fn is_open(&self) -> bool {
+        self.conn.close_reason().is_none()
+    }
+}
+
+/// Start of a server-opened WebTransport stream: its type and the session it
+/// belongs to, as QUIC varints.
+fn stream_header(session_id: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_quic_varint(&mut buf, WEBTRANSPORT_STREAM);
+    put_quic_varint(&mut buf, session_id);
+    buf
+}
+
+/// Append `v` as a QUIC variable-length integer (RFC 9000, section 16).
+fn put_quic_varint(buf: &mut Vec<u8>, v: u64) {
+    if v < 1 << 6 {
+        buf.push(v as u8);
+    } else if v < 1 << 14 {
+        buf.extend_from_slice(&(v as u16 | 0x4000).to_be_bytes());
+    } else if v < 1 << 30 {
+        buf.extend_from_slice(&(v as u32 | 0x8000_0000).to_be_bytes());
+    } else {
+        buf.extend_from_slice(&(v | 0xC000_0000_0000_0000).to_be_bytes());
+    }
+}
+
+/// `data` as a frame: its length as a protobuf varint, then the bytes.
+fn encode_frame(data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(data.len() + 5);
+    prost::encoding::encode_varint(data.len() as u64, &mut frame);
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// Read one frame, or `None` if the stream ends cleanly before it.
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Bytes>> {
+    let mut len = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if shift == 0 && e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        len |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+        if shift > 28 {
+            bail!("frame length too long");
+        }
+    }
+    if len > MAX_FRAME_LEN {
+        bail!(
+            "{} byte frame exceeds the {} byte limit",
+            len,
+            MAX_FRAME_LEN
+        );
+    }
+    let mut data = vec![0; len as usize];
+    reader.read_exact(&mut data).await?;
+    Ok(Some(Bytes::from(data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quic_varints_match_rfc_examples() {
+        for (v, encoded) in [
+            (37, &[0x25][..]),
+            (15293, &[0x7B, 0xBD]),
+            (494878333, &[0x9D, 0x7F, 0x3E, 0x7D]),
+            (
+                151288809941952652,
+                &[0xC2, 0x19, 0x7C, 0x5E, 0xFF, 0x14, 0xE8, 0x8C],
+            ),
+        ] {
+            let mut buf = Vec::new();
+            put_quic_varint(&mut buf, v);
+            assert_eq!(buf, encoded, "{}", v);
+        }
+        assert_eq!(stream_header(4), [0x40, 0x41, 0x04]);
+    }
+
+    #[tokio::test]
+    async fn frames_round_trip() {
+        let messages = [&b"{\"device_pixel_ratio\":2}"[..], &[], &[0x08; 300]];
+        let stream: Vec<u8> = messages.iter().flat_map(|m| encode_frame(m)).collect();
+
+        let mut reader = stream.as_slice();
+        for expected in messages {
+            let frame = read_frame(&mut reader).await.unwrap();
+            assert_eq!(frame.as_deref(), Some(expected));
+        }
+        assert!(read_frame(&mut reader).await.unwrap().is_none());
+
+        let truncated = &stream[..stream.len() - 1];
+        let mut reader = &truncated[encode_frame(messages[0]).len() + 1..];
+        assert!(read_frame(&mut reader).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn oversized_frame_rejected() {
+        let mut header = Vec::new();
+        prost::encoding::encode_varint(MAX_FRAME_LEN + 1, &mut header);
+        assert!(read_frame(&mut header.as_slice()).await.is_err());
+    }
+
+    fn session_request(uri: &str) -> Request<()> {
+        Request::builder()
+            .method(Method::CONNECT)
+            .uri(uri)
+            .extension(Protocol::WEB_TRANSPORT)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn session_requests_are_checked() {
+        let open = Some("s3cret");
+        let check = |req: &Request<()>, token| check_session_request(req, token);
+
+        assert_eq!(
+            check(&session_request("https://h/webtransport"), None),
+            Ok(())
+        );
+        assert_eq!(
+            check(
+                &session_request("https://h/webtransport?x=1&token=s3cret"),
+                open
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check(&session_request("https://h/webtransport"), open),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check(&session_request("https://h/webtransport?token=nope"), open),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check(&session_request("https://h/other"), None),
+            Err(StatusCode::NOT_FOUND)
+        );
+
+        let plain_get = Request::get("https://h/webtransport").body(()).unwrap();
+        assert_eq!(check(&plain_get, None), Err(StatusCode::BAD_REQUEST));
+    }
+}